@@ -1,4 +1,4 @@
-use std::io::Read;
+use std::io::{self, Read};
 
 use byteorder::{ReadBytesExt, BE};
 use paste::paste;
@@ -7,6 +7,10 @@ use uuid::Uuid;
 
 use crate::{DecodeError, MAX_STRING_LENGTH};
 
+/// The maximum number of bytes that will be allocated up front for a length read from the wire.
+/// Anything larger will grow as the data actually arrives, so a bogus length can't exhaust memory.
+pub const MAX_PREALLOCATION: usize = 64 * 1024;
+
 pub trait Decode: Sized {
     fn decode(reader: &mut impl Read) -> Result<Self, DecodeError>;
 }
@@ -15,6 +19,16 @@ pub trait VarDecode: Sized {
     fn var_decode(reader: &mut impl Read) -> Result<Self, DecodeError>;
 }
 
+/// Decoding with a maximum length, used by fields marked with `#[buf(max_len = N)]`.
+/// `field` is the name of the field, used for the error.
+pub trait DecodeBounded: Sized {
+    fn decode_bounded(
+        reader: &mut impl Read,
+        max_length: usize,
+        field: &'static str,
+    ) -> Result<Self, DecodeError>;
+}
+
 pub trait DecodeExt {
     fn decode<T: Decode>(&mut self) -> Result<T, DecodeError>;
     fn var_decode<T: VarDecode>(&mut self) -> Result<T, DecodeError>;
@@ -47,6 +61,59 @@ pub fn decode_string(reader: &mut impl Read, max_length: usize) -> Result<String
     Ok(String::from_utf8(bytes).map_err(|_| DecodeError::InvalidUtf8)?)
 }
 
+/// A reader that counts the bytes read through it.
+/// Used by the derived [`Decode`] to know where each field starts.
+pub struct CountingReader<R> {
+    inner: R,
+    count: usize,
+}
+
+impl<R: Read> CountingReader<R> {
+    pub const fn new(inner: R) -> CountingReader<R> {
+        CountingReader { inner, count: 0 }
+    }
+
+    /// The number of bytes read so far.
+    pub const fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n: usize = self.inner.read(buf)?;
+        self.count += n;
+        Ok(n)
+    }
+}
+
+/// Reads `length` bytes without trusting `length` for the allocation.
+pub fn read_bytes(reader: &mut impl Read, length: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut bytes: Vec<u8> = Vec::with_capacity(length.min(MAX_PREALLOCATION));
+    reader.take(length as u64).read_to_end(&mut bytes)?;
+
+    if bytes.len() != length {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+    }
+
+    Ok(bytes)
+}
+
+/// Decodes `length` items with `decode` without trusting `length` for the allocation.
+pub fn decode_array<R: Read, T>(
+    reader: &mut R,
+    length: usize,
+    mut decode: impl FnMut(&mut R) -> Result<T, DecodeError>,
+) -> Result<Vec<T>, DecodeError> {
+    let mut buf: Vec<T> = Vec::with_capacity(length.min(MAX_PREALLOCATION / size_of::<T>().max(1)));
+
+    for _ in 0..length {
+        buf.push(decode(reader)?);
+    }
+
+    Ok(buf)
+}
+
 macro_rules! impl_decode {
     ($($type:ty),*) => {
         paste! {
@@ -149,12 +216,61 @@ impl Decode for Uuid {
     }
 }
 
+impl DecodeBounded for String {
+    /// `max_length` is in characters, counted the way Java does (UTF-16 code units).
+    fn decode_bounded(
+        reader: &mut impl Read,
+        max_length: usize,
+        field: &'static str,
+    ) -> Result<Self, DecodeError> {
+        let string: String = decode_string(reader, max_length).map_err(|e| match e {
+            DecodeError::InvalidStringLength(length) => DecodeError::LengthLimitExceeded {
+                field,
+                length,
+                max_length,
+            },
+            e => e,
+        })?;
+
+        let length: usize = string.encode_utf16().count();
+        if length > max_length {
+            return Err(DecodeError::LengthLimitExceeded {
+                field,
+                length,
+                max_length,
+            });
+        }
+
+        Ok(string)
+    }
+}
+
 impl<T: Decode> Decode for Vec<T> {
     /// Will use `VarReadable` for the length and `Readable` for the elements.
     fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
-        Ok((0..usize::var_decode(reader)?)
-            .map(|_| T::decode(reader))
-            .collect::<Result<_, _>>()?)
+        let length: usize = usize::var_decode(reader)?;
+        decode_array(reader, length, T::decode)
+    }
+}
+
+impl<T: Decode> DecodeBounded for Vec<T> {
+    /// `max_length` is the number of elements.
+    fn decode_bounded(
+        reader: &mut impl Read,
+        max_length: usize,
+        field: &'static str,
+    ) -> Result<Self, DecodeError> {
+        let length: usize = usize::var_decode(reader)?;
+
+        if length > max_length {
+            return Err(DecodeError::LengthLimitExceeded {
+                field,
+                length,
+                max_length,
+            });
+        }
+
+        decode_array(reader, length, T::decode)
     }
 }
 
@@ -166,3 +282,44 @@ impl<T: Decode> Decode for Option<T> {
             .transpose()?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untrusted_length() {
+        // The length claims 4 GiB of elements, but the data ends right away
+        let buf: &[u8] = &[0xFF, 0xFF, 0xFF, 0xFF, 0x0F, 1, 2, 3];
+        assert!(matches!(
+            Vec::<u64>::decode(&mut &buf[..]),
+            Err(DecodeError::Io(_))
+        ));
+        assert!(matches!(
+            read_bytes(&mut &buf[5..], usize::MAX),
+            Err(DecodeError::Io(_))
+        ));
+        assert_eq!(read_bytes(&mut &buf[5..], 3).unwrap(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_decode_bounded() {
+        let buf: &[u8] = b"\x06\xC3\xA9t\xC3\xA9!";
+        assert_eq!(
+            String::decode_bounded(&mut &buf[..], 4, "s").unwrap(),
+            "été!"
+        );
+        assert!(matches!(
+            String::decode_bounded(&mut &buf[..], 3, "s"),
+            Err(DecodeError::LengthLimitExceeded {
+                field: "s",
+                length: 4,
+                max_length: 3,
+            })
+        ));
+        assert!(matches!(
+            Vec::<u8>::decode_bounded(&mut &[3, 1, 2, 3][..], 2, "v"),
+            Err(DecodeError::LengthLimitExceeded { length: 3, .. })
+        ));
+    }
+}
@@ -42,6 +42,11 @@ macro_rules! impl_encode {
 }
 impl_encode!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
 
+/// Returns the number of bytes `value` takes up when encoded as a VarInt.
+pub fn var_int_len(value: u32) -> usize {
+    (32 - (value | 1).leading_zeros() as usize).div_ceil(7)
+}
+
 impl Encode for bool {
     fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
         writer.write_u8(*self as u8)
@@ -0,0 +1,164 @@
+//! Length-prefixed frames.
+//!
+//! Every type implements its codec once, with [`Decode`] and [`Encode`].
+//! Since everything on the wire is framed, async readers and writers are supported by buffering a whole frame
+//! and running the blocking codec on the buffer, which can never block.
+
+use std::io::{self, Read, Write};
+
+#[cfg(feature = "async")]
+use futures_lite::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{Decode, DecodeError, Encode, VarDecode, VarEncode};
+
+/// Reads a frame prefixed with its VarInt length.
+///
+/// `max_length` is in bytes and does not include the length prefix.
+pub fn read_frame(reader: &mut impl Read, max_length: usize) -> Result<Vec<u8>, DecodeError> {
+    let length: usize = usize::var_decode(reader)?;
+
+    if length > max_length {
+        return Err(DecodeError::InvalidFrameLength(length));
+    }
+
+    let mut frame: Vec<u8> = vec![0; length];
+    reader.read_exact(&mut frame)?;
+
+    Ok(frame)
+}
+
+/// Writes a frame prefixed with its VarInt length.
+pub fn write_frame(writer: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    frame.len().var_encode(writer)?;
+    writer.write_all(frame)
+}
+
+/// Decodes a value from a whole frame.
+/// It is an error for the value not to consume the entire frame.
+pub fn decode_frame<T: Decode>(frame: &[u8]) -> Result<T, DecodeError> {
+    let mut reader: &[u8] = frame;
+    let value: T = T::decode(&mut reader)?;
+
+    if !reader.is_empty() {
+        return Err(DecodeError::TrailingBytes(reader.len()));
+    }
+
+    Ok(value)
+}
+
+/// Encodes a value into a new frame, without the length prefix.
+pub fn encode_frame<T: Encode + ?Sized>(value: &T) -> io::Result<Vec<u8>> {
+    let mut frame: Vec<u8> = Vec::new();
+    value.encode(&mut frame)?;
+    Ok(frame)
+}
+
+/// Reads a frame prefixed with its VarInt length from an async reader.
+///
+/// `max_length` is in bytes and does not include the length prefix.
+#[cfg(feature = "async")]
+pub async fn async_read_frame(
+    reader: &mut (impl AsyncReadExt + Unpin),
+    max_length: usize,
+) -> Result<Vec<u8>, DecodeError> {
+    // A VarInt is at most 5 bytes long, so read it byte by byte and then decode it from the buffer.
+    let mut length_buf: Vec<u8> = Vec::with_capacity(5);
+    loop {
+        let mut byte: [u8; 1] = [0];
+        reader.read_exact(&mut byte).await?;
+        length_buf.push(byte[0]);
+
+        if byte[0] & 0x80 == 0 || length_buf.len() == 5 {
+            break;
+        }
+    }
+    let length: usize = usize::var_decode(&mut &length_buf[..])?;
+
+    if length > max_length {
+        return Err(DecodeError::InvalidFrameLength(length));
+    }
+
+    let mut frame: Vec<u8> = vec![0; length];
+    reader.read_exact(&mut frame).await?;
+
+    Ok(frame)
+}
+
+/// Writes a frame prefixed with its VarInt length to an async writer.
+#[cfg(feature = "async")]
+pub async fn async_write_frame(
+    writer: &mut (impl AsyncWriteExt + Unpin),
+    frame: &[u8],
+) -> io::Result<()> {
+    let mut buf: Vec<u8> = Vec::with_capacity(frame.len() + 5);
+    write_frame(&mut buf, frame)?;
+    writer.write_all(&buf).await
+}
+
+/// Reads a frame from an async reader and decodes a value from it.
+#[cfg(feature = "async")]
+pub async fn async_decode_frame<T: Decode>(
+    reader: &mut (impl AsyncReadExt + Unpin),
+    max_length: usize,
+) -> Result<T, DecodeError> {
+    decode_frame(&async_read_frame(reader, max_length).await?)
+}
+
+/// Encodes a value and writes it as a frame to an async writer.
+#[cfg(feature = "async")]
+pub async fn async_encode_frame<T: Encode + ?Sized>(
+    writer: &mut (impl AsyncWriteExt + Unpin),
+    value: &T,
+) -> io::Result<()> {
+    async_write_frame(writer, &encode_frame(value)?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_frame(&mut buf, &encode_frame("hello").unwrap()).unwrap();
+        assert_eq!(buf, b"\x06\x05hello");
+
+        let frame: Vec<u8> = read_frame(&mut &buf[..], 6).unwrap();
+        assert_eq!(decode_frame::<String>(&frame).unwrap(), "hello");
+
+        assert!(matches!(
+            read_frame(&mut &buf[..], 5),
+            Err(DecodeError::InvalidFrameLength(6))
+        ));
+        assert!(matches!(
+            decode_frame::<u32>(&frame),
+            Err(DecodeError::TrailingBytes(2))
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_frame() {
+        futures_lite::future::block_on(async {
+            let value: Vec<u64> = (0..100).collect();
+
+            let mut buf: Vec<u8> = Vec::new();
+            async_encode_frame(&mut buf, &value[..]).await.unwrap();
+            assert_eq!(buf[..2], [0xA1, 0x06]);
+
+            let mut reader: &[u8] = &buf;
+            assert_eq!(
+                async_decode_frame::<Vec<u64>>(&mut reader, 1024)
+                    .await
+                    .unwrap(),
+                value
+            );
+            assert!(reader.is_empty());
+
+            assert!(matches!(
+                async_read_frame(&mut &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF][..], 1024).await,
+                Err(DecodeError::InvalidVarInt)
+            ));
+        });
+    }
+}
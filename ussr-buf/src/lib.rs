@@ -1,27 +1,17 @@
-#[cfg(feature = "async")]
-pub mod async_decode;
-#[cfg(feature = "async")]
-pub mod async_encode;
 pub mod decode;
 pub mod encode;
+pub mod frame;
 
 use std::io;
 
 use thiserror::Error;
 use ussr_nbt::NbtDecodeError;
 
-#[cfg(feature = "async")]
-pub use async_decode::{
-    decode_string as async_decode_string, Decode as AsyncDecode, DecodeExt as AsyncDecodeExt,
-    VarDecode as AsyncVarDecode,
-};
-#[cfg(feature = "async")]
-pub use async_encode::{
-    Encode as AsyncEncode, EncodeExt as AsyncEncodeExt, VarEncode as AsyncVarEncode,
-};
 pub use decode::*;
 pub use encode::*;
-#[cfg(all(feature = "derive", feature = "async"))]
+pub use frame::*;
+#[cfg(feature = "derive")]
+#[allow(deprecated)]
 pub use ussr_buf_derive::{AsyncDecode, AsyncEncode};
 #[cfg(feature = "derive")]
 pub use ussr_buf_derive::{Decode, Encode};
@@ -34,23 +24,110 @@ pub enum DecodeError {
     #[error(transparent)]
     Io(#[from] io::Error),
 
-    #[error("Invalid VarInt")]
+    #[error("invalid VarInt")]
     InvalidVarInt,
 
-    #[error("Invalid VarLong")]
+    #[error("invalid VarLong")]
     InvalidVarLong,
 
-    #[error("Invalid UTF-8")]
+    #[error("invalid UTF-8")]
     InvalidUtf8,
 
-    #[error("Invalid string length")]
+    #[error("invalid string length {0}")]
     InvalidStringLength(usize),
 
-    #[error("Invalid enum variant")]
-    InvalidEnumVariant,
+    #[error("invalid array length")]
+    InvalidArrayLength,
+
+    #[error("length {length} exceeds the maximum of {max_length}")]
+    LengthLimitExceeded {
+        field: &'static str,
+        length: usize,
+        max_length: usize,
+    },
+
+    #[error("invalid frame length {0}")]
+    InvalidFrameLength(usize),
+
+    #[error("{0} trailing bytes")]
+    TrailingBytes(usize),
+
+    #[error("invalid enum variant {0}")]
+    InvalidEnumVariant(i64),
+
+    #[error("invalid JSON: {0}")]
+    InvalidJson(String),
 
-    #[error("Error reading NBT: {0}")]
+    #[error("error reading NBT: {0}")]
     Nbt(NbtDecodeError),
+
+    /// An error in a field of a struct, added by the derived [`Decode`].
+    /// `fields` is the path from `ty` to the field that failed, and `offset` is where that field starts relative to `ty`.
+    #[error("{ty}.{}: {source}", .fields.join("."))]
+    InField {
+        ty: &'static str,
+        fields: Vec<&'static str>,
+        offset: usize,
+        source: Box<DecodeError>,
+    },
+}
+
+impl DecodeError {
+    /// Wraps the error with the field it occurred in.
+    /// `offset` is where the field starts relative to the start of `ty`.
+    ///
+    /// If the error is already in a field, the paths and offsets are joined.
+    pub fn in_field(self, ty: &'static str, field: &'static str, offset: usize) -> DecodeError {
+        match self {
+            DecodeError::InField {
+                fields: inner_fields,
+                offset: inner_offset,
+                source,
+                ..
+            } => {
+                let mut fields: Vec<&'static str> = Vec::with_capacity(inner_fields.len() + 1);
+                fields.push(field);
+                fields.extend(inner_fields);
+
+                DecodeError::InField {
+                    ty,
+                    fields,
+                    offset: offset + inner_offset,
+                    source,
+                }
+            }
+            e => DecodeError::InField {
+                ty,
+                fields: vec![field],
+                offset,
+                source: Box::new(e),
+            },
+        }
+    }
+
+    /// The path of the field that failed, e.g. `Handshake.next_state`.
+    pub fn path(&self) -> Option<String> {
+        match self {
+            DecodeError::InField { ty, fields, .. } => Some(format!("{ty}.{}", fields.join("."))),
+            _ => None,
+        }
+    }
+
+    /// Where the field that failed starts, relative to the outermost struct.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            DecodeError::InField { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// The underlying error, without the field context.
+    pub fn cause(&self) -> &DecodeError {
+        match self {
+            DecodeError::InField { source, .. } => source.cause(),
+            e => e,
+        }
+    }
 }
 
 impl From<NbtDecodeError> for DecodeError {
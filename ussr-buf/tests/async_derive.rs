@@ -0,0 +1,37 @@
+//! The async derives are kept for compatibility, on top of the unified ones.
+#![cfg(feature = "derive")]
+#![allow(deprecated)]
+
+use ussr_buf::{AsyncDecode, AsyncEncode, Decode, Encode};
+
+#[derive(Debug, PartialEq, Decode, Encode, AsyncDecode, AsyncEncode)]
+struct Handshake {
+    #[var]
+    protocol_version: i32,
+    #[buf(max_len = 255)]
+    server_address: String,
+}
+
+#[derive(Debug, PartialEq, Decode, Encode, AsyncDecode, AsyncEncode)]
+struct KeepAlive {
+    id: i32,
+}
+
+#[test]
+fn test_async_derive() {
+    let handshake = Handshake {
+        protocol_version: 47,
+        server_address: "localhost".to_owned(),
+    };
+    let mut buf: Vec<u8> = Vec::new();
+    handshake.encode(&mut buf).unwrap();
+    KeepAlive { id: 42 }.encode(&mut buf).unwrap();
+
+    let mut reader: &[u8] = &buf;
+    assert_eq!(Handshake::decode(&mut reader).unwrap(), handshake);
+    assert_eq!(
+        KeepAlive::decode(&mut reader).unwrap(),
+        KeepAlive { id: 42 }
+    );
+    assert!(reader.is_empty());
+}
@@ -27,17 +27,37 @@ pub(crate) fn wrap_result(
     }
 }
 
-pub(crate) fn get_field_info(
-    fields: &FieldsNamed,
-) -> Result<
-    Vec<(
-        &Ident,
-        &Type,
-        bool,
-        Option<(bool, Expr, Expr)>,
-        Option<(Expr, Expr, Expr, Expr)>,
-    )>,
-> {
+/// Fails to compile if the type doesn't implement `ussr_buf::<trait_name>`.
+pub(crate) fn assert_implemented(input: &DeriveInput, trait_name: &str) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let trait_name: Path = parse_str(trait_name).unwrap();
+
+    quote! {
+        const _: () = {
+            fn assert_implemented #impl_generics () #where_clause {
+                fn implemented<T: ussr_buf::#trait_name + ?Sized>() {}
+                implemented::<#name #ty_generics>();
+            }
+        };
+    }
+}
+
+/// Everything the field attributes say about a field.
+pub(crate) struct FieldInfo<'a> {
+    pub(crate) name: &'a Ident,
+    pub(crate) ty: &'a Type,
+    /// `#[var]`
+    pub(crate) var: bool,
+    /// `#[array(var?, length_ty, item_ty)]`
+    pub(crate) array: Option<(bool, Expr, Expr)>,
+    /// `#[with(decode, encode)]`
+    pub(crate) with: Option<(Expr, Expr)>,
+    /// `#[buf(max_len = N)]`
+    pub(crate) max_len: Option<Expr>,
+}
+
+pub(crate) fn get_field_info(fields: &FieldsNamed) -> Result<Vec<FieldInfo<'_>>> {
     fields
         .named
         .iter()
@@ -45,6 +65,7 @@ pub(crate) fn get_field_info(
             let mut var = false;
             let mut array = None;
             let mut with = None;
+            let mut max_len = None;
 
             for attr in &f.attrs {
                 if attr.path().is_ident("var") {
@@ -125,26 +146,56 @@ pub(crate) fn get_field_info(
                             let exprs: Punctuated<Expr, Token![,]> =
                                 list.parse_args_with(Punctuated::parse_terminated)?;
 
-                            if exprs.len() != 4 {
+                            // The async functions are accepted for compatibility, but are no longer used.
+                            if exprs.len() != 2 && exprs.len() != 4 {
                                 return Err(Error::new_spanned(
                                     attr,
-                                    "the #[with] attribute must have exactly four arguments",
+                                    "the #[with] attribute must have exactly two or four arguments",
                                 ));
                             }
 
-                            with = Some((exprs[0].clone(), exprs[1].clone(), exprs[2].clone(), exprs[3].clone()));
+                            with = Some((exprs[0].clone(), exprs[1].clone()));
                         }
                         _ => {
                             return Err(Error::new_spanned(
                                 attr,
-                                "the #[with] attribute must have exactly four arguments",
+                                "the #[with] attribute must have exactly two or four arguments",
                             ));
                         }
                     }
+                } else if attr.path().is_ident("buf") {
+                    attr.parse_nested_meta(|meta| {
+                        if meta.path.is_ident("max_len") {
+                            if max_len.is_some() {
+                                return Err(meta.error("duplicate `max_len`"));
+                            }
+
+                            max_len = Some(meta.value()?.parse::<Expr>()?);
+                            Ok(())
+                        } else {
+                            Err(meta.error("unknown #[buf] argument"))
+                        }
+                    })?;
+                }
+            }
+
+            if let Some(max_len) = &max_len {
+                if (var && array.is_none()) || with.is_some() {
+                    return Err(Error::new_spanned(
+                        max_len,
+                        "`max_len` can only be used with strings and arrays",
+                    ));
                 }
             }
 
-            Ok((f.ident.as_ref().unwrap(), &f.ty, var, array, with))
+            Ok(FieldInfo {
+                name: f.ident.as_ref().unwrap(),
+                ty: &f.ty,
+                var,
+                array,
+                with,
+                max_len,
+            })
         })
         .collect::<Result<Vec<_>>>()
 }
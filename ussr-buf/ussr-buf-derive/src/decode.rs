@@ -1,15 +1,10 @@
 use proc_macro2::TokenStream;
-use quote::{quote, quote_spanned};
+use quote::{quote, quote_spanned, ToTokens};
 use syn::{spanned::Spanned, Data, DeriveInput, Error, Fields, Result};
 
-use crate::common::get_field_info;
-
-pub(crate) fn try_derive_decode(input: &DeriveInput, is_async: bool) -> Result<TokenStream> {
-    let module = is_async
-        .then_some(quote! {async_decode})
-        .unwrap_or(quote! {decode});
-    let dot_await = is_async.then_some(quote! {.await}).unwrap_or(quote! {});
+use crate::common::{get_field_info, FieldInfo};
 
+pub(crate) fn try_derive_decode(input: &DeriveInput) -> Result<TokenStream> {
     let Data::Struct(data) = &input.data else {
         return Err(Error::new_spanned(
             input,
@@ -23,74 +18,108 @@ pub(crate) fn try_derive_decode(input: &DeriveInput, is_async: bool) -> Result<T
         ));
     };
     let fields = get_field_info(fields)?;
-    let names = fields.iter().map(|(name, _, _, _, _)| name);
+    let names = fields.iter().map(|field| field.name);
+    let struct_name = &input.ident;
 
-    let decode = fields.iter().map(|(name, ty, var, array, with)| {
-        if let Some((length_var, length_ty, item_ty)) = array {
-            let length = if *length_var {
-                quote_spanned! {length_ty.span()=>
-                    <#length_ty as ussr_buf:: #module ::VarDecode>::var_decode
-                }
-            } else {
-                quote_spanned! {length_ty.span()=>
-                    <#length_ty as ussr_buf:: #module ::Decode>::decode
-                }
-            };
-
-            let item = if *var {
-                quote_spanned! {item_ty.span()=>
-                    <#item_ty as ussr_buf:: #module ::VarDecode>::var_decode
-                }
-            } else {
-                quote_spanned! {item_ty.span()=>
-                    <#item_ty as ussr_buf:: #module ::Decode>::decode
-                }
-            };
+    let decode = fields.iter().map(
+        |FieldInfo {
+             name,
+             ty,
+             var,
+             array,
+             with,
+             max_len,
+         }| {
+            let decode = if let Some((length_var, length_ty, item_ty)) = array {
+                let length = if *length_var {
+                    quote_spanned! {length_ty.span()=>
+                        <#length_ty as ussr_buf::decode::VarDecode>::var_decode
+                    }
+                } else {
+                    quote_spanned! {length_ty.span()=>
+                        <#length_ty as ussr_buf::decode::Decode>::decode
+                    }
+                };
 
-            let decode_array = is_async
-                .then_some(quote! {{
-                    let length: usize = #length (reader)
-                        .await?
-                        .try_into()
-                        .expect("Could not convert to usize");
-                    let mut buf = Vec::with_capacity(length);
+                let item = if *var {
+                    quote_spanned! {item_ty.span()=>
+                        <#item_ty as ussr_buf::decode::VarDecode>::var_decode
+                    }
+                } else {
+                    quote_spanned! {item_ty.span()=>
+                        <#item_ty as ussr_buf::decode::Decode>::decode
+                    }
+                };
 
-                    for _ in 0..length {
-                        buf.push( #item (reader).await?);
+                let check_length = max_len.as_ref().map(|max_len| {
+                    quote_spanned! {max_len.span()=>
+                        if length > #max_len {
+                            return Err(ussr_buf::DecodeError::LengthLimitExceeded {
+                                field: stringify!(#name),
+                                length,
+                                max_length: #max_len,
+                            });
+                        }
                     }
+                });
 
-                    buf
-                }})
-                .unwrap_or(quote! {{
-                    let length: usize = #length (reader)?.try_into().expect("Could not convert to usize");
-                    (0..length).map(|_| #item (reader)).collect::<Result<_, _>>()?
-                }});
+                // Byte arrays are read in one go instead of byte by byte
+                let read_items = if !*var && item_ty.to_token_stream().to_string() == "u8" {
+                    quote! { ussr_buf::decode::read_bytes(reader, length) }
+                } else {
+                    quote! { ussr_buf::decode::decode_array(reader, length, #item) }
+                };
 
-            quote! {
-                let #name: #ty = #decode_array ;
-            }
-        } else if *var {
-            quote_spanned! {name.span()=>
-                let #name: #ty = <#ty as ussr_buf:: #module ::VarDecode>::var_decode(reader) #dot_await ?;
-            }
-        } else if let Some((read_with, _, async_read_with, _)) = with {
-            if is_async {
+                quote! {
+                    (|| -> std::result::Result<#ty, ussr_buf::DecodeError> {
+                        let length: usize = #length (reader)?
+                            .try_into()
+                            .map_err(|_| ussr_buf::DecodeError::InvalidArrayLength)?;
+                        #check_length
+                        #read_items
+                    })()
+                }
+            } else if *var {
                 quote_spanned! {name.span()=>
-                    let #name: #ty = #async_read_with (reader).await?;
+                    <#ty as ussr_buf::decode::VarDecode>::var_decode(reader)
+                }
+            } else if let Some((read_with, _)) = with {
+                quote_spanned! {name.span()=>
+                    #read_with (reader)
+                }
+            } else if let Some(max_len) = max_len {
+                quote_spanned! {name.span()=>
+                    <#ty as ussr_buf::decode::DecodeBounded>::decode_bounded(
+                        reader,
+                        #max_len,
+                        stringify!(#name),
+                    )
                 }
             } else {
                 quote_spanned! {name.span()=>
-                    let #name: #ty = #read_with (reader)?;
+                    <#ty as ussr_buf::decode::Decode>::decode(reader)
                 }
+            };
+
+            quote! {
+                let offset: usize = reader.count();
+                let #name: #ty = #decode.map_err(|e| {
+                    e.in_field(stringify!(#struct_name), stringify!(#name), offset)
+                })?;
             }
-        } else {
-            quote_spanned! {name.span()=>
-                let #name: #ty = <#ty as ussr_buf:: #module ::Decode>::decode(reader) #dot_await ?;
-            }
+        },
+    );
+
+    // Count the bytes read, so that errors know where the field that failed starts
+    let counting_reader = (!fields.is_empty()).then(|| {
+        quote! {
+            let mut reader = ussr_buf::decode::CountingReader::new(reader);
+            let reader = &mut reader;
         }
     });
 
     Ok(quote! {
+        #counting_reader
         #(#decode)*
         Ok(Self {
             #(#names),*
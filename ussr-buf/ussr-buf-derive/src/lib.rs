@@ -5,15 +5,15 @@ mod encode;
 use proc_macro::TokenStream;
 use syn::{parse_macro_input, DeriveInput};
 
-use common::wrap_result;
+use common::{assert_implemented, wrap_result};
 use decode::try_derive_decode;
 use encode::try_derive_encode;
 
-#[proc_macro_derive(Decode, attributes(var, array, with, ignore))]
+#[proc_macro_derive(Decode, attributes(var, array, with, buf, ignore))]
 pub fn derive_decode(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     wrap_result(
-        try_derive_decode(&input, false),
+        try_derive_decode(&input),
         &input,
         "decode::Decode",
         "fn decode(reader: &mut impl std::io::Read) -> std::result::Result<Self, ussr_buf::DecodeError>",
@@ -21,11 +21,11 @@ pub fn derive_decode(input: TokenStream) -> TokenStream {
     .into()
 }
 
-#[proc_macro_derive(Encode, attributes(var, array, with, ignore))]
+#[proc_macro_derive(Encode, attributes(var, array, with, buf, ignore))]
 pub fn derive_encode(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     wrap_result(
-        try_derive_encode(&input, false),
+        try_derive_encode(&input),
         &input,
         "encode::Encode",
         "fn encode(&self, writer: &mut impl std::io::Write) -> std::io::Result<()>",
@@ -33,26 +33,20 @@ pub fn derive_encode(input: TokenStream) -> TokenStream {
     .into()
 }
 
-#[proc_macro_derive(AsyncDecode, attributes(var, array, with, ignore))]
+/// Kept so that `#[derive(AsyncDecode)]` still compiles.
+/// [`Decode`](derive_decode) is used from async code too,
+/// so this only checks that the type implements it, as deriving it again would conflict.
+#[deprecated(note = "derive `Decode`, which is used from async code too")]
+#[proc_macro_derive(AsyncDecode, attributes(var, array, with, buf, ignore))]
 pub fn derive_async_decode(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    wrap_result(
-        try_derive_decode(&input, true),
-        &input,
-        "async_decode::Decode",
-        "async fn decode(reader: &mut (impl futures_lite::AsyncReadExt + std::marker::Unpin + std::marker::Send)) -> std::result::Result<Self, ussr_buf::DecodeError>",
-    )
-    .into()
+    assert_implemented(&input, "Decode").into()
 }
 
-#[proc_macro_derive(AsyncEncode, attributes(var, array, with, ignore))]
+/// Kept so that `#[derive(AsyncEncode)]` still compiles, like `AsyncDecode`.
+#[deprecated(note = "derive `Encode`, which is used from async code too")]
+#[proc_macro_derive(AsyncEncode, attributes(var, array, with, buf, ignore))]
 pub fn derive_async_encode(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    wrap_result(
-        try_derive_encode(&input, true),
-        &input,
-        "async_encode::Encode",
-        "async fn encode(&self, writer: &mut (impl futures_lite::AsyncWriteExt + std::marker::Unpin + std::marker::Send)) -> std::io::Result<()>",
-    )
-    .into()
+    assert_implemented(&input, "Encode").into()
 }
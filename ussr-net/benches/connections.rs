@@ -0,0 +1,103 @@
+use std::{
+    io::{Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpStream},
+    time::Duration,
+};
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ussr_net::{ListenAddress, Listeners, PacketReceived, RateLimits, Timeouts, UssrNetPlugin};
+
+const CLIENTS: usize = 200;
+const PINGS: usize = 100;
+
+#[derive(Resource, Default)]
+struct Received(usize);
+
+/// Counts the packets, the pings are answered by the plugin so that both directions are measured.
+fn count_packets(mut received: ResMut<Received>, mut events: EventReader<PacketReceived>) {
+    received.0 += events.read().count();
+}
+
+fn update_until(app: &mut App, received: usize) {
+    while app.world().resource::<Received>().0 < received {
+        app.update();
+    }
+}
+
+fn bench(c: &mut Criterion) {
+    let mut app = App::new();
+    app.add_plugins(UssrNetPlugin {
+        listeners: vec![SocketAddr::from((Ipv4Addr::LOCALHOST, 0)).into()],
+        ..UssrNetPlugin::default()
+    })
+    .init_resource::<Received>()
+    .add_systems(Update, count_packets);
+    // Every client connects from the same address, and stays in the status state for the whole benchmark
+    app.world_mut().remove_resource::<RateLimits>();
+    app.insert_resource(Timeouts {
+        handshake: Duration::MAX,
+        ..Timeouts::default()
+    });
+
+    let [ListenAddress::Tcp(address)] = app.world().resource::<Listeners>().local_addresses()[..]
+    else {
+        panic!("Expected a single TCP listener");
+    };
+
+    #[rustfmt::skip]
+    let handshake: &[u8] = &[
+        0x0F, 0x00, 0x2F,
+        0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+        0x63, 0xDD, 0x01,
+    ];
+    let mut clients: Vec<TcpStream> = (0..CLIENTS)
+        .map(|_| {
+            let mut client: TcpStream = TcpStream::connect(address).unwrap();
+            client.write_all(handshake).unwrap();
+            // Accept it before the backlog of the listener is full
+            app.update();
+            client
+        })
+        .collect();
+    update_until(&mut app, CLIENTS);
+
+    let mut pings: Vec<u8> = Vec::new();
+    for payload in 0..PINGS as u64 {
+        pings.extend_from_slice(&[0x09, 0x01]);
+        pings.extend_from_slice(&payload.to_be_bytes());
+    }
+    let mut responses: Vec<u8> = vec![0; pings.len()];
+
+    let mut group = c.benchmark_group("connections");
+    // The cost of a frame when no client sends anything
+    group.bench_function("idle", |b| b.iter(|| app.update()));
+
+    group.throughput(Throughput::Elements((CLIENTS * PINGS) as u64));
+    group.bench_function("ping", |b| {
+        b.iter(|| {
+            for client in &mut clients {
+                client.write_all(&pings).unwrap();
+            }
+            let received: usize = app.world().resource::<Received>().0 + CLIENTS * PINGS;
+            update_until(&mut app, received);
+            // Send the responses queued by the last update
+            app.update();
+
+            for client in &mut clients {
+                client.read_exact(&mut responses).unwrap();
+            }
+            assert_eq!(responses, pings);
+        })
+    });
+}
+
+criterion_group! {
+    name = connections;
+    config = Criterion::default()
+                .measurement_time(Duration::from_secs(10))
+                .sample_size(20);
+    targets = bench
+}
+criterion_main!(connections);
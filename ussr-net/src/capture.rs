@@ -0,0 +1,241 @@
+//! Recording connections into captures and replaying them, see [`ussr_protocol::capture`].
+
+use std::{
+    fs::File,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use thiserror::Error;
+use tracing::warn;
+use ussr_buf::{read_frame, write_frame, DecodeError};
+use ussr_protocol::{
+    capture::{Capture, CaptureWriter},
+    Direction, State,
+};
+
+use crate::{add_connection_systems, memory, Connection};
+
+/// Record every connection into a capture in this directory.
+/// The captures are named after the address of the client and the time it connected.
+#[derive(Resource, Debug, Clone)]
+pub struct CaptureDirectory(pub PathBuf);
+
+pub(crate) type CaptureFile = CaptureWriter<BufWriter<File>>;
+
+pub(crate) fn start_capture(directory: &Path, address: &str) -> io::Result<CaptureFile> {
+    let address: String = address.replace([':', '[', ']'], "_");
+    let time: u128 = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let file: File = File::create(directory.join(format!("{address}-{time}.cap")))?;
+    CaptureWriter::new(BufWriter::new(file))
+}
+
+impl Connection {
+    /// Records a frame if the connection is captured.
+    /// The capture is stopped if it can't be written.
+    pub(crate) fn capture_frame(&mut self, direction: Direction, data: &[u8]) {
+        let state: State = self.state;
+        if let Some(capture) = &mut self.capture {
+            if let Err(e) = capture.write_frame(direction, state, data) {
+                warn!("Failed to write capture, stopping it: {e}");
+                self.capture = None;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    #[error(
+        "frame {index} was sent in state {expected:?}, but the connection is in state {actual:?}"
+    )]
+    State {
+        index: usize,
+        expected: State,
+        actual: State,
+    },
+
+    #[error("the connection was closed before frame {index}")]
+    Closed { index: usize },
+
+    #[error("frame {index} differs from the one sent by the connection: {actual:02X?}")]
+    Mismatch { index: usize, actual: Vec<u8> },
+
+    #[error("frame {index} wasn't sent by the connection")]
+    Missing { index: usize },
+
+    #[error("the connection sent a frame that isn't in the capture: {0:02X?}")]
+    Unexpected(Vec<u8>),
+}
+
+/// Feeds the serverbound frames of a capture into a fresh connection,
+/// and checks that it goes through the same states and sends the same clientbound frames, without being closed.
+///
+/// The connection runs with the default resources, without compression and encryption,
+/// so the capture must have been recorded by ussr with the same configuration.
+pub fn replay(capture: &Capture) -> Result<(), ReplayError> {
+    run_replay(capture, true)
+}
+
+/// Like [`replay`], but without comparing the clientbound frames,
+/// for captures of other servers such as vanilla, whose responses differ.
+pub fn replay_requests(capture: &Capture) -> Result<(), ReplayError> {
+    run_replay(capture, false)
+}
+
+fn run_replay(capture: &Capture, compare: bool) -> Result<(), ReplayError> {
+    let mut app = App::new();
+    add_connection_systems(&mut app);
+    let (entity, mut client) = memory::connect(app.world_mut(), State::Handshaking);
+
+    let mut expected = capture
+        .frames
+        .iter()
+        .enumerate()
+        .filter(|(_, frame)| frame.direction == Direction::Clientbound);
+
+    for (index, frame) in capture.frames.iter().enumerate() {
+        if frame.direction != Direction::Serverbound {
+            continue;
+        }
+        let Some(connection) = app.world().get::<Connection>(entity) else {
+            return Err(ReplayError::Closed { index });
+        };
+        if connection.state != frame.state {
+            return Err(ReplayError::State {
+                index,
+                expected: frame.state,
+                actual: connection.state,
+            });
+        }
+
+        write_frame(&mut client, &frame.data)?;
+        app.update();
+
+        let sent: Vec<u8> = client.read_available();
+        if !compare {
+            continue;
+        }
+        let mut sent: &[u8] = &sent;
+        while !sent.is_empty() {
+            let actual: Vec<u8> = read_frame(&mut sent, usize::MAX)?;
+            match expected.next() {
+                Some((_, frame)) if frame.data == actual => {}
+                Some((index, _)) => return Err(ReplayError::Mismatch { index, actual }),
+                None => return Err(ReplayError::Unexpected(actual)),
+            }
+        }
+    }
+
+    if let (true, Some((index, _))) = (compare, expected.next()) {
+        return Err(ReplayError::Missing { index });
+    }
+    match app.world().get::<Connection>(entity) {
+        Some(_) => Ok(()),
+        None => Err(ReplayError::Closed {
+            index: capture.frames.len(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Reads the captures in a directory of `tests/`, and checks that all of their frames can be decoded.
+    fn read_captures(directory: &str) -> Vec<(PathBuf, Capture)> {
+        let directory: PathBuf = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join(directory);
+        let mut captures: Vec<(PathBuf, Capture)> = Vec::new();
+
+        for entry in fs::read_dir(directory).unwrap() {
+            let path: PathBuf = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "cap") {
+                continue;
+            }
+
+            let capture: Capture = Capture::read(&mut File::open(&path).unwrap()).unwrap();
+            let rendered: String = capture.render();
+            assert!(
+                !rendered.contains("error: "),
+                "{}:\n{rendered}",
+                path.display()
+            );
+            captures.push((path, capture));
+        }
+        captures
+    }
+
+    /// Replays the captures recorded from ussr with the default resources,
+    /// so that a change in its responses is caught.
+    #[test]
+    fn test_replay_snapshots() {
+        let captures: Vec<(PathBuf, Capture)> = read_captures("snapshots");
+        assert!(!captures.is_empty());
+        for (path, capture) in captures {
+            replay(&capture).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        }
+    }
+
+    /// Replays the requests of the sessions recorded between vanilla clients and servers,
+    /// see `tests/vanilla/README.md`.
+    #[test]
+    fn test_replay_vanilla() {
+        for (path, capture) in read_captures("vanilla") {
+            replay_requests(&capture).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        }
+    }
+
+    #[test]
+    fn test_replay_wrong_state() {
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        // A status request without a handshake
+        writer
+            .write_frame(Direction::Serverbound, State::Status, &[0x00])
+            .unwrap();
+        let capture: Capture = Capture::read(&mut &writer.into_inner()[..]).unwrap();
+
+        assert!(matches!(
+            replay(&capture),
+            Err(ReplayError::State {
+                index: 0,
+                expected: State::Status,
+                actual: State::Handshaking,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_replay_mismatch() {
+        let path: PathBuf =
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots/status_1_8_9.cap");
+        let mut capture: Capture = Capture::read(&mut File::open(path).unwrap()).unwrap();
+        // Change the payload of the ping response
+        let index: usize = capture.frames.len() - 1;
+        assert_eq!(capture.frames[index].direction, Direction::Clientbound);
+        *capture.frames[index].data.last_mut().unwrap() ^= 1;
+
+        assert!(matches!(
+            replay(&capture),
+            Err(ReplayError::Mismatch { index: i, .. }) if i == index
+        ));
+
+        capture.frames.pop();
+        assert!(matches!(replay(&capture), Err(ReplayError::Unexpected(_))));
+    }
+}
@@ -0,0 +1,153 @@
+//! Compressed frames, used once compression is enabled by [`SetCompression`](ussr_protocol::neutral::Clientbound::SetCompression).
+//!
+//! A compressed frame starts with the length of the uncompressed frame as a VarInt,
+//! followed by the frame compressed with zlib.
+//! Frames smaller than the threshold are not compressed, and their uncompressed length is 0.
+
+use std::io::{self, Read, Write};
+
+use bevy_ecs::prelude::*;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use thiserror::Error;
+use ussr_buf::{write_frame, DecodeError, VarDecode, VarEncode};
+
+use crate::MAX_PACKET_SIZE;
+
+/// Enable compression for clients that support it, compressing packets of this size or larger.
+/// [`SetCompression`](ussr_protocol::neutral::Clientbound::SetCompression) is sent
+/// right before [`LoginSuccess`](ussr_protocol::neutral::Clientbound::LoginSuccess).
+/// Vanilla uses a threshold of 256.
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct CompressionThreshold(pub usize);
+
+#[derive(Debug, Error)]
+pub(crate) enum CompressionError {
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+
+    #[error("uncompressed length {length} is below the threshold of {threshold}")]
+    BelowThreshold { length: usize, threshold: usize },
+
+    #[error("uncompressed length {0} is too large")]
+    TooLarge(usize),
+
+    #[error("uncompressed length is {expected}, but the data is {actual} bytes")]
+    LengthMismatch { expected: usize, actual: usize },
+}
+
+/// Returns the frame (the packet ID followed by its body) in a compressed frame without its length prefix.
+pub(crate) fn decompress_frame(
+    frame: &[u8],
+    threshold: usize,
+) -> Result<Vec<u8>, CompressionError> {
+    let mut reader: &[u8] = frame;
+    let length: usize = usize::var_decode(&mut reader)?;
+    if length == 0 {
+        return Ok(reader.to_vec());
+    }
+    if length < threshold {
+        return Err(CompressionError::BelowThreshold { length, threshold });
+    }
+    if length > MAX_PACKET_SIZE {
+        return Err(CompressionError::TooLarge(length));
+    }
+
+    // Read one more byte than declared, so that longer data is detected without inflating all of it
+    let mut data: Vec<u8> = Vec::with_capacity(length);
+    ZlibDecoder::new(reader)
+        .take(length as u64 + 1)
+        .read_to_end(&mut data)
+        .map_err(DecodeError::from)?;
+    if data.len() != length {
+        return Err(CompressionError::LengthMismatch {
+            expected: length,
+            actual: data.len(),
+        });
+    }
+    Ok(data)
+}
+
+/// Appends a frame in the compressed format to `buf`, prefixed with its length.
+pub(crate) fn write_compressed_frame(
+    buf: &mut Vec<u8>,
+    frame: &[u8],
+    threshold: usize,
+) -> io::Result<()> {
+    let mut compressed: Vec<u8> = Vec::new();
+    if frame.len() < threshold {
+        0u32.var_encode(&mut compressed)?;
+        compressed.extend_from_slice(frame);
+    } else {
+        frame.len().var_encode(&mut compressed)?;
+        let mut encoder = ZlibEncoder::new(compressed, Compression::default());
+        encoder.write_all(frame)?;
+        compressed = encoder.finish()?;
+    }
+    write_frame(buf, &compressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLD: usize = 64;
+
+    /// Writes and reads back a frame, returning whether it was compressed.
+    fn round_trip(frame: &[u8]) -> bool {
+        let mut buf: Vec<u8> = Vec::new();
+        write_compressed_frame(&mut buf, frame, THRESHOLD).unwrap();
+
+        let mut reader: &[u8] = &buf;
+        let length: usize = usize::var_decode(&mut reader).unwrap();
+        assert_eq!(reader.len(), length);
+        assert_eq!(decompress_frame(reader, THRESHOLD).unwrap(), frame);
+        reader[0] != 0
+    }
+
+    #[test]
+    fn test_threshold() {
+        assert!(!round_trip(&[]));
+        assert!(!round_trip(&[0xAB; THRESHOLD - 1]));
+        assert!(round_trip(&[0xAB; THRESHOLD]));
+        assert!(round_trip(&[0xAB; THRESHOLD + 1]));
+        assert!(round_trip(&[0xAB; 100_000]));
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let compress = |length: usize, data: &[u8]| {
+            let mut frame: Vec<u8> = Vec::new();
+            length.var_encode(&mut frame).unwrap();
+            let mut encoder = ZlibEncoder::new(frame, Compression::default());
+            encoder.write_all(data).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        assert!(matches!(
+            decompress_frame(&compress(THRESHOLD - 1, &[0; THRESHOLD - 1]), THRESHOLD),
+            Err(CompressionError::BelowThreshold {
+                length: 63,
+                threshold: THRESHOLD,
+            })
+        ));
+        assert!(matches!(
+            decompress_frame(&compress(MAX_PACKET_SIZE + 1, &[0; 8]), THRESHOLD),
+            Err(CompressionError::TooLarge(_))
+        ));
+        assert!(matches!(
+            decompress_frame(&compress(THRESHOLD, &[0; THRESHOLD + 1]), THRESHOLD),
+            Err(CompressionError::LengthMismatch {
+                expected: THRESHOLD,
+                actual: 65,
+            })
+        ));
+        assert!(matches!(
+            decompress_frame(&compress(THRESHOLD + 1, &[0; THRESHOLD]), THRESHOLD),
+            Err(CompressionError::LengthMismatch { actual: 64, .. })
+        ));
+        assert!(matches!(
+            decompress_frame(&[THRESHOLD as u8, 0xDE, 0xAD], THRESHOLD),
+            Err(CompressionError::Decode(_))
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+//! The cryptography of the login sequence.
+
+use aes::Aes128;
+use cfb8::cipher::{inout::InOutBuf, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use num_bigint::BigInt;
+use rsa::{pkcs8::EncodePublicKey, Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+use sha1::{Digest, Sha1};
+use thiserror::Error;
+
+/// The size of the RSA key, which vanilla clients expect.
+const KEY_BITS: usize = 1024;
+
+#[derive(Debug, Error)]
+pub enum KeyError {
+    #[error("failed to generate the server key: {0}")]
+    Generate(#[from] rsa::Error),
+
+    #[error("failed to encode the server key: {0}")]
+    Encode(#[from] rsa::pkcs8::spki::Error),
+}
+
+/// The RSA key used to exchange the shared secret of connections.
+pub(crate) struct ServerKey {
+    private_key: RsaPrivateKey,
+    /// The public key as a DER-encoded `SubjectPublicKeyInfo`, which is sent to clients.
+    public_key_der: Vec<u8>,
+}
+
+impl ServerKey {
+    pub(crate) fn generate() -> Result<ServerKey, KeyError> {
+        let private_key: RsaPrivateKey = RsaPrivateKey::new(&mut rand::thread_rng(), KEY_BITS)?;
+        let public_key_der: Vec<u8> = RsaPublicKey::from(&private_key)
+            .to_public_key_der()?
+            .into_vec();
+        Ok(ServerKey {
+            private_key,
+            public_key_der,
+        })
+    }
+
+    pub(crate) fn public_key_der(&self) -> &[u8] {
+        &self.public_key_der
+    }
+
+    /// Decrypts data encrypted by a client with PKCS#1 v1.5 padding, returning `None` if it is invalid.
+    pub(crate) fn decrypt(&self, ciphertext: &[u8]) -> Option<Vec<u8>> {
+        self.private_key.decrypt(Pkcs1v15Encrypt, ciphertext).ok()
+    }
+
+    /// Encrypts data with the public key, as a client would.
+    #[cfg(test)]
+    pub(crate) fn encrypt(&self, data: &[u8]) -> Vec<u8> {
+        RsaPublicKey::from(&self.private_key)
+            .encrypt(&mut rand::thread_rng(), Pkcs1v15Encrypt, data)
+            .unwrap()
+    }
+}
+
+/// The AES-128-CFB8 encryption of a connection, in both directions.
+/// The protocol uses the shared secret as both the key and the initialization vector.
+pub(crate) struct StreamCipher {
+    encryptor: cfb8::Encryptor<Aes128>,
+    decryptor: cfb8::Decryptor<Aes128>,
+}
+
+impl StreamCipher {
+    pub(crate) fn new(shared_secret: &[u8; 16]) -> StreamCipher {
+        StreamCipher {
+            encryptor: cfb8::Encryptor::new(shared_secret.into(), shared_secret.into()),
+            decryptor: cfb8::Decryptor::new(shared_secret.into(), shared_secret.into()),
+        }
+    }
+
+    /// Encrypts data sent to the client in place.
+    pub(crate) fn encrypt(&mut self, data: &mut [u8]) {
+        // CFB8 has blocks of a single byte
+        let (blocks, _) = InOutBuf::from(data).into_chunks();
+        self.encryptor.encrypt_blocks_inout_mut(blocks);
+    }
+
+    /// Decrypts data received from the client in place.
+    pub(crate) fn decrypt(&mut self, data: &mut [u8]) {
+        let (blocks, _) = InOutBuf::from(data).into_chunks();
+        self.decryptor.decrypt_blocks_inout_mut(blocks);
+    }
+}
+
+/// The hash that the client and the session server use to identify a login to this server.
+/// It is the SHA-1 of the inputs as a signed number in hexadecimal, as formatted by Java's `BigInteger`.
+pub(crate) fn server_hash(server_id: &str, shared_secret: &[u8], public_key: &[u8]) -> String {
+    let digest = Sha1::new()
+        .chain_update(server_id)
+        .chain_update(shared_secret)
+        .chain_update(public_key)
+        .finalize();
+    BigInt::from_signed_bytes_be(&digest).to_str_radix(16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_cipher() {
+        let secret: [u8; 16] = *b"0123456789abcdef";
+        let mut server = StreamCipher::new(&secret);
+        let mut client = StreamCipher::new(&secret);
+
+        // The stream continues across calls
+        let mut data: Vec<u8> = b"Hello, world!".to_vec();
+        server.encrypt(&mut data[..5]);
+        server.encrypt(&mut data[5..]);
+        assert_ne!(data, b"Hello, world!");
+        client.decrypt(&mut data);
+        assert_eq!(data, b"Hello, world!");
+    }
+
+    #[test]
+    fn test_server_key() {
+        let key = ServerKey::generate().unwrap();
+        let secret: [u8; 16] = *b"0123456789abcdef";
+        assert_eq!(
+            key.decrypt(&key.encrypt(&secret)).as_deref(),
+            Some(&secret[..])
+        );
+        assert_eq!(key.decrypt(&[0; 128]), None);
+    }
+
+    #[test]
+    fn test_server_hash() {
+        assert_eq!(
+            server_hash("Notch", &[], &[]),
+            "4ed1f46bbe04bc756bcb17c0c7ce3e4632f06a48"
+        );
+        assert_eq!(
+            server_hash("jeb_", &[], &[]),
+            "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+        );
+        assert_eq!(
+            server_hash("simon", &[], &[]),
+            "88e16a1019277b15d58faf0541e11910eb756f6"
+        );
+    }
+}
@@ -1,150 +1,300 @@
+mod capture;
+mod compression;
+mod crypto;
+mod lifecycle;
+mod listener;
+mod login;
+mod memory;
+mod player;
 mod process_data;
+mod proxy;
+mod send;
+mod status;
+mod throttle;
+mod transport;
 
 use std::{
-    io::{self, ErrorKind, Read, Write},
-    net::{TcpListener, TcpStream},
+    net::{Ipv4Addr, SocketAddr},
+    time::Instant,
 };
 
 use bevy_app::prelude::*;
 use bevy_ecs::prelude::*;
 use bytes::BytesMut;
-use tracing::{instrument, trace, warn};
-use ussr_protocol::State;
+use tracing::{error, instrument, trace, warn};
+use ussr_protocol::{neutral::Serverbound, ProtocolVersion, ServerStatus, State, TextComponent};
 
+use capture::{start_capture, CaptureFile};
+use crypto::StreamCipher;
+use lifecycle::{keep_alive, time_out_logins};
+use login::{handle_login, poll_authentication};
 use process_data::process_data;
+use proxy::{handle_forwarding, read_proxy_header, AwaitingProxyHeader};
+use send::encode_packets;
+use status::respond_status;
+use throttle::{limit_clients, Clients, Usage};
+use transport::{IoThread, QueueError, Transport};
 
-// TODO: do IO stuff in tasks
-
-/// The size of the read buffer.
-const READ_BUFFER_SIZE: usize = 1024;
+pub use capture::{replay, replay_requests, CaptureDirectory, ReplayError};
+pub use compression::CompressionThreshold;
+pub use crypto::KeyError;
+pub use lifecycle::{ClientDisconnected, DisconnectCause, DisconnectExt, Latency, Timeouts};
+pub use listener::{BindError, ListenAddress, Listeners};
+pub use login::{
+    session_server_url, AuthError, Authenticator, GameProfile, OnlineMode, ProfileProperty,
+};
+pub use player::{offline_uuid, JoinGameSettings, Player, PlayerBundle, PlayerUuid, Username};
+pub use proxy::{BungeeCordForwarding, ClientAddress, ProxyProtocol};
+pub use send::{Outbox, SendPacketExt};
+pub use throttle::{PacketBudget, Rate, RateLimits};
 
 /// The maximum size of a packet in bytes.
-const MAX_PACKET_SIZE: usize = 2097151;
+pub const MAX_PACKET_SIZE: usize = 2097151;
+
+/// The networking of the server.
+/// If the addresses can't be listened on, the error is logged and the app exits with [`AppExit::error`].
+#[derive(Debug, Clone)]
+pub struct UssrNetPlugin {
+    /// The addresses to accept connections on.
+    pub listeners: Vec<ListenAddress>,
+    /// The maximum number of connections waiting to be accepted, for each listener.
+    pub backlog: i32,
+    /// Set TCP_NODELAY on TCP connections, so that packets are sent without waiting for more data.
+    pub nodelay: bool,
+}
 
-pub struct UssrNetPlugin;
+impl Default for UssrNetPlugin {
+    fn default() -> UssrNetPlugin {
+        UssrNetPlugin {
+            listeners: vec![SocketAddr::from((Ipv4Addr::LOCALHOST, 25565)).into()],
+            backlog: 128,
+            nodelay: true,
+        }
+    }
+}
 
 impl Plugin for UssrNetPlugin {
     fn build(&self, app: &mut bevy_app::App) {
-        app.insert_resource(Server::new()).add_systems(
-            Update,
-            (accept_connections, read_data, process_data, send_data).chain(),
-        );
+        let listeners: Listeners = Listeners::bind(&self.listeners, self.backlog, self.nodelay)
+            .unwrap_or_else(|e| {
+                error!("{e}");
+                app.world_mut().send_event(AppExit::error());
+                Listeners::default()
+            });
+
+        match IoThread::start() {
+            Ok(io) => {
+                app.insert_resource(io);
+            }
+            Err(e) => {
+                error!("Failed to start the IO thread: {e}");
+                app.world_mut().send_event(AppExit::error());
+            }
+        }
+
+        app.insert_resource(listeners)
+            .add_systems(Update, accept_connections.before(read_data));
+        add_connection_systems(app);
     }
 }
 
-/// A resource that contains server information.
-/// The underlying [`TcpListener`] must be non-blocking, see [`TcpListener::set_nonblocking`].
-#[derive(Resource)]
-struct Server {
-    listener: TcpListener,
+/// Adds everything that processes connections once they are accepted.
+/// Replays use it without listeners.
+fn add_connection_systems(app: &mut App) {
+    app.init_resource::<Status>()
+        .init_resource::<JoinGameSettings>()
+        .init_resource::<Timeouts>()
+        .init_resource::<RateLimits>()
+        .init_resource::<Clients>()
+        .add_event::<PacketReceived>()
+        .add_event::<ClientDisconnected>()
+        .add_systems(
+            Update,
+            (
+                read_data,
+                read_proxy_header,
+                process_data,
+                handle_forwarding.run_if(resource_exists::<BungeeCordForwarding>),
+                limit_clients.run_if(resource_exists::<RateLimits>),
+                respond_status,
+                handle_login,
+                poll_authentication,
+                time_out_logins,
+                keep_alive,
+                encode_packets,
+                send_data,
+            )
+                .chain(),
+        );
 }
 
-impl Server {
-    fn new() -> Server {
-        let listener: TcpListener =
-            TcpListener::bind("127.0.0.1:25565").expect("Failed to bind server on port 25565");
-        listener
-            .set_nonblocking(true)
-            .expect("Failed to set server to non-blocking");
-        Server { listener }
-    }
+/// The status shown in the server list.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Status(pub ServerStatus);
+
+/// Sent for every packet received from a client.
+/// Play packets that aren't part of the neutral model are sent as [`Serverbound::Other`],
+/// which carries the packet of the version of the client.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct PacketReceived {
+    /// The entity with the connection.
+    pub entity: Entity,
+    pub packet: Serverbound,
 }
 
 /// A single connection to the server.
 /// This component is added automatically by [`accept_connections`].
 #[derive(Component)]
+#[require(Outbox)]
 struct Connection {
-    stream: TcpStream,
+    transport: Transport,
     state: State,
+    /// The version chosen by the handshake, [`ProtocolVersion::LATEST`] before that.
+    version: ProtocolVersion,
     incoming_buf: BytesMut, //? Maybe it should be a vector of frames
     outgoing_buf: Vec<u8>,
+    /// The threshold sent in [`SetCompression`](ussr_protocol::neutral::Clientbound::SetCompression),
+    /// if frames are compressed.
+    compression: Option<usize>,
+    /// Set once the login enables encryption, which applies to everything sent and received after it.
+    cipher: Option<StreamCipher>,
+    /// Set by an [`EncryptionResponse`](Serverbound::EncryptionResponse) until the login enables encryption,
+    /// as the data after it can't be processed before.
+    awaiting_key: bool,
+    /// Close the connection once [`Connection::outgoing_buf`] is sent, and don't process incoming data.
+    /// Set by [`Connection::disconnect`].
+    closing: Option<DisconnectCause>,
+    /// Set by [`DisconnectExt`], to disconnect once the [`Outbox`] is sent.
+    kick: Option<TextComponent>,
+    /// When the connection was accepted, for [`Timeouts::login`].
+    connected_at: Instant,
+    /// The ID of the keep alive waiting for an answer, and when it was sent.
+    keep_alive: Option<(i32, Instant)>,
+    /// When the last keep alive was answered.
+    last_keep_alive: Option<Instant>,
+    /// What was received in the current state, for [`RateLimits`].
+    usage: Option<Usage>,
+    /// Set if there is a [`CaptureDirectory`].
+    capture: Option<CaptureFile>,
 }
 
 impl Connection {
     /// Create a new connection.
-    /// The stream will be made non-blocking, see [`TcpStream::set_nonblocking`].
-    fn new(stream: TcpStream) -> io::Result<Connection> {
-        stream.set_nonblocking(true)?;
-        Ok(Connection {
-            stream,
+    fn new(transport: Transport) -> Connection {
+        Connection {
+            transport,
             state: State::Handshaking,
+            version: ProtocolVersion::LATEST,
             incoming_buf: BytesMut::new(),
             outgoing_buf: vec![],
-        })
+            compression: None,
+            cipher: None,
+            awaiting_key: false,
+            closing: None,
+            kick: None,
+            connected_at: Instant::now(),
+            keep_alive: None,
+            last_keep_alive: None,
+            usage: None,
+            capture: None,
+        }
     }
 }
 
 /// A system that accepts connections and spawns new entities with [`Connection`].
-/// [`Server::listener`] must be non-blocking, see [`TcpListener::set_nonblocking`].
-#[instrument(skip_all, level = "trace")]
-fn accept_connections(mut commands: Commands, server: Res<Server>) {
-    while let Ok((stream, _)) = server.listener.accept() {
-        trace!("Accepted connection");
-        commands.spawn(Connection::new(stream).expect("Failed to create connection"));
-    }
-}
-
-/// A system that reads data from a [`Connection`].
-/// [`Connection::stream`] must be non-blocking, see [`TcpStream::set_nonblocking`].
 #[instrument(skip_all, level = "trace")]
-fn read_data(mut commands: Commands, mut query: Query<(Entity, &mut Connection)>) {
-    let mut buf: Vec<u8> = vec![0; READ_BUFFER_SIZE];
+fn accept_connections(
+    mut commands: Commands,
+    listeners: Res<Listeners>,
+    io: Option<Res<IoThread>>,
+    capture_directory: Option<Res<CaptureDirectory>>,
+    proxy_protocol: Option<Res<ProxyProtocol>>,
+    forwarding: Option<Res<BungeeCordForwarding>>,
+) {
+    let Some(io) = io else {
+        return;
+    };
 
-    for (entity, mut connection) in &mut query {
-        match connection.stream.read(&mut buf) {
-            // Connection closed
-            Ok(0) => {
-                trace!("Connection closed");
-                //? Maybe we shouldn't despawn the entity, and process the leftower data?
-                commands.entity(entity).despawn();
-                // commands.entity(entity).remove::<Connection>();
+    for (stream, address) in listeners.accept() {
+        trace!("Accepted connection");
+        let name: String = address.map_or_else(|| "unix".to_owned(), |address| address.to_string());
+        let transport: Transport = match io.register(stream) {
+            Ok(transport) => transport,
+            Err(e) => {
+                warn!("Failed to start connection IO: {e}");
+                continue;
             }
+        };
+        let mut connection: Connection = Connection::new(transport);
 
-            // Successful read
-            Ok(n) => {
-                trace!("Read {n} bytes");
-                connection.incoming_buf.extend_from_slice(&buf[..n]);
+        if let Some(directory) = &capture_directory {
+            match start_capture(&directory.0, &name) {
+                Ok(capture) => connection.capture = Some(capture),
+                Err(e) => warn!("Failed to start capture: {e}"),
             }
+        }
 
-            // Wood block
-            Err(e) if e.kind() == ErrorKind::WouldBlock => continue,
-
-            // Other error
-            Err(e) => {
-                trace!("Error: {e:?}");
-                continue;
-            }
+        let mut entity = commands.spawn(connection);
+        if proxy_protocol.is_some() {
+            entity.insert(AwaitingProxyHeader);
+        } else if let (Some(address), None) = (address, &forwarding) {
+            // Behind a proxy, the address is only known once it is forwarded,
+            // so that the connections of the proxy aren't limited by its address
+            entity.insert(ClientAddress(address.ip()));
         }
     }
 }
 
+/// A system that moves the data read by the IO thread into [`Connection::incoming_buf`].
 #[instrument(skip_all, level = "trace")]
-fn send_data(mut commands: Commands, mut query: Query<(Entity, &mut Connection)>) {
-    for (entity, mut connection) in &mut query {
-        if connection.outgoing_buf.is_empty() {
+fn read_data(mut query: Query<&mut Connection>) {
+    for mut connection in &mut query {
+        let connection: &mut Connection = &mut connection;
+        let start: usize = connection.incoming_buf.len();
+        if !connection.transport.receive(&mut connection.incoming_buf) {
+            trace!("Connection closed");
+            //? Maybe we shouldn't close the connection, and process the leftower data?
+            connection.disconnect(DisconnectCause::Closed);
             continue;
         }
+        if let Some(cipher) = &mut connection.cipher {
+            cipher.decrypt(&mut connection.incoming_buf[start..]);
+        }
+    }
+}
 
-        let mut outgoing_buf: Vec<u8> = std::mem::take(&mut connection.outgoing_buf);
-        match connection.stream.write(&outgoing_buf) {
-            // Successful write
-            Ok(n) => {
-                trace!("Wrote {n} bytes");
-                std::mem::swap(&mut connection.outgoing_buf, &mut outgoing_buf);
-                connection.outgoing_buf.drain(..n);
+/// A system that hands [`Connection::outgoing_buf`] to the IO thread,
+/// and despawns the connections that are closing.
+#[instrument(skip_all, level = "trace")]
+fn send_data(
+    mut commands: Commands,
+    mut events: EventWriter<ClientDisconnected>,
+    mut query: Query<(Entity, &mut Connection)>,
+) {
+    for (entity, mut connection) in &mut query {
+        if !connection.outgoing_buf.is_empty() {
+            let mut outgoing_buf: Vec<u8> = std::mem::take(&mut connection.outgoing_buf);
+            if let Some(cipher) = &mut connection.cipher {
+                cipher.encrypt(&mut outgoing_buf);
             }
-
-            // Wood block
-            Err(e) if e.kind() == ErrorKind::WouldBlock => continue,
-
-            // Other error
-            Err(e) => {
-                warn!("Error: {e:?}");
-                commands.entity(entity).despawn();
+            match connection.transport.send(outgoing_buf) {
+                Ok(()) => {}
+                Err(QueueError::Closed) => {
+                    trace!("Connection closed");
+                    connection.disconnect(DisconnectCause::Closed);
+                }
+                Err(e @ QueueError::Backlogged(_)) => {
+                    trace!("{e}, disconnecting");
+                    connection.disconnect(DisconnectCause::SendFailed(e.to_string()));
+                }
             }
         }
+
+        // The IO thread writes what was sent before the connection is dropped
+        if let Some(cause) = connection.closing.take() {
+            trace!("Closing connection: {cause:?}");
+            events.send(ClientDisconnected { entity, cause });
+            commands.entity(entity).despawn();
+        }
     }
 }
-
-// TODO: system for writing outgoing data
-// TODO: function to prefix the packet with its length
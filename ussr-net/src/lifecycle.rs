@@ -0,0 +1,371 @@
+//! Disconnecting clients, timeouts and keep alives.
+//!
+//! A connection is closed by [`Connection::disconnect`], which tells the client why if its state has a disconnect packet.
+//! The connection is despawned once its outgoing data is handed to its IO, which sends it before closing the socket,
+//! and a [`ClientDisconnected`] event is sent with the [`DisconnectCause`].
+
+use std::time::{Duration, Instant};
+
+use bevy_ecs::{prelude::*, world::Command};
+use tracing::{instrument, trace, warn};
+use ussr_protocol::{
+    neutral::{Clientbound, Serverbound},
+    State, TextComponent,
+};
+
+use crate::{Connection, Outbox, PacketReceived};
+
+/// Why a connection was closed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisconnectCause {
+    /// The client closed the connection.
+    Closed,
+    /// The server closed the connection after answering a legacy ping.
+    Finished,
+    /// The client sent data that can't be processed in its state.
+    InvalidData(String),
+    /// A packet could not be sent to the client.
+    SendFailed(String),
+    /// The client didn't log in, or didn't answer a keep alive, in time. See [`Timeouts`].
+    TimedOut,
+    /// The client sent more than its [`PacketBudget`](crate::PacketBudget).
+    RateLimited,
+    /// The address of the client is blocked, or has too many connections. See [`RateLimits`](crate::RateLimits).
+    Throttled,
+    /// The server disconnected the client, like when a login fails or with [`DisconnectExt`].
+    Kicked(TextComponent),
+}
+
+impl DisconnectCause {
+    /// The reason shown to the player, if they are told about it.
+    fn reason(&self) -> Option<TextComponent> {
+        match self {
+            DisconnectCause::Closed
+            | DisconnectCause::Finished
+            | DisconnectCause::SendFailed(_) => None,
+            DisconnectCause::InvalidData(message) => {
+                Some(TextComponent::text(format!("Invalid data: {message}")))
+            }
+            DisconnectCause::TimedOut => Some(TextComponent::text("Timed out")),
+            DisconnectCause::RateLimited => {
+                Some(TextComponent::text("You are sending too many packets!"))
+            }
+            DisconnectCause::Throttled => Some(TextComponent::text(
+                "Connection throttled! Please wait before reconnecting.",
+            )),
+            DisconnectCause::Kicked(reason) => Some(reason.clone()),
+        }
+    }
+}
+
+/// Sent when a connection is closed.
+/// The entity of the connection is despawned in the same update.
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ClientDisconnected {
+    pub entity: Entity,
+    pub cause: DisconnectCause,
+}
+
+/// How long clients may take to do things before they are disconnected.
+#[derive(Resource, Debug, Clone)]
+pub struct Timeouts {
+    /// The time from the connection to the end of the handshake,
+    /// and of the status queries after it.
+    pub handshake: Duration,
+    /// The time from the connection to the end of the login.
+    pub login: Duration,
+    /// The time between the answer to a keep alive and the next keep alive.
+    pub keep_alive_interval: Duration,
+    /// The time a keep alive may go unanswered.
+    pub keep_alive: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Timeouts {
+        Timeouts {
+            handshake: Duration::from_secs(10),
+            login: Duration::from_secs(30),
+            keep_alive_interval: Duration::from_secs(15),
+            keep_alive: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The round trip time of the last keep alive of a player.
+/// This component is added to connections in the play state once they answer a keep alive.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Latency(pub Duration);
+
+/// Disconnecting clients with [`Commands`].
+pub trait DisconnectExt {
+    /// Disconnects the client of `entity` with a reason, once the packets sent to it before are sent.
+    /// The reason is only shown in the login and play states.
+    fn disconnect(&mut self, entity: Entity, reason: impl Into<TextComponent>);
+}
+
+impl DisconnectExt for Commands<'_, '_> {
+    fn disconnect(&mut self, entity: Entity, reason: impl Into<TextComponent>) {
+        self.queue(Disconnect {
+            entity,
+            reason: reason.into(),
+        });
+    }
+}
+
+struct Disconnect {
+    entity: Entity,
+    reason: TextComponent,
+}
+
+impl Command for Disconnect {
+    fn apply(self, world: &mut World) {
+        match world.get_mut::<Connection>(self.entity) {
+            Some(mut connection) => connection.kick = Some(self.reason),
+            None => trace!("Can't disconnect {}, it has no connection", self.entity),
+        }
+    }
+}
+
+impl Connection {
+    /// Closes the connection once its outgoing data is sent,
+    /// after sending the reason to the client if its state has a disconnect packet.
+    /// Only the first cause is kept if it's called again.
+    pub(crate) fn disconnect(&mut self, cause: DisconnectCause) {
+        if self.closing.is_some() {
+            return;
+        }
+
+        let packet: Option<Clientbound> = match (self.state, cause.reason()) {
+            (State::Login, Some(reason)) => Some(Clientbound::LoginDisconnect { reason }),
+            (State::Play, Some(reason)) => Some(Clientbound::Disconnect { reason }),
+            _ => None,
+        };
+        if let Some(packet) = packet {
+            if let Err(e) = self.write_packet(&packet) {
+                warn!("Failed to send {packet:?}: {e}");
+            }
+        }
+        self.closing = Some(cause);
+    }
+}
+
+/// A system that disconnects the clients that didn't finish their handshake or login
+/// within [`Timeouts::handshake`] and [`Timeouts::login`].
+#[instrument(skip_all, level = "trace")]
+pub(crate) fn time_out_logins(timeouts: Res<Timeouts>, mut query: Query<&mut Connection>) {
+    let now: Instant = Instant::now();
+    for mut connection in &mut query {
+        let timeout: Duration = match connection.state {
+            State::Handshaking | State::Status => timeouts.handshake,
+            State::Login => timeouts.login,
+            State::Play => continue,
+        };
+        if now.duration_since(connection.connected_at) >= timeout {
+            trace!("Login timed out, disconnecting");
+            connection.disconnect(DisconnectCause::TimedOut);
+        }
+    }
+}
+
+/// A system that sends keep alives to players, measures their [`Latency`],
+/// and disconnects those that don't answer within [`Timeouts::keep_alive`].
+#[instrument(skip_all, level = "trace")]
+pub(crate) fn keep_alive(
+    mut commands: Commands,
+    timeouts: Res<Timeouts>,
+    mut events: EventReader<PacketReceived>,
+    mut query: Query<(&mut Connection, &mut Outbox)>,
+) {
+    let now: Instant = Instant::now();
+
+    for event in events.read() {
+        let Serverbound::KeepAlive { id } = event.packet else {
+            continue;
+        };
+        let Ok((mut connection, _)) = query.get_mut(event.entity) else {
+            continue;
+        };
+        match connection.keep_alive {
+            Some((expected, sent)) if expected == id => {
+                connection.keep_alive = None;
+                connection.last_keep_alive = Some(now);
+                commands
+                    .entity(event.entity)
+                    .insert(Latency(now.duration_since(sent)));
+            }
+            // Like vanilla, unexpected keep alives are ignored
+            _ => trace!("Unexpected keep alive {id}"),
+        }
+    }
+
+    for (mut connection, mut outbox) in &mut query {
+        if connection.state != State::Play || connection.closing.is_some() {
+            continue;
+        }
+        match (connection.keep_alive, connection.last_keep_alive) {
+            (Some((_, sent)), _) if now.duration_since(sent) >= timeouts.keep_alive => {
+                trace!("Keep alive timed out, disconnecting");
+                connection.disconnect(DisconnectCause::TimedOut);
+            }
+            (Some(_), _) => {}
+            (None, Some(last)) if now.duration_since(last) < timeouts.keep_alive_interval => {}
+            // The first keep alive is sent as soon as the player joins
+            (None, _) => {
+                let id: i32 = rand::random();
+                outbox.push(Clientbound::KeepAlive { id });
+                connection.keep_alive = Some((id, now));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bevy_app::prelude::*;
+    use ussr_buf::{read_frame, VarDecode, VarEncode};
+
+    use super::*;
+    use crate::{
+        memory, process_data::process_data, read_data, send::encode_packets, send_data,
+        SendPacketExt, Status, MAX_PACKET_SIZE,
+    };
+
+    fn app(timeouts: Timeouts) -> App {
+        let mut app = App::new();
+        app.init_resource::<Status>()
+            .insert_resource(timeouts)
+            .add_event::<PacketReceived>()
+            .add_event::<ClientDisconnected>()
+            .add_systems(
+                Update,
+                (
+                    read_data,
+                    process_data,
+                    time_out_logins,
+                    keep_alive,
+                    encode_packets,
+                    send_data,
+                )
+                    .chain(),
+            );
+        app
+    }
+
+    /// Reads the frames sent to a client.
+    fn received(client: &mut memory::MemoryClient) -> Vec<Vec<u8>> {
+        let buf: Vec<u8> = client.read_available();
+        let mut reader: &[u8] = &buf;
+        std::iter::from_fn(|| read_frame(&mut reader, MAX_PACKET_SIZE).ok()).collect()
+    }
+
+    fn disconnected(app: &App) -> Vec<ClientDisconnected> {
+        let events = app.world().resource::<Events<ClientDisconnected>>();
+        events.get_cursor().read(events).cloned().collect()
+    }
+
+    #[test]
+    fn test_disconnect() {
+        let mut app = app(Timeouts::default());
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Play);
+        app.update();
+        received(&mut client);
+
+        // The packets sent before are sent first
+        let mut commands = app.world_mut().commands();
+        commands.send_packet(entity, Clientbound::KeepAlive { id: 1 });
+        commands.disconnect(entity, "Bye");
+        app.world_mut().flush();
+        app.update();
+
+        let frames: Vec<Vec<u8>> = received(&mut client);
+        assert_eq!(frames[0], [0x00, 0x01]);
+        assert_eq!(frames[1][0], 0x40);
+        assert!(String::from_utf8_lossy(&frames[1]).contains("Bye"));
+        assert_eq!(frames.len(), 2);
+        assert!(app.world().get_entity(entity).is_err());
+        assert_eq!(
+            disconnected(&app),
+            [ClientDisconnected {
+                entity,
+                cause: DisconnectCause::Kicked(TextComponent::text("Bye")),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_login_timeout() {
+        let mut app = app(Timeouts {
+            handshake: Duration::ZERO,
+            login: Duration::ZERO,
+            ..Timeouts::default()
+        });
+        let (handshaking, mut handshaking_client) =
+            memory::connect(app.world_mut(), State::Handshaking);
+        let (login, mut login_client) = memory::connect(app.world_mut(), State::Login);
+        let (play, _play_client) = memory::connect(app.world_mut(), State::Play);
+        app.update();
+
+        // There is no disconnect packet before the login
+        assert!(received(&mut handshaking_client).is_empty());
+        let frames: Vec<Vec<u8>> = received(&mut login_client);
+        assert_eq!(frames[0][0], 0x00);
+        assert!(String::from_utf8_lossy(&frames[0]).contains("Timed out"));
+
+        let mut disconnected: Vec<Entity> = disconnected(&app)
+            .into_iter()
+            .inspect(|event| assert_eq!(event.cause, DisconnectCause::TimedOut))
+            .map(|event| event.entity)
+            .collect();
+        disconnected.sort();
+        assert_eq!(disconnected, [handshaking, login]);
+        assert!(app.world().get_entity(play).is_ok());
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let mut app = app(Timeouts::default());
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Play);
+        app.update();
+
+        let frames: Vec<Vec<u8>> = received(&mut client);
+        let mut keep_alive: &[u8] = &frames[0];
+        assert_eq!(u32::var_decode(&mut keep_alive).unwrap(), 0x00);
+        let id: i32 = i32::var_decode(&mut keep_alive).unwrap();
+
+        // A wrong answer is ignored
+        client.write_all(&[0x02, 0x00, 0x7F]).unwrap();
+        app.update();
+        assert!(app.world().get::<Latency>(entity).is_none());
+
+        let mut answer: Vec<u8> = vec![0x00];
+        id.var_encode(&mut answer).unwrap();
+        client.write_all(&[answer.len() as u8]).unwrap();
+        client.write_all(&answer).unwrap();
+        app.update();
+        assert!(app.world().get::<Latency>(entity).is_some());
+        // The next one is sent after the interval
+        assert!(received(&mut client).is_empty());
+
+        // A player that stops answering times out
+        app.insert_resource(Timeouts {
+            keep_alive_interval: Duration::ZERO,
+            keep_alive: Duration::ZERO,
+            ..Timeouts::default()
+        });
+        app.update();
+        app.update();
+        assert!(app.world().get_entity(entity).is_err());
+        assert_eq!(
+            disconnected(&app),
+            [ClientDisconnected {
+                entity,
+                cause: DisconnectCause::TimedOut,
+            }]
+        );
+        let frames: Vec<Vec<u8>> = received(&mut client);
+        assert_eq!(frames[0][0], 0x00);
+        assert_eq!(frames[1][0], 0x40);
+    }
+}
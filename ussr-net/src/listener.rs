@@ -0,0 +1,342 @@
+//! The sockets that accept connections, see [`UssrNetPlugin`](crate::UssrNetPlugin).
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+};
+#[cfg(unix)]
+use std::{
+    os::{
+        fd::OwnedFd,
+        unix::net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+};
+
+use bevy_ecs::prelude::*;
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use thiserror::Error;
+use tracing::warn;
+
+/// An address to accept connections on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ListenAddress {
+    /// A TCP address, IPv4 or IPv6. Port 0 picks a free port, see [`Listeners::local_addresses`].
+    /// IPv6 addresses only accept IPv6 connections, so `0.0.0.0` and `[::]` can both be used with the same port.
+    Tcp(SocketAddr),
+    /// A Unix domain socket, for a proxy on the same machine.
+    /// The file must not exist, and it is removed when the listener is dropped.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl From<SocketAddr> for ListenAddress {
+    fn from(address: SocketAddr) -> ListenAddress {
+        ListenAddress::Tcp(address)
+    }
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddress::Tcp(address) => write!(f, "{address}"),
+            #[cfg(unix)]
+            ListenAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("failed to listen on {address}: {source}")]
+pub struct BindError {
+    pub address: ListenAddress,
+    #[source]
+    pub source: io::Error,
+}
+
+/// The listening sockets of the server, which are non-blocking.
+#[derive(Resource, Debug, Default)]
+pub struct Listeners {
+    listeners: Vec<Listener>,
+    /// Set TCP_NODELAY on the accepted TCP connections.
+    nodelay: bool,
+}
+
+#[derive(Debug)]
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix {
+        listener: UnixListener,
+        path: PathBuf,
+    },
+}
+
+impl Listeners {
+    /// Listens on every address, failing if any of them can't be used.
+    pub(crate) fn bind(
+        addresses: &[ListenAddress],
+        backlog: i32,
+        nodelay: bool,
+    ) -> Result<Listeners, BindError> {
+        let listeners: Vec<Listener> = addresses
+            .iter()
+            .map(|address| {
+                Listener::bind(address, backlog).map_err(|source| BindError {
+                    address: address.clone(),
+                    source,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Listeners { listeners, nodelay })
+    }
+
+    /// The addresses that are listened on, with the ports chosen by the system for port 0.
+    pub fn local_addresses(&self) -> Vec<ListenAddress> {
+        self.listeners
+            .iter()
+            .filter_map(|listener| match listener {
+                Listener::Tcp(listener) => match listener.local_addr() {
+                    Ok(address) => Some(ListenAddress::Tcp(address)),
+                    Err(e) => {
+                        warn!("Failed to get listener address: {e}");
+                        None
+                    }
+                },
+                #[cfg(unix)]
+                Listener::Unix { path, .. } => Some(ListenAddress::Unix(path.clone())),
+            })
+            .collect()
+    }
+
+    /// Accepts every waiting connection, with the address of the client if it's a TCP connection.
+    pub(crate) fn accept(&self) -> Vec<(Stream, Option<SocketAddr>)> {
+        let mut accepted = Vec::new();
+        for listener in &self.listeners {
+            loop {
+                let result: io::Result<(Stream, Option<SocketAddr>)> = match listener {
+                    Listener::Tcp(listener) => listener
+                        .accept()
+                        .map(|(stream, address)| (Stream::Tcp(stream), Some(address))),
+                    #[cfg(unix)]
+                    Listener::Unix { listener, .. } => listener
+                        .accept()
+                        .map(|(stream, _)| (Stream::Unix(stream), None)),
+                };
+                match result {
+                    Ok((stream, address)) => {
+                        if let Stream::Tcp(stream) = &stream {
+                            if let Err(e) = stream.set_nodelay(self.nodelay) {
+                                warn!("Failed to set TCP_NODELAY: {e}");
+                            }
+                        }
+                        accepted.push((stream, address));
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        // Like running out of file descriptors, which may be over by the next update
+                        warn!("Failed to accept connection: {e}");
+                        break;
+                    }
+                }
+            }
+        }
+        accepted
+    }
+}
+
+impl Listener {
+    fn bind(address: &ListenAddress, backlog: i32) -> io::Result<Listener> {
+        match address {
+            ListenAddress::Tcp(address) => {
+                let socket = Socket::new(
+                    Domain::for_address(*address),
+                    Type::STREAM,
+                    Some(Protocol::TCP),
+                )?;
+                if address.is_ipv6() {
+                    socket.set_only_v6(true)?;
+                }
+                // Like std, so that the server can restart while old connections are closing
+                #[cfg(unix)]
+                socket.set_reuse_address(true)?;
+                socket.bind(&(*address).into())?;
+                socket.listen(backlog)?;
+                socket.set_nonblocking(true)?;
+                Ok(Listener::Tcp(socket.into()))
+            }
+            #[cfg(unix)]
+            ListenAddress::Unix(path) => {
+                let socket = Socket::new(Domain::UNIX, Type::STREAM, None)?;
+                socket.bind(&SockAddr::unix(path)?)?;
+                socket.listen(backlog)?;
+                socket.set_nonblocking(true)?;
+                Ok(Listener::Unix {
+                    listener: OwnedFd::from(socket).into(),
+                    path: path.clone(),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Listener::Unix { path, .. } = self {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("Failed to remove {}: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// An accepted socket.
+#[derive(Debug)]
+pub(crate) enum Stream {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Stream {
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_nonblocking(nonblocking),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.set_nonblocking(nonblocking),
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{Ipv4Addr, Ipv6Addr},
+        thread,
+    };
+
+    use bevy_app::prelude::*;
+
+    use super::*;
+    use crate::UssrNetPlugin;
+
+    /// Accepts a single connection, waiting for it to reach the listener.
+    fn accept_one(listeners: &Listeners) -> (Stream, Option<SocketAddr>) {
+        for _ in 0..1000 {
+            if let Some(accepted) = listeners.accept().pop() {
+                return accepted;
+            }
+            thread::yield_now();
+        }
+        panic!("Timed out");
+    }
+
+    #[test]
+    fn test_tcp_listeners() {
+        let listeners = Listeners::bind(
+            &[
+                SocketAddr::from((Ipv4Addr::LOCALHOST, 0)).into(),
+                SocketAddr::from((Ipv6Addr::LOCALHOST, 0)).into(),
+            ],
+            16,
+            true,
+        )
+        .unwrap();
+
+        let addresses: Vec<ListenAddress> = listeners.local_addresses();
+        assert_eq!(addresses.len(), 2);
+        for address in addresses {
+            let ListenAddress::Tcp(address) = address else {
+                panic!("{address} isn't a TCP address");
+            };
+            assert_ne!(address.port(), 0);
+
+            let mut client: TcpStream = TcpStream::connect(address).unwrap();
+            let (mut stream, peer) = accept_one(&listeners);
+            assert_eq!(peer, Some(client.local_addr().unwrap()));
+            let Stream::Tcp(tcp) = &stream else {
+                panic!("Accepted a Unix stream");
+            };
+            assert!(tcp.nodelay().unwrap());
+
+            client.write_all(b"ping").unwrap();
+            stream.set_nonblocking(false).unwrap();
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).unwrap();
+            assert_eq!(&buf, b"ping");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_listener() {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("ussr-net-test-{}.sock", std::process::id()));
+        let listeners = Listeners::bind(&[ListenAddress::Unix(path.clone())], 16, true).unwrap();
+        assert_eq!(
+            listeners.local_addresses(),
+            [ListenAddress::Unix(path.clone())]
+        );
+
+        let mut client: UnixStream = UnixStream::connect(&path).unwrap();
+        let (mut stream, peer) = accept_one(&listeners);
+        assert_eq!(peer, None);
+        stream.write_all(b"pong").unwrap();
+        let mut buf = [0; 4];
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"pong");
+
+        drop(listeners);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_bind_error() {
+        let used: TcpListener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let address: ListenAddress = used.local_addr().unwrap().into();
+
+        let error: BindError =
+            Listeners::bind(std::slice::from_ref(&address), 16, true).unwrap_err();
+        assert_eq!(error.address, address);
+        assert_eq!(error.source.kind(), io::ErrorKind::AddrInUse);
+
+        // The plugin makes the app exit with an error instead of panicking
+        let mut app = App::new();
+        app.add_plugins(UssrNetPlugin {
+            listeners: vec![address],
+            ..UssrNetPlugin::default()
+        });
+        app.update();
+        assert_eq!(app.should_exit(), Some(AppExit::error()));
+    }
+}
@@ -0,0 +1,585 @@
+//! The login sequence, which authenticates players with a session server in online mode.
+//!
+//! In offline mode, the login succeeds right after [`LoginStart`](Serverbound::LoginStart),
+//! with the UUID of [`offline_uuid`](crate::offline_uuid).
+//! In online mode, the server sends the public key of its [`OnlineMode`]
+//! in [`EncryptionRequest`](Clientbound::EncryptionRequest), with a random verify token.
+//! The client encrypts a shared secret and the token with it in
+//! [`EncryptionResponse`](Serverbound::EncryptionResponse), after which both directions are encrypted.
+//! The [`Authenticator`] then checks that the player joined this server,
+//! and the login succeeds with their [`GameProfile`].
+//! Behind a proxy with [`BungeeCordForwarding`](crate::BungeeCordForwarding),
+//! the login succeeds right away with the profile forwarded by the proxy.
+//! Either way, the connection then becomes a [`Player`](crate::Player),
+//! with [`PlayerBundle`](crate::PlayerBundle).
+
+use std::{sync::Arc, thread};
+
+use async_channel::{Receiver, TryRecvError};
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{instrument, trace};
+use ussr_protocol::neutral::{Clientbound, Serverbound};
+use uuid::Uuid;
+
+use crate::{
+    crypto::{server_hash, KeyError, ServerKey, StreamCipher},
+    player::{join_game, offline_uuid, valid_username, JoinGameSettings, Player},
+    proxy::Forwarded,
+    Connection, DisconnectCause, Outbox, PacketReceived, Status,
+};
+
+/// The server ID sent in [`EncryptionRequest`](Clientbound::EncryptionRequest).
+/// It has been empty since 1.7.
+const SERVER_ID: &str = "";
+
+/// Authenticate players, and encrypt their connection.
+/// Without this resource, players are not sent [`EncryptionRequest`](Clientbound::EncryptionRequest).
+#[derive(Resource, Clone)]
+pub struct OnlineMode {
+    authenticator: Arc<dyn Authenticator>,
+    key: Arc<ServerKey>,
+}
+
+impl OnlineMode {
+    /// Generates the RSA key of the server, which takes a moment.
+    pub fn new(authenticator: impl Authenticator) -> Result<OnlineMode, KeyError> {
+        Ok(OnlineMode {
+            authenticator: Arc::new(authenticator),
+            key: Arc::new(ServerKey::generate()?),
+        })
+    }
+}
+
+/// Checks that a player joined this server with the session server, which the client does before
+/// sending [`EncryptionResponse`](Serverbound::EncryptionResponse).
+///
+/// It is called on its own thread, so it may block.
+/// There is no HTTP client in ussr-net: an implementation for Mojang's session server
+/// makes a GET request to [`session_server_url`], where a `200` response is the [`GameProfile`] as JSON,
+/// and a `204` response is [`AuthError::NotJoined`].
+pub trait Authenticator: Send + Sync + 'static {
+    fn authenticate(&self, username: &str, server_hash: &str) -> Result<GameProfile, AuthError>;
+}
+
+impl<F> Authenticator for F
+where
+    F: Fn(&str, &str) -> Result<GameProfile, AuthError> + Send + Sync + 'static,
+{
+    fn authenticate(&self, username: &str, server_hash: &str) -> Result<GameProfile, AuthError> {
+        self(username, server_hash)
+    }
+}
+
+/// The URL of Mojang's session server that checks that `username` joined the server identified by `server_hash`.
+pub fn session_server_url(username: &str, server_hash: &str) -> String {
+    let username: String = username
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect();
+    format!(
+        "https://sessionserver.mojang.com/session/minecraft/hasJoined?username={username}&serverId={server_hash}"
+    )
+}
+
+/// The profile of an authenticated player, as returned by the session server.
+/// This component is added to the connection once the login succeeds.
+#[derive(Component, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameProfile {
+    pub id: Uuid,
+    pub name: String,
+    /// Usually only the `textures` of the skin and cape.
+    #[serde(default)]
+    pub properties: Vec<ProfileProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("the player did not join the server")]
+    NotJoined,
+
+    #[error("the session server is unavailable: {0}")]
+    Unavailable(String),
+}
+
+impl AuthError {
+    /// The disconnect reason shown to the player, which is the same as vanilla.
+    fn reason(&self) -> &'static str {
+        match self {
+            AuthError::NotJoined => "Failed to verify username!",
+            AuthError::Unavailable(_) => {
+                "Authentication servers are down. Please try again later, sorry!"
+            }
+        }
+    }
+}
+
+/// The progress of an online login.
+#[derive(Component)]
+pub(crate) enum Login {
+    /// [`EncryptionRequest`](Clientbound::EncryptionRequest) was sent.
+    Encrypting {
+        username: String,
+        verify_token: [u8; 4],
+    },
+    /// The [`Authenticator`] is running.
+    Authenticating(Receiver<Result<GameProfile, AuthError>>),
+}
+
+impl Connection {
+    /// Encrypts everything sent and received from now on, including the data received but not processed yet.
+    fn enable_encryption(&mut self, shared_secret: &[u8; 16]) {
+        let mut cipher = StreamCipher::new(shared_secret);
+        cipher.decrypt(&mut self.incoming_buf);
+        self.cipher = Some(cipher);
+        self.awaiting_key = false;
+    }
+}
+
+/// A system that finishes logins in offline mode,
+/// or starts their encryption and authentication if there is [`OnlineMode`].
+#[instrument(skip_all, level = "trace")]
+pub(crate) fn handle_login(
+    mut commands: Commands,
+    online_mode: Option<Res<OnlineMode>>,
+    settings: Res<JoinGameSettings>,
+    status: Res<Status>,
+    mut events: EventReader<PacketReceived>,
+    mut query: Query<(
+        &mut Connection,
+        &mut Outbox,
+        Option<&Login>,
+        Option<&Forwarded>,
+    )>,
+    joined: Query<(), With<Player>>,
+) {
+    // The components inserted for a login start are only visible after this system,
+    // so the logins started by this batch are tracked here
+    let mut started: EntityHashSet = EntityHashSet::default();
+
+    for event in events.read() {
+        let Ok((mut connection, mut outbox, login, forwarded)) = query.get_mut(event.entity) else {
+            continue;
+        };
+        if connection.closing.is_some() {
+            continue;
+        }
+
+        match &event.packet {
+            Serverbound::LoginStart { username } => {
+                if login.is_some() || joined.contains(event.entity) || !started.insert(event.entity)
+                {
+                    trace!("Unexpected login start, disconnecting");
+                    connection
+                        .disconnect(DisconnectCause::Kicked("Unexpected hello packet".into()));
+                    continue;
+                }
+                if !valid_username(username) {
+                    trace!("Invalid username {username:?}, disconnecting");
+                    connection.disconnect(DisconnectCause::Kicked("Invalid username".into()));
+                    continue;
+                }
+                if let Some(Forwarded { id, properties }) = forwarded {
+                    join_game(
+                        &mut commands,
+                        event.entity,
+                        &mut outbox,
+                        username.clone(),
+                        *id,
+                        &settings,
+                        status.0.players.max,
+                    );
+                    commands
+                        .entity(event.entity)
+                        .remove::<Forwarded>()
+                        .insert(GameProfile {
+                            id: *id,
+                            name: username.clone(),
+                            properties: properties.clone(),
+                        });
+                    continue;
+                }
+                let Some(online_mode) = &online_mode else {
+                    join_game(
+                        &mut commands,
+                        event.entity,
+                        &mut outbox,
+                        username.clone(),
+                        offline_uuid(username),
+                        &settings,
+                        status.0.players.max,
+                    );
+                    continue;
+                };
+                let verify_token: [u8; 4] = rand::random();
+                outbox.push(Clientbound::EncryptionRequest {
+                    server_id: SERVER_ID.to_owned(),
+                    public_key: online_mode.key.public_key_der().to_vec(),
+                    verify_token: verify_token.to_vec(),
+                });
+                commands.entity(event.entity).insert(Login::Encrypting {
+                    username: username.clone(),
+                    verify_token,
+                });
+            }
+
+            Serverbound::EncryptionResponse {
+                shared_secret,
+                verify_token,
+            } => {
+                let (
+                    Some(online_mode),
+                    Some(Login::Encrypting {
+                        username,
+                        verify_token: expected_token,
+                    }),
+                ) = (&online_mode, login)
+                else {
+                    trace!("Unexpected encryption response, disconnecting");
+                    connection.disconnect(DisconnectCause::InvalidData(
+                        "unexpected encryption response".to_owned(),
+                    ));
+                    continue;
+                };
+
+                let key: &ServerKey = &online_mode.key;
+                let shared_secret: Option<[u8; 16]> = key
+                    .decrypt(shared_secret)
+                    .and_then(|secret| secret.try_into().ok());
+                let valid_token: bool = key
+                    .decrypt(verify_token)
+                    .is_some_and(|token| token == expected_token);
+                let (Some(shared_secret), true) = (shared_secret, valid_token) else {
+                    trace!("Invalid encryption response, disconnecting");
+                    connection.disconnect(DisconnectCause::InvalidData(
+                        "invalid encryption response".to_owned(),
+                    ));
+                    continue;
+                };
+                connection.enable_encryption(&shared_secret);
+
+                let server_hash: String =
+                    server_hash(SERVER_ID, &shared_secret, key.public_key_der());
+                let authenticator: Arc<dyn Authenticator> = online_mode.authenticator.clone();
+                let username: String = username.clone();
+                let (sender, receiver) = async_channel::bounded(1);
+                // If the thread can't be spawned, the channel is closed and the login fails
+                let _ = thread::Builder::new()
+                    .name(format!("ussr-net authenticate {username}"))
+                    .spawn(move || {
+                        let _ = sender
+                            .send_blocking(authenticator.authenticate(&username, &server_hash));
+                    });
+                commands
+                    .entity(event.entity)
+                    .insert(Login::Authenticating(receiver));
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// A system that finishes the logins whose [`Authenticator`] returned.
+#[instrument(skip_all, level = "trace")]
+pub(crate) fn poll_authentication(
+    mut commands: Commands,
+    settings: Res<JoinGameSettings>,
+    status: Res<Status>,
+    mut query: Query<(Entity, &mut Connection, &mut Outbox, &Login)>,
+) {
+    for (entity, mut connection, mut outbox, login) in &mut query {
+        let Login::Authenticating(receiver) = login else {
+            continue;
+        };
+        let result: Result<GameProfile, AuthError> = match receiver.try_recv() {
+            Ok(result) => result,
+            Err(TryRecvError::Empty) => continue,
+            Err(TryRecvError::Closed) => Err(AuthError::Unavailable(
+                "the authenticator did not return".to_owned(),
+            )),
+        };
+        commands.entity(entity).remove::<Login>();
+
+        match result {
+            Ok(profile) => {
+                trace!("Authenticated {profile:?}");
+                join_game(
+                    &mut commands,
+                    entity,
+                    &mut outbox,
+                    profile.name.clone(),
+                    profile.id,
+                    &settings,
+                    status.0.players.max,
+                );
+                commands.entity(entity).insert(profile);
+            }
+            Err(e) => {
+                trace!("Authentication failed: {e}, disconnecting");
+                connection.disconnect(DisconnectCause::Kicked(e.reason().into()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, sync::Mutex, time::Duration};
+
+    use bevy_app::prelude::*;
+    use ussr_buf::{read_frame, VarDecode, VarEncode};
+    use ussr_protocol::State;
+
+    use super::*;
+    use crate::{
+        memory, process_data::process_data, read_data, send::encode_packets, send_data,
+        ClientDisconnected, PlayerUuid, MAX_PACKET_SIZE,
+    };
+
+    const SHARED_SECRET: [u8; 16] = *b"0123456789abcdef";
+
+    /// The arguments the authenticator was called with.
+    type Calls = Arc<Mutex<Vec<(String, String)>>>;
+
+    fn online_app(result: fn() -> Result<GameProfile, AuthError>) -> (App, Calls) {
+        let calls: Calls = Calls::default();
+        let authenticator_calls: Calls = calls.clone();
+        let authenticator = move |username: &str, server_hash: &str| {
+            authenticator_calls
+                .lock()
+                .unwrap()
+                .push((username.to_owned(), server_hash.to_owned()));
+            result()
+        };
+
+        let mut app = App::new();
+        app.init_resource::<Status>()
+            .init_resource::<JoinGameSettings>()
+            .insert_resource(OnlineMode::new(authenticator).unwrap())
+            .add_event::<PacketReceived>()
+            .add_event::<ClientDisconnected>()
+            .add_systems(
+                Update,
+                (
+                    read_data,
+                    process_data,
+                    handle_login,
+                    poll_authentication,
+                    encode_packets,
+                    send_data,
+                )
+                    .chain(),
+            );
+        (app, calls)
+    }
+
+    fn notch() -> Result<GameProfile, AuthError> {
+        Ok(GameProfile {
+            id: Uuid::parse_str("069a79f444e94726a5befca90e38aaf5").unwrap(),
+            name: "Notch".to_owned(),
+            properties: Vec::new(),
+        })
+    }
+
+    /// Updates the app until the client receives something.
+    fn receive(app: &mut App, client: &mut memory::MemoryClient) -> Vec<u8> {
+        for _ in 0..1000 {
+            app.update();
+            let buf: Vec<u8> = client.read_available();
+            if !buf.is_empty() {
+                return buf;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("Nothing received");
+    }
+
+    /// Connects a 1.8.9 client, and returns its verify token from the encryption request.
+    fn start_login(app: &mut App) -> (Entity, memory::MemoryClient, Vec<u8>) {
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Handshaking);
+
+        #[rustfmt::skip]
+        client.write_all(&[
+            // Handshake
+            0x0F, 0x00, 0x2F,
+            0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+            0x63, 0xDD, 0x02,
+            // Login start
+            0x07, 0x00, 0x05, b'N', b'o', b't', b'c', b'h',
+        ])
+        .unwrap();
+
+        let buf: Vec<u8> = receive(app, &mut client);
+        let mut frame: &[u8] = &read_frame(&mut &buf[..], MAX_PACKET_SIZE).unwrap();
+        let array = |frame: &mut &[u8]| {
+            let len: usize = usize::var_decode(frame).unwrap();
+            let (array, rest) = frame.split_at(len);
+            *frame = rest;
+            array.to_vec()
+        };
+        assert_eq!(u32::var_decode(&mut frame).unwrap(), 0x01);
+        assert!(array(&mut frame).is_empty());
+        let online_mode = app.world().resource::<OnlineMode>();
+        assert_eq!(array(&mut frame), online_mode.key.public_key_der());
+        let verify_token: Vec<u8> = array(&mut frame);
+        assert_eq!(verify_token.len(), 4);
+        assert!(frame.is_empty());
+
+        (entity, client, verify_token)
+    }
+
+    /// Sends an encryption response, encrypted with the public key of the server.
+    fn respond(app: &App, client: &mut memory::MemoryClient, verify_token: &[u8]) {
+        let key: &ServerKey = &app.world().resource::<OnlineMode>().key;
+        let mut frame: Vec<u8> = vec![0x01];
+        for data in [&SHARED_SECRET[..], verify_token] {
+            let encrypted: Vec<u8> = key.encrypt(data);
+            encrypted.len().var_encode(&mut frame).unwrap();
+            frame.extend_from_slice(&encrypted);
+        }
+        let mut buf: Vec<u8> = Vec::new();
+        frame.len().var_encode(&mut buf).unwrap();
+        buf.extend_from_slice(&frame);
+        client.write_all(&buf).unwrap();
+    }
+
+    #[test]
+    fn test_online_login() {
+        let (mut app, calls) = online_app(notch);
+        let (entity, mut client, verify_token) = start_login(&mut app);
+        respond(&app, &mut client, &verify_token);
+
+        let mut cipher = StreamCipher::new(&SHARED_SECRET);
+        let mut buf: Vec<u8> = receive(&mut app, &mut client);
+        cipher.decrypt(&mut buf);
+        let mut expected: Vec<u8> = vec![0x02, 0x24];
+        expected.extend_from_slice(b"069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        expected.extend_from_slice(&[0x05, b'N', b'o', b't', b'c', b'h']);
+        let mut reader: &[u8] = &buf;
+        assert_eq!(read_frame(&mut reader, MAX_PACKET_SIZE).unwrap(), expected);
+        // Join game
+        assert_eq!(read_frame(&mut reader, MAX_PACKET_SIZE).unwrap()[0], 0x01);
+
+        let key: &ServerKey = &app.world().resource::<OnlineMode>().key;
+        let hash: String = server_hash(SERVER_ID, &SHARED_SECRET, key.public_key_der());
+        assert_eq!(*calls.lock().unwrap(), [("Notch".to_owned(), hash)]);
+        let profile: GameProfile = notch().unwrap();
+        assert_eq!(app.world().get::<GameProfile>(entity), Some(&profile));
+        assert_eq!(
+            app.world().get::<PlayerUuid>(entity),
+            Some(&PlayerUuid(profile.id))
+        );
+        assert!(app.world().get::<Login>(entity).is_none());
+        assert_eq!(
+            app.world().get::<Connection>(entity).unwrap().state,
+            State::Play
+        );
+
+        // The client encrypts what it sends too
+        let mut keep_alive: Vec<u8> = vec![0x02, 0x00, 0x2A];
+        cipher.encrypt(&mut keep_alive);
+        client.write_all(&keep_alive).unwrap();
+        app.update();
+        let events = app.world().resource::<Events<PacketReceived>>();
+        assert_eq!(
+            events.get_cursor().read(events).last().unwrap().packet,
+            Serverbound::KeepAlive { id: 42 }
+        );
+    }
+
+    #[test]
+    fn test_failed_login() {
+        // A wrong verify token
+        let (mut app, calls) = online_app(notch);
+        let (entity, mut client, mut verify_token) = start_login(&mut app);
+        verify_token[0] ^= 1;
+        respond(&app, &mut client, &verify_token);
+
+        let buf: Vec<u8> = receive(&mut app, &mut client);
+        assert_eq!(read_frame(&mut &buf[..], MAX_PACKET_SIZE).unwrap()[0], 0x00);
+        assert!(app.world().get_entity(entity).is_err());
+        assert!(calls.lock().unwrap().is_empty());
+
+        // The player didn't join the server
+        let (mut app, _) = online_app(|| Err(AuthError::NotJoined));
+        let (entity, mut client, verify_token) = start_login(&mut app);
+        respond(&app, &mut client, &verify_token);
+
+        let mut buf: Vec<u8> = receive(&mut app, &mut client);
+        StreamCipher::new(&SHARED_SECRET).decrypt(&mut buf);
+        let frame: Vec<u8> = read_frame(&mut &buf[..], MAX_PACKET_SIZE).unwrap();
+        assert_eq!(frame[0], 0x00);
+        assert!(String::from_utf8_lossy(&frame).contains("Failed to verify username!"));
+        assert!(app.world().get_entity(entity).is_err());
+    }
+
+    #[test]
+    fn test_second_login_start() {
+        let assert_kicked = |app: &mut App, client: &mut memory::MemoryClient, entity: Entity| {
+            let buf: Vec<u8> = receive(app, client);
+            let frame: Vec<u8> = read_frame(&mut &buf[..], MAX_PACKET_SIZE).unwrap();
+            assert_eq!(frame[0], 0x00);
+            assert!(String::from_utf8_lossy(&frame).contains("Unexpected hello packet"));
+            assert!(app.world().get_entity(entity).is_err());
+        };
+
+        // Both in the same batch, in offline mode
+        let (mut app, _) = online_app(notch);
+        app.world_mut().remove_resource::<OnlineMode>();
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Handshaking);
+        #[rustfmt::skip]
+        client.write_all(&[
+            // Handshake
+            0x0F, 0x00, 0x2F,
+            0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+            0x63, 0xDD, 0x02,
+            // Login start, twice
+            0x07, 0x00, 0x05, b'N', b'o', b't', b'c', b'h',
+            0x07, 0x00, 0x05, b'N', b'o', b't', b'c', b'h',
+        ])
+        .unwrap();
+        assert_kicked(&mut app, &mut client, entity);
+
+        // While encrypting, in online mode
+        let (mut app, _) = online_app(notch);
+        let (entity, mut client, _) = start_login(&mut app);
+        client
+            .write_all(&[0x07, 0x00, 0x05, b'N', b'o', b't', b'c', b'h'])
+            .unwrap();
+        assert_kicked(&mut app, &mut client, entity);
+    }
+
+    #[test]
+    fn test_game_profile() {
+        let profile: GameProfile = serde_json::from_str(
+            r#"{
+                "id": "069a79f444e94726a5befca90e38aaf5",
+                "name": "Notch",
+                "properties": [{ "name": "textures", "value": "e30=", "signature": "c2ln" }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(profile.id, notch().unwrap().id);
+        assert_eq!(profile.properties[0].signature.as_deref(), Some("c2ln"));
+
+        assert_eq!(
+            session_server_url("a b&c", "-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"),
+            "https://sessionserver.mojang.com/session/minecraft/hasJoined?username=a%20b%26c&serverId=-7c9d5b0044c130109a5d7b5fb5c317c02b4e28c1"
+        );
+    }
+}
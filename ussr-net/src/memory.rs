@@ -0,0 +1,98 @@
+//! In-memory connections, used to replay captures and in tests.
+
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use async_channel::{Receiver, Sender, TryRecvError};
+use bevy_ecs::prelude::*;
+use ussr_protocol::State;
+
+use crate::{transport::Transport, Connection};
+
+/// The client end of an in-memory connection, see [`pair`].
+/// Like a non-blocking socket, reading returns [`ErrorKind::WouldBlock`] if there is no data.
+/// Dropping it closes the connection.
+#[derive(Debug)]
+pub(crate) struct MemoryClient {
+    incoming: Receiver<Vec<u8>>,
+    outgoing: Sender<Vec<u8>>,
+    /// The number of bytes sent by the server that weren't received yet.
+    queued: Arc<AtomicUsize>,
+    /// The rest of the last received data that didn't fit in a read.
+    pending: Vec<u8>,
+}
+
+/// Creates both ends of an in-memory connection.
+pub(crate) fn pair() -> (Transport, MemoryClient) {
+    let (client_sender, server_receiver) = async_channel::unbounded();
+    let (server_sender, client_receiver) = async_channel::unbounded();
+    let queued: Arc<AtomicUsize> = Arc::default();
+    let client = MemoryClient {
+        incoming: client_receiver,
+        outgoing: client_sender,
+        queued: queued.clone(),
+        pending: Vec::new(),
+    };
+    (
+        Transport::new(server_receiver, server_sender, queued),
+        client,
+    )
+}
+
+/// Spawns a connection in `state`, and returns its entity and client.
+pub(crate) fn connect(world: &mut World, state: State) -> (Entity, MemoryClient) {
+    let (transport, client) = pair();
+    let mut connection: Connection = Connection::new(transport);
+    connection.state = state;
+    (world.spawn(connection).id(), client)
+}
+
+impl MemoryClient {
+    /// Reads everything the server sent so far, without waiting for more.
+    pub(crate) fn read_available(&mut self) -> Vec<u8> {
+        let mut buf: Vec<u8> = std::mem::take(&mut self.pending);
+        while let Ok(data) = self.incoming.try_recv() {
+            self.queued.fetch_sub(data.len(), Ordering::Relaxed);
+            buf.extend_from_slice(&data);
+        }
+        buf
+    }
+}
+
+impl Read for MemoryClient {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            match self.incoming.try_recv() {
+                Ok(data) => {
+                    self.queued.fetch_sub(data.len(), Ordering::Relaxed);
+                    self.pending = data;
+                }
+                Err(TryRecvError::Empty) => return Err(ErrorKind::WouldBlock.into()),
+                Err(TryRecvError::Closed) => return Ok(0),
+            }
+        }
+
+        let n: usize = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+}
+
+impl Write for MemoryClient {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.outgoing
+            .try_send(buf.to_vec())
+            .map_err(|_| io::Error::from(ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+//! Players, which are connections that finished the login.
+//!
+//! Once the login succeeds, [`LoginSuccess`](Clientbound::LoginSuccess) and [`JoinGame`](Clientbound::JoinGame)
+//! are sent, and [`PlayerBundle`] is added to the entity of the connection,
+//! which is the entity of the player until it disconnects.
+//! Connections that are still in the status or login state have an [`Outbox`] too,
+//! so systems of the game should only query entities with [`Player`].
+
+use bevy_ecs::prelude::*;
+use md5::{Digest, Md5};
+use ussr_protocol::{
+    enums::{Difficulty, Dimension, Gamemode},
+    neutral::Clientbound,
+};
+use uuid::{Builder, Uuid};
+
+use crate::Outbox;
+
+/// The maximum length of a username.
+const MAX_USERNAME_LENGTH: usize = 16;
+
+/// Marks the connections that joined the game, and are in the play state.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Player;
+
+#[derive(Component, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Username(pub String);
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlayerUuid(pub Uuid);
+
+/// The components added to a connection once it joined the game.
+#[derive(Bundle, Debug, Clone)]
+pub struct PlayerBundle {
+    pub player: Player,
+    pub username: Username,
+    pub uuid: PlayerUuid,
+}
+
+/// The world sent to players in [`JoinGame`](Clientbound::JoinGame).
+/// The maximum number of players is the one of [`Status`](crate::Status).
+#[derive(Resource, Debug, Clone)]
+pub struct JoinGameSettings {
+    pub gamemode: Gamemode,
+    pub hardcore: bool,
+    pub dimension: Dimension,
+    pub difficulty: Difficulty,
+    pub level_type: String,
+    pub reduced_debug_info: bool,
+}
+
+impl Default for JoinGameSettings {
+    fn default() -> JoinGameSettings {
+        JoinGameSettings {
+            gamemode: Gamemode::Survival,
+            hardcore: false,
+            dimension: Dimension::Overworld,
+            difficulty: Difficulty::Normal,
+            level_type: "default".to_owned(),
+            reduced_debug_info: false,
+        }
+    }
+}
+
+/// Whether a username is between 1 and 16 letters, digits and underscores, like vanilla usernames.
+pub(crate) fn valid_username(username: &str) -> bool {
+    (1..=MAX_USERNAME_LENGTH).contains(&username.len())
+        && username
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+}
+
+/// The UUID of a player in offline mode, which is the MD5 UUID of `OfflinePlayer:<username>`, like vanilla.
+pub fn offline_uuid(username: &str) -> Uuid {
+    let digest = Md5::new()
+        .chain_update("OfflinePlayer:")
+        .chain_update(username)
+        .finalize();
+    Builder::from_md5_bytes(digest.into()).into_uuid()
+}
+
+/// Sends [`LoginSuccess`](Clientbound::LoginSuccess) and [`JoinGame`](Clientbound::JoinGame),
+/// and adds [`PlayerBundle`] to `entity`.
+pub(crate) fn join_game(
+    commands: &mut Commands,
+    entity: Entity,
+    outbox: &mut Outbox,
+    username: String,
+    uuid: Uuid,
+    settings: &JoinGameSettings,
+    max_players: i32,
+) {
+    outbox.push(Clientbound::LoginSuccess {
+        uuid,
+        username: username.clone(),
+    });
+    outbox.push(Clientbound::JoinGame {
+        // Entities are only spawned by the server, so their index is unique
+        entity_id: entity.index() as i32,
+        hardcore: settings.hardcore,
+        gamemode: settings.gamemode,
+        dimension: settings.dimension,
+        difficulty: settings.difficulty,
+        max_players: max_players.clamp(0, u8::MAX.into()) as u8,
+        level_type: settings.level_type.clone(),
+        reduced_debug_info: settings.reduced_debug_info,
+    });
+    commands.entity(entity).insert(PlayerBundle {
+        player: Player,
+        username: Username(username),
+        uuid: PlayerUuid(uuid),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_username() {
+        assert!(valid_username("Notch"));
+        assert!(valid_username("jeb_"));
+        assert!(valid_username("a"));
+        assert!(valid_username("abcdefghijklmnop"));
+        assert!(!valid_username(""));
+        assert!(!valid_username("abcdefghijklmnopq"));
+        assert!(!valid_username("Not ch"));
+        assert!(!valid_username("Notch!"));
+        assert!(!valid_username("Nötch"));
+    }
+
+    #[test]
+    fn test_offline_uuid() {
+        assert_eq!(
+            offline_uuid("Notch").to_string(),
+            "b50ad385-829d-3141-a216-7e7d7539ba7f"
+        );
+        assert_eq!(
+            offline_uuid("jeb_").to_string(),
+            "a762f560-4fce-3236-812a-b80efff0b62b"
+        );
+    }
+}
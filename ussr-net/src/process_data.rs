@@ -1,16 +1,62 @@
-use std::io::{Cursor, Read};
+use std::{
+    borrow::Cow,
+    io::{Cursor, Read},
+};
 
 use bevy_ecs::prelude::*;
 use bytes::{Buf, BytesMut};
 use tracing::{instrument, trace, warn};
 use ussr_buf::{DecodeError, VarDecode};
-use ussr_protocol::PacketDecodeError;
+use ussr_protocol::{
+    legacy_ping::{LegacyPing, LEGACY_PING_ID},
+    neutral::Serverbound,
+    Direction, NextState, PacketDecodeError, ProtocolVersion, State, TextComponent,
+};
 
-use crate::{Connection, MAX_PACKET_SIZE};
+use crate::{
+    compression::decompress_frame, proxy::AwaitingProxyHeader, Connection, DisconnectCause,
+    PacketReceived, RateLimits, Status, MAX_PACKET_SIZE,
+};
 
 #[instrument(skip_all, level = "trace")]
-pub(crate) fn process_data(mut commands: Commands, mut query: Query<(Entity, &mut Connection)>) {
+pub(crate) fn process_data(
+    status: Res<Status>,
+    limits: Option<Res<RateLimits>>,
+    mut events: EventWriter<PacketReceived>,
+    mut query: Query<(Entity, &mut Connection), Without<AwaitingProxyHeader>>,
+) {
     'entities: for (entity, mut connection) in &mut query {
+        if connection.closing.is_some() || connection.awaiting_key {
+            continue;
+        }
+
+        // Clients before 1.7 start with a legacy ping instead of a frame.
+        // Frames with a length of 254, 382… start with the same byte, so they are processed as usual if it isn't a ping.
+        if connection.state == State::Handshaking
+            && connection.incoming_buf.first() == Some(&LEGACY_PING_ID)
+        {
+            match LegacyPing::decode(&connection.incoming_buf) {
+                Ok(Some((ping, _))) => {
+                    trace!("{ping:?}");
+                    let mut response: Vec<u8> = Vec::new();
+                    connection.incoming_buf.clear();
+                    match ping.encode_response(&status.0, &mut response) {
+                        Ok(()) => {
+                            connection.outgoing_buf.extend_from_slice(&response);
+                            connection.disconnect(DisconnectCause::Finished);
+                        }
+                        Err(e) => {
+                            warn!("Failed to send legacy ping response: {e}, disconnecting");
+                            connection.disconnect(DisconnectCause::SendFailed(e.to_string()));
+                        }
+                    }
+                    continue;
+                }
+                Ok(None) => continue,
+                Err(e) => trace!("Not a legacy ping: {e}"),
+            }
+        }
+
         while !connection.incoming_buf.is_empty() {
             let mut buf: Cursor<&[u8]> = Cursor::new(&connection.incoming_buf[..]);
             let len_before_length = buf.remaining(); // Length of the buffer before reading packet length
@@ -22,8 +68,10 @@ pub(crate) fn process_data(mut commands: Commands, mut query: Query<(Entity, &mu
 
                     // Check that the packet length is valid
                     if packet_length > MAX_PACKET_SIZE {
-                        trace!("Invalid packet length, despawning entity");
-                        commands.entity(entity).despawn();
+                        trace!("Invalid packet length, disconnecting");
+                        connection.disconnect(DisconnectCause::InvalidData(format!(
+                            "packet is too large ({packet_length} bytes)"
+                        )));
                         continue 'entities;
                     }
 
@@ -32,6 +80,15 @@ pub(crate) fn process_data(mut commands: Commands, mut query: Query<(Entity, &mu
                         continue 'entities;
                     }
 
+                    let frame_length: usize = len_before_length - len_after_length + packet_length;
+                    if let Some(limits) = &limits {
+                        if !connection.count_frame(limits, frame_length) {
+                            trace!("Over the packet budget, disconnecting");
+                            connection.disconnect(DisconnectCause::RateLimited);
+                            continue 'entities;
+                        }
+                    }
+
                     // Remove the packet length from the buffer
                     connection
                         .incoming_buf
@@ -39,40 +96,62 @@ pub(crate) fn process_data(mut commands: Commands, mut query: Query<(Entity, &mu
 
                     // Split the buffer into the packet data and the rest
                     let packet_data: BytesMut = connection.incoming_buf.split_to(packet_length);
+                    let packet_data: Cow<[u8]> = match connection.compression {
+                        Some(threshold) => match decompress_frame(&packet_data, threshold) {
+                            Ok(packet_data) => Cow::Owned(packet_data),
+                            Err(e) => {
+                                trace!("Invalid compressed packet: {e}, disconnecting");
+                                connection.disconnect(DisconnectCause::InvalidData(e.to_string()));
+                                continue 'entities;
+                            }
+                        },
+                        None => Cow::Borrowed(&packet_data),
+                    };
+                    connection.capture_frame(Direction::Serverbound, &packet_data);
                     let mut packet_data: Cursor<&[u8]> = Cursor::new(&packet_data[..]);
 
                     let Ok(packet_id) = u32::var_decode(&mut packet_data) else {
-                        trace!("Invalid packet id, despawning entity");
-                        commands.entity(entity).despawn();
+                        trace!("Invalid packet id, disconnecting");
+                        connection.disconnect(DisconnectCause::InvalidData(
+                            "invalid packet id".to_owned(),
+                        ));
                         continue 'entities;
                     };
 
                     // Parse the packet
                     match parse_packet(
-                        // &mut commands,
-                        // entity,
+                        entity,
                         &mut connection,
+                        &mut events,
                         packet_id,
                         &mut packet_data,
                     ) {
-                        Ok(_) => {
+                        Ok(next_state) => {
                             // Ensure that packet data is empty.
                             // If it's not, this means that packet length was invalid.
                             if packet_data.remaining() != 0 {
-                                trace!("Leftover packet data, despawning entity");
-                                commands.entity(entity).despawn();
+                                trace!("Leftover packet data, disconnecting");
+                                connection.disconnect(DisconnectCause::InvalidData(
+                                    "leftover packet data".to_owned(),
+                                ));
+                                continue 'entities;
+                            }
+
+                            // Update the connection state
+                            connection.state = next_state;
+                            if connection.closing.is_some() {
                                 continue 'entities;
                             }
 
-                            // // Update the connection state
-                            // if let Some(state) = state {
-                            //     connection.state = state;
-                            // }
+                            // The rest of the data is encrypted with the key in this packet
+                            if connection.awaiting_key {
+                                continue 'entities;
+                            }
                         }
 
                         Err(e) => {
-                            trace!("Parse error: {e:?}, despawning entity");
-                            commands.entity(entity).despawn();
+                            trace!("Parse error: {e}, disconnecting");
+                            connection.disconnect(DisconnectCause::InvalidData(e.to_string()));
                             continue 'entities;
                         }
                     }
@@ -80,8 +159,10 @@ pub(crate) fn process_data(mut commands: Commands, mut query: Query<(Entity, &mu
 
                 // The packet length was invalid
                 Err(DecodeError::InvalidVarInt) => {
-                    trace!("Invalid packet length, despawning entity");
-                    commands.entity(entity).despawn();
+                    trace!("Invalid packet length, disconnecting");
+                    connection.disconnect(DisconnectCause::InvalidData(
+                        "invalid packet length".to_owned(),
+                    ));
                     continue 'entities;
                 }
 
@@ -93,16 +174,321 @@ pub(crate) fn process_data(mut commands: Commands, mut query: Query<(Entity, &mu
     }
 }
 
-/// This function will parse and dispatch a packet as a [`PacketEvent`].
-/// It will return a state to transition to.
+/// This function will parse and dispatch a packet as a [`PacketReceived`] event.
+/// It will return the state to transition to, or an error if the packet is not allowed in the current state.
+/// A client that wants to log in with an unsupported protocol version is disconnected instead.
 #[instrument(skip_all, level = "trace")]
 fn parse_packet(
-    // commands: &mut Commands,
-    // entity: Entity,
+    entity: Entity,
     connection: &mut Connection,
+    events: &mut EventWriter<PacketReceived>,
     packet_id: u32,
     reader: &mut impl Read,
-) -> Result<(), PacketDecodeError> {
-    let _ = (connection, packet_id, reader);
-    todo!()
+) -> Result<State, PacketDecodeError> {
+    let packet: Serverbound =
+        connection
+            .version
+            .decode_serverbound(connection.state, packet_id, reader)?;
+    trace!("{packet:?}");
+
+    let state: State = connection.state.next_serverbound(&packet)?;
+
+    if let Serverbound::Handshake {
+        protocol_version,
+        next_state,
+        ..
+    } = packet
+    {
+        match ProtocolVersion::from_protocol_version(protocol_version) {
+            Some(version) => connection.version = version,
+            // The status packets are the same in every version
+            None if next_state == NextState::Status => {}
+            // Like vanilla, tell the client that it can't log in
+            None => {
+                let reason: String =
+                    if protocol_version > ProtocolVersion::LATEST.protocol_version() {
+                        format!(
+                            "Outdated server! I'm still on {}",
+                            ProtocolVersion::LATEST.name()
+                        )
+                    } else {
+                        format!(
+                            "Outdated client! Please use {}",
+                            ProtocolVersion::LATEST.name()
+                        )
+                    };
+                trace!("Unsupported protocol version {protocol_version}, disconnecting");
+                connection.state = State::Login;
+                connection.disconnect(DisconnectCause::Kicked(TextComponent::text(reason)));
+                return Ok(State::Login);
+            }
+        }
+    }
+
+    if let Serverbound::EncryptionResponse { .. } = packet {
+        connection.awaiting_key = true;
+    }
+
+    events.send(PacketReceived { entity, packet });
+    Ok(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bevy_app::prelude::*;
+    use ussr_protocol::{
+        neutral::{Clientbound, OtherServerbound},
+        v1_7_2, NextState,
+    };
+
+    use super::*;
+    use crate::{compression::write_compressed_frame, memory, read_data};
+
+    fn app() -> (App, Entity, memory::MemoryClient) {
+        let mut app = App::new();
+        app.init_resource::<Status>()
+            .add_event::<PacketReceived>()
+            .add_systems(Update, (read_data, process_data).chain());
+
+        let (entity, client) = memory::connect(app.world_mut(), State::Handshaking);
+        (app, entity, client)
+    }
+
+    fn received(app: &App) -> Vec<Serverbound> {
+        let events = app.world().resource::<Events<PacketReceived>>();
+        events
+            .get_cursor()
+            .read(events)
+            .map(|event| event.packet.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_packet_events() {
+        let (mut app, entity, mut client) = app();
+
+        #[rustfmt::skip]
+        client.write_all(&[
+            // Handshake
+            0x0F, 0x00, 0x04,
+            0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+            0x63, 0xDD, 0x01,
+            // Status request
+            0x01, 0x00,
+            // The start of a ping request
+            0x09, 0x01, 0x00,
+        ])
+        .unwrap();
+        app.update();
+
+        assert_eq!(
+            received(&app),
+            [
+                Serverbound::Handshake {
+                    protocol_version: 4,
+                    server_address: "localhost".to_owned(),
+                    server_port: 25565,
+                    next_state: NextState::Status,
+                },
+                Serverbound::StatusRequest,
+            ]
+        );
+        let connection = app.world().get::<Connection>(entity).unwrap();
+        assert_eq!(connection.state, State::Status);
+        assert_eq!(connection.version, ProtocolVersion::V1_7_2);
+
+        client.write_all(&[0, 0, 0, 0, 0, 0, 42]).unwrap();
+        app.update();
+        assert_eq!(
+            received(&app).last(),
+            Some(&Serverbound::PingRequest { payload: 42 })
+        );
+    }
+
+    #[test]
+    fn test_version_specific_packets() {
+        let (mut app, entity, mut client) = app();
+        let mut connection: Mut<Connection> = app.world_mut().get_mut(entity).unwrap();
+        connection.state = State::Play;
+        connection.version = ProtocolVersion::V1_7_2;
+
+        // A keep alive and a close window, which isn't part of the neutral model
+        client
+            .write_all(&[0x05, 0x00, 0x00, 0x00, 0x00, 0x05, 0x02, 0x0D, 0x01])
+            .unwrap();
+        app.update();
+
+        assert_eq!(
+            received(&app),
+            [
+                Serverbound::KeepAlive { id: 5 },
+                Serverbound::Other(OtherServerbound::V1_7_2(Box::new(
+                    v1_7_2::packets::play::serverbound::CloseWindow { window_id: 1 }.into()
+                ))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compressed_packets() {
+        let (mut app, entity, mut client) = app();
+        let mut connection: Mut<Connection> = app.world_mut().get_mut(entity).unwrap();
+        connection.state = State::Play;
+        connection.version = ProtocolVersion::V1_8_9;
+        connection.compression = Some(64);
+
+        // A keep alive below the threshold, and a chat message of exactly the threshold
+        let mut buf: Vec<u8> = vec![0x03, 0x00, 0x00, 0x05];
+        let mut chat_message: Vec<u8> = vec![0x01, 62];
+        chat_message.extend_from_slice(&[b'a'; 62]);
+        write_compressed_frame(&mut buf, &chat_message, 64).unwrap();
+        client.write_all(&buf).unwrap();
+        app.update();
+
+        assert_eq!(
+            received(&app),
+            [
+                Serverbound::KeepAlive { id: 5 },
+                Serverbound::ChatMessage {
+                    message: "a".repeat(62),
+                },
+            ]
+        );
+
+        // A compressed frame can't be smaller than the threshold
+        client.write_all(&[0x03, 0x01, 0x00, 0x05]).unwrap();
+        app.update();
+        let connection = app.world().get::<Connection>(entity).unwrap();
+        assert!(matches!(
+            connection.closing,
+            Some(DisconnectCause::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_legacy_ping_prefix() {
+        let (mut app, entity, mut client) = app();
+
+        // A handshake of 254 bytes, whose length is `0xFE 0x01` like the start of a legacy ping
+        let server_address: String = "a".repeat(247);
+        let mut frame: Vec<u8> = vec![0xFE, 0x01, 0x00, 0x2F, 0xF7, 0x01];
+        frame.extend_from_slice(server_address.as_bytes());
+        frame.extend_from_slice(&[0x63, 0xDD, 0x01]);
+        assert_eq!(frame.len(), 2 + 254);
+        client.write_all(&frame).unwrap();
+        app.update();
+
+        assert_eq!(
+            received(&app),
+            [Serverbound::Handshake {
+                protocol_version: 47,
+                server_address,
+                server_port: 25565,
+                next_state: NextState::Status,
+            }]
+        );
+        let connection = app.world().get::<Connection>(entity).unwrap();
+        assert!(connection.closing.is_none());
+        assert_eq!(connection.state, State::Status);
+    }
+
+    #[test]
+    fn test_legacy_ping_too_large() {
+        let (mut app, entity, mut client) = app();
+        // The response can't have more than 65535 UTF-16 units
+        app.world_mut().resource_mut::<Status>().0.description =
+            TextComponent::text("a".repeat(70_000));
+
+        client.write_all(&[0xFE, 0x01]).unwrap();
+        app.update();
+
+        let connection = app.world().get::<Connection>(entity).unwrap();
+        assert!(connection.outgoing_buf.is_empty());
+        assert!(matches!(
+            connection.closing,
+            Some(DisconnectCause::SendFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_packet_of_another_state() {
+        let (mut app, entity, mut client) = app();
+        let mut connection: Mut<Connection> = app.world_mut().get_mut(entity).unwrap();
+        connection.state = State::Login;
+        connection.version = ProtocolVersion::V1_7_2;
+
+        // A player position in the login state
+        let mut frame: Vec<u8> = vec![0x22, 0x04];
+        frame.extend_from_slice(&[0; 33]);
+        client.write_all(&frame).unwrap();
+        app.update();
+
+        assert!(received(&app).is_empty());
+        let connection = app.world().get::<Connection>(entity).unwrap();
+        assert_eq!(
+            connection.closing,
+            Some(DisconnectCause::InvalidData(
+                "unknown packet id 0x04 in state Login".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        for (protocol_version, reason) in [
+            (3, "Outdated client! Please use 1.8.9"),
+            (48, "Outdated server! I'm still on 1.8.9"),
+        ] {
+            let (mut app, entity, mut client) = app();
+
+            #[rustfmt::skip]
+            client.write_all(&[
+                // Handshake
+                0x0F, 0x00, protocol_version,
+                0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+                0x63, 0xDD, 0x02,
+                // Login start
+                0x07, 0x00, 0x05, b'N', b'o', b't', b'c', b'h',
+            ])
+            .unwrap();
+            app.update();
+
+            assert!(received(&app).is_empty());
+            let connection = app.world().get::<Connection>(entity).unwrap();
+            assert_eq!(connection.state, State::Login);
+            assert_eq!(
+                connection.closing,
+                Some(DisconnectCause::Kicked(TextComponent::text(reason)))
+            );
+
+            let mut expected: Vec<u8> = Vec::new();
+            ProtocolVersion::LATEST
+                .encode_clientbound(
+                    &Clientbound::LoginDisconnect {
+                        reason: TextComponent::text(reason),
+                    },
+                    &mut expected,
+                )
+                .unwrap();
+            assert_eq!(connection.outgoing_buf[1..], expected);
+        }
+    }
+
+    #[test]
+    fn test_invalid_packet() {
+        let (mut app, entity, mut client) = app();
+
+        // An unknown packet in the handshaking state
+        client.write_all(&[0x01, 0x05]).unwrap();
+        app.update();
+
+        assert!(received(&app).is_empty());
+        let connection = app.world().get::<Connection>(entity).unwrap();
+        assert!(matches!(
+            connection.closing,
+            Some(DisconnectCause::InvalidData(_))
+        ));
+    }
 }
@@ -0,0 +1,509 @@
+//! Running behind proxies, which hide the address of the client.
+//!
+//! A TCP load balancer can send a PROXY protocol header at the start of every connection, see [`ProxyProtocol`].
+//! BungeeCord adds the address and profile of the player to the handshake, see [`BungeeCordForwarding`].
+//! Velocity's modern forwarding uses login plugin messages, which were added in 1.13,
+//! so Velocity must use its `legacy` forwarding mode, which is the one of BungeeCord.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use bevy_ecs::prelude::*;
+use bytes::Buf;
+use thiserror::Error;
+use tracing::{instrument, trace};
+use ussr_protocol::{enums::NextState, neutral::Serverbound};
+use uuid::Uuid;
+
+use crate::{Connection, DisconnectCause, PacketReceived, ProfileProperty};
+
+/// The signature that starts a version 2 header.
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// The start of a version 1 header.
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+/// The maximum length of a version 1 header, including the CRLF.
+const V1_MAX_LENGTH: usize = 107;
+
+/// Expect a PROXY protocol header, version 1 or 2, at the start of every connection,
+/// and use its source address as the [`ClientAddress`].
+/// Connections without one are closed, so clients can't forge it if the server is only reachable through the proxy.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct ProxyProtocol;
+
+/// Use the address and UUID of players forwarded by BungeeCord, or Velocity in `legacy` mode, in the handshake.
+/// Logins without them are refused, and logins with them are not authenticated, even with [`OnlineMode`](crate::OnlineMode),
+/// so the server must only be reachable through the proxy.
+#[derive(Resource, Debug, Clone, Copy, Default)]
+pub struct BungeeCordForwarding;
+
+/// The IP address of the client.
+/// It is the address of the peer of TCP connections, unless there is [`ProxyProtocol`] or [`BungeeCordForwarding`],
+/// in which case it is added once the proxy forwards it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ClientAddress(pub IpAddr);
+
+/// Added to connections until their PROXY protocol header is received.
+#[derive(Component, Debug)]
+pub(crate) struct AwaitingProxyHeader;
+
+/// The profile of the player forwarded in the handshake, used once the login starts.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub(crate) struct Forwarded {
+    pub(crate) id: Uuid,
+    pub(crate) properties: Vec<ProfileProperty>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub(crate) enum ProxyHeaderError {
+    #[error("missing PROXY protocol header")]
+    Missing,
+
+    #[error("invalid PROXY protocol header")]
+    Invalid,
+
+    #[error("unsupported PROXY protocol version {0}")]
+    UnsupportedVersion(u8),
+}
+
+/// Decodes the PROXY protocol header at the start of `buf`.
+/// Returns the source address, if the header has one, and the length of the header,
+/// or `None` if the header isn't complete yet.
+pub(crate) fn decode_proxy_header(
+    buf: &[u8],
+) -> Result<Option<(Option<SocketAddr>, usize)>, ProxyHeaderError> {
+    if buf.starts_with(V2_SIGNATURE) {
+        decode_v2(buf)
+    } else if buf.starts_with(V1_PREFIX) {
+        decode_v1(buf)
+    } else if V2_SIGNATURE.starts_with(buf) || V1_PREFIX.starts_with(buf) {
+        Ok(None)
+    } else {
+        Err(ProxyHeaderError::Missing)
+    }
+}
+
+/// Decodes a human-readable header, like `PROXY TCP4 192.0.2.1 198.51.100.1 56324 25565\r\n`.
+fn decode_v1(buf: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, ProxyHeaderError> {
+    let Some(end) = buf
+        .windows(2)
+        .take(V1_MAX_LENGTH - 1)
+        .position(|window| window == b"\r\n")
+    else {
+        if buf.len() < V1_MAX_LENGTH {
+            return Ok(None);
+        }
+        return Err(ProxyHeaderError::Invalid);
+    };
+    let line: &str = std::str::from_utf8(&buf[..end]).map_err(|_| ProxyHeaderError::Invalid)?;
+
+    let mut fields = line.split(' ').skip(1);
+    let source: Option<SocketAddr> = match fields.next() {
+        // The connection doesn't come from a TCP client, like health checks
+        Some("UNKNOWN") => None,
+        Some(protocol @ ("TCP4" | "TCP6")) => {
+            let (Some(source), Some(_), Some(source_port), Some(_), None) = (
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+                fields.next(),
+            ) else {
+                return Err(ProxyHeaderError::Invalid);
+            };
+            let ip: IpAddr = source.parse().map_err(|_| ProxyHeaderError::Invalid)?;
+            let port: u16 = source_port.parse().map_err(|_| ProxyHeaderError::Invalid)?;
+            if ip.is_ipv4() != (protocol == "TCP4") {
+                return Err(ProxyHeaderError::Invalid);
+            }
+            Some(SocketAddr::new(ip, port))
+        }
+        _ => return Err(ProxyHeaderError::Invalid),
+    };
+    Ok(Some((source, end + 2)))
+}
+
+/// Decodes a binary header, which is the signature, the version and command,
+/// the family of the addresses, and the length of the addresses and of any extensions.
+fn decode_v2(buf: &[u8]) -> Result<Option<(Option<SocketAddr>, usize)>, ProxyHeaderError> {
+    let Some(mut header) = buf.get(V2_SIGNATURE.len()..V2_SIGNATURE.len() + 4) else {
+        return Ok(None);
+    };
+    let version_command: u8 = header.get_u8();
+    let family: u8 = header.get_u8();
+    let length: usize = header.get_u16().into();
+
+    let version: u8 = version_command >> 4;
+    if version != 2 {
+        return Err(ProxyHeaderError::UnsupportedVersion(version));
+    }
+    let start: usize = V2_SIGNATURE.len() + 4;
+    let Some(mut addresses) = buf.get(start..start + length) else {
+        return Ok(None);
+    };
+
+    let source: Option<SocketAddr> = match (version_command & 0x0F, family) {
+        // A LOCAL command, sent by the proxy itself
+        (0x0, _) => None,
+        // TCP over IPv4
+        (0x1, 0x11) if addresses.len() >= 12 => {
+            let ip = Ipv4Addr::from(addresses.get_u32());
+            addresses.advance(4);
+            Some(SocketAddr::new(ip.into(), addresses.get_u16()))
+        }
+        // TCP over IPv6
+        (0x1, 0x21) if addresses.len() >= 36 => {
+            let ip = Ipv6Addr::from(addresses.get_u128());
+            addresses.advance(16);
+            Some(SocketAddr::new(ip.into(), addresses.get_u16()))
+        }
+        (0x1, 0x11 | 0x21) => return Err(ProxyHeaderError::Invalid),
+        // Unspecified, UDP or Unix sockets, which have no client address to use
+        (0x1, _) => None,
+        _ => return Err(ProxyHeaderError::Invalid),
+    };
+    Ok(Some((source, start + length)))
+}
+
+/// A system that removes the PROXY protocol header from the data of new connections,
+/// before it is processed.
+#[instrument(skip_all, level = "trace")]
+pub(crate) fn read_proxy_header(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Connection), With<AwaitingProxyHeader>>,
+) {
+    for (entity, mut connection) in &mut query {
+        if connection.closing.is_some() {
+            continue;
+        }
+        match decode_proxy_header(&connection.incoming_buf) {
+            Ok(Some((source, length))) => {
+                trace!("PROXY protocol source: {source:?}");
+                connection.incoming_buf.advance(length);
+                let mut entity = commands.entity(entity);
+                entity.remove::<AwaitingProxyHeader>();
+                if let Some(source) = source {
+                    entity.insert(ClientAddress(source.ip()));
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                trace!("{e}, disconnecting");
+                connection.disconnect(DisconnectCause::InvalidData(e.to_string()));
+            }
+        }
+    }
+}
+
+/// Decodes the server address of a handshake forwarded by BungeeCord,
+/// which is the host, the IP address of the client, its UUID and optionally the properties of its profile,
+/// separated by null characters.
+fn decode_bungeecord(server_address: &str) -> Option<(IpAddr, Forwarded)> {
+    let mut fields = server_address.split('\0').skip(1);
+    let ip: IpAddr = fields.next()?.parse().ok()?;
+    let id: Uuid = Uuid::try_parse(fields.next()?).ok()?;
+    let properties: Vec<ProfileProperty> = match fields.next() {
+        Some(properties) => serde_json::from_str(properties).ok()?,
+        None => Vec::new(),
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+    Some((ip, Forwarded { id, properties }))
+}
+
+/// A system that reads the data forwarded by BungeeCord in login handshakes.
+/// It only runs with [`BungeeCordForwarding`].
+#[instrument(skip_all, level = "trace")]
+pub(crate) fn handle_forwarding(
+    mut commands: Commands,
+    mut events: EventReader<PacketReceived>,
+    mut query: Query<&mut Connection>,
+) {
+    for event in events.read() {
+        let Serverbound::Handshake {
+            server_address,
+            next_state: NextState::Login,
+            ..
+        } = &event.packet
+        else {
+            continue;
+        };
+        let Ok(mut connection) = query.get_mut(event.entity) else {
+            continue;
+        };
+
+        match decode_bungeecord(server_address) {
+            Some((ip, forwarded)) => {
+                trace!("Forwarded {ip}: {forwarded:?}");
+                commands
+                    .entity(event.entity)
+                    .insert((ClientAddress(ip), forwarded));
+            }
+            None => {
+                trace!("Missing forwarded data, disconnecting");
+                // The message of Spigot, as it is usually a misconfigured proxy
+                connection.disconnect(DisconnectCause::Kicked(
+                    "If you wish to use IP forwarding, please enable it in your BungeeCord config as well!"
+                        .into(),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bevy_app::prelude::*;
+    use ussr_buf::{read_frame, VarDecode, VarEncode};
+    use ussr_protocol::State;
+
+    use super::*;
+    use crate::{
+        login::handle_login, memory, process_data::process_data, read_data, send::encode_packets,
+        send_data, ClientDisconnected, GameProfile, JoinGameSettings, PlayerUuid, Status,
+        MAX_PACKET_SIZE,
+    };
+
+    #[test]
+    fn test_proxy_header_v1() {
+        let header: &[u8] = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 25565\r\n";
+        let source: SocketAddr = "192.0.2.1:56324".parse().unwrap();
+        assert_eq!(
+            decode_proxy_header(&[header, &[0x0F, 0x00]].concat()),
+            Ok(Some((Some(source), header.len())))
+        );
+        for end in 0..header.len() {
+            assert_eq!(decode_proxy_header(&header[..end]), Ok(None));
+        }
+
+        assert_eq!(
+            decode_proxy_header(b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 25565\r\n"),
+            Ok(Some((Some("[2001:db8::1]:56324".parse().unwrap()), 48)))
+        );
+        assert_eq!(
+            decode_proxy_header(b"PROXY UNKNOWN\r\n"),
+            Ok(Some((None, 15)))
+        );
+
+        assert_eq!(
+            decode_proxy_header(b"PROXY TCP4 2001:db8::1 2001:db8::2 56324 25565\r\n"),
+            Err(ProxyHeaderError::Invalid)
+        );
+        assert_eq!(
+            decode_proxy_header(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324\r\n"),
+            Err(ProxyHeaderError::Invalid)
+        );
+        assert_eq!(
+            decode_proxy_header(&[V1_PREFIX, &[b'a'; V1_MAX_LENGTH]].concat()),
+            Err(ProxyHeaderError::Invalid)
+        );
+        assert_eq!(
+            decode_proxy_header(&[0x0F, 0x00, 0x2F]),
+            Err(ProxyHeaderError::Missing)
+        );
+    }
+
+    #[test]
+    fn test_proxy_header_v2() {
+        #[rustfmt::skip]
+        let header: Vec<u8> = [
+            &V2_SIGNATURE[..],
+            // PROXY, TCP over IPv4, 12 bytes of addresses and 3 bytes of extensions
+            &[0x21, 0x11, 0x00, 0x0F],
+            &[192, 0, 2, 1], &[198, 51, 100, 1],
+            &56324u16.to_be_bytes(), &25565u16.to_be_bytes(),
+            &[0x04, 0x00, 0x00],
+        ]
+        .concat();
+        let source: SocketAddr = "192.0.2.1:56324".parse().unwrap();
+        assert_eq!(
+            decode_proxy_header(&[&header[..], &[0x0F, 0x00]].concat()),
+            Ok(Some((Some(source), header.len())))
+        );
+        for end in 0..header.len() {
+            assert_eq!(decode_proxy_header(&header[..end]), Ok(None));
+        }
+
+        #[rustfmt::skip]
+        let ipv6: Vec<u8> = [
+            &V2_SIGNATURE[..],
+            &[0x21, 0x21, 0x00, 0x24],
+            &Ipv6Addr::LOCALHOST.octets(), &Ipv6Addr::UNSPECIFIED.octets(),
+            &56324u16.to_be_bytes(), &25565u16.to_be_bytes(),
+        ]
+        .concat();
+        assert_eq!(
+            decode_proxy_header(&ipv6),
+            Ok(Some((Some("[::1]:56324".parse().unwrap()), 52)))
+        );
+
+        // LOCAL, without addresses
+        let local: Vec<u8> = [&V2_SIGNATURE[..], &[0x20, 0x00, 0x00, 0x00]].concat();
+        assert_eq!(decode_proxy_header(&local), Ok(Some((None, 16))));
+
+        let truncated: Vec<u8> =
+            [&V2_SIGNATURE[..], &[0x21, 0x11, 0x00, 0x04, 0, 0, 0, 0]].concat();
+        assert_eq!(
+            decode_proxy_header(&truncated),
+            Err(ProxyHeaderError::Invalid)
+        );
+        let version: Vec<u8> = [&V2_SIGNATURE[..], &[0x11, 0x11, 0x00, 0x00]].concat();
+        assert_eq!(
+            decode_proxy_header(&version),
+            Err(ProxyHeaderError::UnsupportedVersion(1))
+        );
+    }
+
+    #[test]
+    fn test_read_proxy_header() {
+        let mut app = App::new();
+        app.init_resource::<Status>()
+            .add_event::<PacketReceived>()
+            .add_systems(Update, (read_data, read_proxy_header, process_data).chain());
+
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Handshaking);
+        app.world_mut()
+            .entity_mut(entity)
+            .insert(AwaitingProxyHeader);
+
+        // The handshake isn't processed before the header is complete
+        client.write_all(b"PROXY TCP4 192.0.2.1 198.51").unwrap();
+        app.update();
+        client
+            .write_all(b".100.1 56324 25565\r\n\x0F\x00\x2F\x09localhost\x63\xDD\x01")
+            .unwrap();
+        app.update();
+
+        assert_eq!(
+            app.world().get::<ClientAddress>(entity),
+            Some(&ClientAddress("192.0.2.1".parse().unwrap()))
+        );
+        assert!(app.world().get::<AwaitingProxyHeader>(entity).is_none());
+        let events = app.world().resource::<Events<PacketReceived>>();
+        assert!(matches!(
+            events.iter_current_update_events().next(),
+            Some(PacketReceived {
+                packet: Serverbound::Handshake { .. },
+                ..
+            })
+        ));
+
+        // Without a header
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Handshaking);
+        app.world_mut()
+            .entity_mut(entity)
+            .insert(AwaitingProxyHeader);
+        client.write_all(&[0x0F, 0x00, 0x2F]).unwrap();
+        app.update();
+        assert!(matches!(
+            app.world().get::<Connection>(entity).unwrap().closing,
+            Some(DisconnectCause::InvalidData(_))
+        ));
+    }
+
+    /// A signed textures property like the ones of the session server, base64 of the profile textures JSON.
+    const TEXTURES: &str = concat!(
+        "ewogICJ0aW1lc3RhbXAiIDogMTQ1OTk0NjU0NzA2NCwKICAicHJvZmlsZUlkIiA6ICIwNjlhNzlmNDQ0ZTk0NzI2YTViZWZj",
+        "YTkwZTM4YWFmNSIsCiAgInByb2ZpbGVOYW1lIiA6ICJOb3RjaCIsCiAgInNpZ25hdHVyZVJlcXVpcmVkIiA6IHRydWUsCiAg",
+        "InRleHR1cmVzIiA6IHsKICAgICJTS0lOIiA6IHsKICAgICAgInVybCIgOiAiaHR0cDovL3RleHR1cmVzLm1pbmVjcmFmdC5u",
+        "ZXQvdGV4dHVyZS8yOTIwMDlhNDkyNWI1OGYwMmM3N2RhZGMzZWNlZjA3ZWE0Yzc0NzJmNjRlMGZkYzMyY2U1NTIyNDg5MzYy",
+        "NjgwIgogICAgfSwKICAgICJDQVBFIiA6IHsKICAgICAgInVybCIgOiAiaHR0cDovL3RleHR1cmVzLm1pbmVjcmFmdC5uZXQv",
+        "dGV4dHVyZS8zZjY4OGUwZTY5OWIzZDlmZTQ0OGI1YmI1MGEzYTI4OGY5YzU4OTc2MmIzZGFlODMwODg0MjEyMmRjYjgxIgog",
+        "ICAgfQogIH0KfQ==",
+    );
+
+    #[test]
+    fn test_bungeecord_forwarding() {
+        let mut app = App::new();
+        app.init_resource::<Status>()
+            .init_resource::<JoinGameSettings>()
+            .add_event::<PacketReceived>()
+            .add_event::<ClientDisconnected>()
+            .add_systems(
+                Update,
+                (
+                    read_data,
+                    process_data,
+                    handle_forwarding,
+                    handle_login,
+                    encode_packets,
+                    send_data,
+                )
+                    .chain(),
+            );
+
+        let login = |app: &mut App, server_address: &str| {
+            let (entity, mut client) = memory::connect(app.world_mut(), State::Handshaking);
+            let mut handshake: Vec<u8> = vec![0x00, 0x2F];
+            server_address.len().var_encode(&mut handshake).unwrap();
+            handshake.extend_from_slice(server_address.as_bytes());
+            handshake.extend_from_slice(&[0x63, 0xDD, 0x02]);
+            handshake.len().var_encode(&mut client).unwrap();
+            client.write_all(&handshake).unwrap();
+            client.write_all(&[0x07, 0x00, 0x05]).unwrap();
+            client.write_all(b"Notch").unwrap();
+            app.update();
+            (entity, client)
+        };
+
+        // The signature is 512 bytes, so the handshake is about 1.5 KB like with a real skin
+        let signature: String = "c2lnbmF0dXJl".repeat(57);
+        let properties: String = format!(
+            "[{{\"name\":\"textures\",\"value\":\"{TEXTURES}\",\"signature\":\"{signature}\"}}]"
+        );
+        let (entity, _client) = login(
+            &mut app,
+            &format!("localhost\x00192.0.2.1\x00069a79f444e94726a5befca90e38aaf5\x00{properties}"),
+        );
+        let world: &World = app.world();
+        assert_eq!(
+            world.get::<ClientAddress>(entity),
+            Some(&ClientAddress("192.0.2.1".parse().unwrap()))
+        );
+        let id: Uuid = "069a79f4-44e9-4726-a5be-fca90e38aaf5".parse().unwrap();
+        assert_eq!(world.get::<PlayerUuid>(entity), Some(&PlayerUuid(id)));
+        assert_eq!(
+            world.get::<GameProfile>(entity),
+            Some(&GameProfile {
+                id,
+                name: "Notch".to_owned(),
+                properties: vec![ProfileProperty {
+                    name: "textures".to_owned(),
+                    value: TEXTURES.to_owned(),
+                    signature: Some(signature),
+                }],
+            })
+        );
+        assert!(world.get::<Forwarded>(entity).is_none());
+
+        // Without forwarded data, the player is told to configure the proxy
+        let (entity, mut client) = login(&mut app, "localhost");
+        assert!(app.world().get_entity(entity).is_err());
+        let received: Vec<u8> = client.read_available();
+        let frame: Vec<u8> = read_frame(&mut &received[..], MAX_PACKET_SIZE).unwrap();
+        assert_eq!(u32::var_decode(&mut &frame[..]).unwrap(), 0x00);
+        assert!(String::from_utf8_lossy(&frame).contains("BungeeCord config"));
+    }
+
+    #[test]
+    fn test_decode_bungeecord() {
+        let (ip, forwarded) =
+            decode_bungeecord("localhost\x002001:db8::1\x00069a79f444e94726a5befca90e38aaf5")
+                .unwrap();
+        assert_eq!(ip, "2001:db8::1".parse::<IpAddr>().unwrap());
+        assert!(forwarded.properties.is_empty());
+
+        assert_eq!(decode_bungeecord("localhost"), None);
+        assert_eq!(
+            decode_bungeecord("localhost\x00192.0.2.1\x00not a uuid"),
+            None
+        );
+        assert_eq!(
+            decode_bungeecord("localhost\x00192.0.2.1\x00069a79f444e94726a5befca90e38aaf5\x00{}"),
+            None
+        );
+    }
+}
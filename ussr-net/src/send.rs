@@ -0,0 +1,322 @@
+//! Sending packets to connections.
+//!
+//! Packets are queued in the [`Outbox`] of a connection, usually with [`SendPacketExt`],
+//! and are framed into its outgoing buffer by [`encode_packets`] in the order they were queued.
+
+use std::{collections::VecDeque, io};
+
+use bevy_ecs::{prelude::*, world::Command};
+use thiserror::Error;
+use tracing::{instrument, trace, warn};
+use ussr_buf::write_frame;
+use ussr_protocol::{neutral::Clientbound, Direction, State, UnexpectedPacket};
+
+use crate::{
+    compression::write_compressed_frame, CompressionThreshold, Connection, DisconnectCause,
+    MAX_PACKET_SIZE,
+};
+
+/// The packets waiting to be sent to a connection, which are sent in order.
+/// This component is added with every [`Connection`].
+#[derive(Component, Debug, Clone, Default)]
+pub struct Outbox(VecDeque<Clientbound>);
+
+impl Outbox {
+    /// Queues a packet, after every packet queued before it.
+    pub fn push(&mut self, packet: Clientbound) {
+        self.0.push_back(packet);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Sending packets with [`Commands`].
+/// Packets sent by one system are sent in the order the commands are issued.
+pub trait SendPacketExt {
+    /// Sends a packet to the connection of `entity`.
+    /// Nothing is sent if the entity has no connection when the command is applied.
+    fn send_packet(&mut self, entity: Entity, packet: Clientbound);
+
+    /// Sends a packet to every connection in `state` when the command is applied.
+    fn broadcast(&mut self, state: State, packet: Clientbound);
+}
+
+impl SendPacketExt for Commands<'_, '_> {
+    fn send_packet(&mut self, entity: Entity, packet: Clientbound) {
+        self.queue(SendPacket { entity, packet });
+    }
+
+    fn broadcast(&mut self, state: State, packet: Clientbound) {
+        self.queue(Broadcast { state, packet });
+    }
+}
+
+struct SendPacket {
+    entity: Entity,
+    packet: Clientbound,
+}
+
+impl Command for SendPacket {
+    fn apply(self, world: &mut World) {
+        match world.get_mut::<Outbox>(self.entity) {
+            Some(mut outbox) => outbox.push(self.packet),
+            None => trace!("Dropped packet for {}, it has no connection", self.entity),
+        }
+    }
+}
+
+struct Broadcast {
+    state: State,
+    packet: Clientbound,
+}
+
+impl Command for Broadcast {
+    fn apply(self, world: &mut World) {
+        let mut query = world.query::<(&Connection, &mut Outbox)>();
+        for (connection, mut outbox) in query.iter_mut(world) {
+            if connection.state == self.state {
+                outbox.push(self.packet.clone());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum SendError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    UnexpectedPacket(#[from] UnexpectedPacket),
+
+    #[error("packet is too large ({0} bytes)")]
+    TooLarge(usize),
+}
+
+impl Connection {
+    /// Appends a packet, prefixed with its length, to the outgoing buffer,
+    /// and moves to the state after it.
+    pub(crate) fn write_packet(&mut self, packet: &Clientbound) -> Result<(), SendError> {
+        let state: State = self.state.next_clientbound(packet)?;
+
+        let mut frame: Vec<u8> = Vec::new();
+        self.version.encode_clientbound(packet, &mut frame)?;
+        if frame.len() > MAX_PACKET_SIZE {
+            return Err(SendError::TooLarge(frame.len()));
+        }
+        self.capture_frame(Direction::Clientbound, &frame);
+
+        match self.compression {
+            Some(threshold) => write_compressed_frame(&mut self.outgoing_buf, &frame, threshold)?,
+            None => write_frame(&mut self.outgoing_buf, &frame)?,
+        }
+        self.state = state;
+
+        // Every frame after this one uses the new threshold
+        if let Clientbound::SetCompression { threshold } = *packet {
+            self.compression = usize::try_from(threshold).ok();
+        }
+        Ok(())
+    }
+}
+
+/// A system that moves the packets of every [`Outbox`] into the outgoing buffer of its connection.
+/// A connection that is sent a packet that can't be encoded is closed,
+/// as the client would not be able to follow the rest of the stream.
+/// Connections kicked with [`DisconnectExt`](crate::DisconnectExt) are disconnected after their outbox is sent.
+#[instrument(skip_all, level = "trace")]
+pub(crate) fn encode_packets(
+    compression_threshold: Option<Res<CompressionThreshold>>,
+    mut query: Query<(&mut Connection, &mut Outbox)>,
+) {
+    let set_compression: Option<Clientbound> =
+        compression_threshold.map(|threshold| Clientbound::SetCompression {
+            threshold: threshold.0.try_into().unwrap_or(i32::MAX),
+        });
+
+    for (mut connection, mut outbox) in &mut query {
+        if connection.closing.is_some() {
+            outbox.0.clear();
+            continue;
+        }
+
+        for packet in outbox.0.drain(..) {
+            trace!("{packet:?}");
+
+            // Like vanilla, compression is enabled right before the login succeeds
+            let enable_compression: bool = matches!(packet, Clientbound::LoginSuccess { .. })
+                && connection.version.supports_compression()
+                && connection.compression.is_none();
+            let result: Result<(), SendError> = match &set_compression {
+                Some(set_compression) if enable_compression => connection
+                    .write_packet(set_compression)
+                    .and_then(|()| connection.write_packet(&packet)),
+                _ => connection.write_packet(&packet),
+            };
+
+            if let Err(e) = result {
+                warn!("Failed to send {packet:?}: {e}, disconnecting");
+                connection.disconnect(DisconnectCause::SendFailed(e.to_string()));
+                break;
+            }
+        }
+
+        if let Some(reason) = connection.kick.take() {
+            connection.disconnect(DisconnectCause::Kicked(reason));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_app::prelude::*;
+    use ussr_buf::read_frame;
+    use ussr_protocol::{ProtocolVersion, ServerStatus};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{compression::decompress_frame, memory, send_data, ClientDisconnected};
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_event::<ClientDisconnected>()
+            .add_systems(Update, (encode_packets, send_data).chain());
+        app
+    }
+
+    #[test]
+    fn test_send_packet() {
+        let mut app = app();
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Status);
+
+        let mut commands = app.world_mut().commands();
+        commands.send_packet(entity, Clientbound::PingResponse { payload: 1 });
+        commands.send_packet(entity, Clientbound::PingResponse { payload: 2 });
+        app.world_mut().flush();
+        app.update();
+
+        #[rustfmt::skip]
+        assert_eq!(
+            client.read_available(),
+            [
+                0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 1,
+                0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 2,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_state_transition() {
+        let mut app = app();
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Login);
+
+        let mut outbox: Mut<Outbox> = app.world_mut().get_mut::<Outbox>(entity).unwrap();
+        outbox.push(Clientbound::LoginSuccess {
+            uuid: Uuid::nil(),
+            username: "Notch".to_owned(),
+        });
+        outbox.push(Clientbound::KeepAlive { id: 7 });
+        app.update();
+
+        let connection = app.world().get::<Connection>(entity).unwrap();
+        assert_eq!(connection.state, State::Play);
+        assert!(app.world().get::<Outbox>(entity).unwrap().is_empty());
+
+        let buf: Vec<u8> = client.read_available();
+        assert_eq!(buf[1], 0x02);
+        // The keep alive is encoded in the play state of 1.8.9
+        assert_eq!(buf[buf.len() - 3..], [0x02, 0x00, 0x07]);
+    }
+
+    #[test]
+    fn test_compression() {
+        let mut app = app();
+        app.insert_resource(CompressionThreshold(64));
+        let (v1_8_9, mut v1_8_9_client) = memory::connect(app.world_mut(), State::Login);
+        let (v1_7_2, mut v1_7_2_client) = memory::connect(app.world_mut(), State::Login);
+        app.world_mut()
+            .get_mut::<Connection>(v1_7_2)
+            .unwrap()
+            .version = ProtocolVersion::V1_7_2;
+
+        for entity in [v1_8_9, v1_7_2] {
+            let mut outbox: Mut<Outbox> = app.world_mut().get_mut::<Outbox>(entity).unwrap();
+            outbox.push(Clientbound::LoginSuccess {
+                uuid: Uuid::nil(),
+                username: "Notch".to_owned(),
+            });
+            outbox.push(Clientbound::PluginMessage {
+                channel: "MC|Brand".to_owned(),
+                data: vec![b'a'; 100],
+            });
+        }
+        app.update();
+
+        let connection = app.world().get::<Connection>(v1_8_9).unwrap();
+        assert_eq!(connection.compression, Some(64));
+        let buf: Vec<u8> = v1_8_9_client.read_available();
+        let mut reader: &[u8] = &buf;
+        let mut frame = || read_frame(&mut reader, MAX_PACKET_SIZE).unwrap();
+        // Set compression isn't compressed yet
+        assert_eq!(frame(), [0x03, 0x40]);
+        // Login success is smaller than the threshold
+        assert_eq!(frame()[..2], [0x00, 0x02]);
+        assert_eq!(decompress_frame(&frame(), 64).unwrap()[..2], [0x3F, 0x08]);
+        assert!(reader.is_empty());
+
+        // 1.7 doesn't support compression
+        let connection = app.world().get::<Connection>(v1_7_2).unwrap();
+        assert_eq!(connection.compression, None);
+        assert_eq!(v1_7_2_client.read_available()[1], 0x02);
+    }
+
+    #[test]
+    fn test_broadcast() {
+        let mut app = app();
+        let (_, mut status) = memory::connect(app.world_mut(), State::Status);
+        let (_, mut login) = memory::connect(app.world_mut(), State::Login);
+
+        app.world_mut().commands().broadcast(
+            State::Status,
+            Clientbound::StatusResponse {
+                response: ServerStatus::default(),
+            },
+        );
+        app.world_mut().flush();
+        app.update();
+
+        let buf: Vec<u8> = status.read_available();
+        let mut frame: Vec<u8> = Vec::new();
+        ProtocolVersion::LATEST
+            .encode_clientbound(
+                &Clientbound::StatusResponse {
+                    response: ServerStatus::default(),
+                },
+                &mut frame,
+            )
+            .unwrap();
+        assert_eq!(buf[buf.len() - frame.len()..], frame);
+        assert!(login.read_available().is_empty());
+    }
+
+    #[test]
+    fn test_unexpected_packet() {
+        let mut app = app();
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Status);
+
+        app.world_mut()
+            .commands()
+            .send_packet(entity, Clientbound::KeepAlive { id: 1 });
+        app.world_mut().flush();
+        app.update();
+
+        assert!(app.world().get_entity(entity).is_err());
+        assert!(client.read_available().is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+//! The server list ping of clients since 1.7, the legacy ping is answered by [`process_data`](crate::process_data).
+
+use bevy_ecs::prelude::*;
+use tracing::{instrument, trace, warn};
+use ussr_protocol::neutral::{Clientbound, Serverbound};
+
+use crate::{Connection, DisconnectCause, PacketReceived, Status};
+
+/// A system that answers status requests with the [`Status`] and pings with their payload.
+/// The client closes the connection after the ping, or it times out after [`Timeouts::handshake`](crate::Timeouts::handshake).
+#[instrument(skip_all, level = "trace")]
+pub(crate) fn respond_status(
+    status: Res<Status>,
+    mut events: EventReader<PacketReceived>,
+    mut query: Query<&mut Connection>,
+) {
+    for event in events.read() {
+        let packet: Clientbound = match &event.packet {
+            Serverbound::StatusRequest => Clientbound::StatusResponse {
+                response: status.0.clone(),
+            },
+            Serverbound::PingRequest { payload } => Clientbound::PingResponse { payload: *payload },
+            _ => continue,
+        };
+        let Ok(mut connection) = query.get_mut(event.entity) else {
+            continue;
+        };
+        if connection.closing.is_some() {
+            continue;
+        }
+
+        trace!("{packet:?}");
+        if let Err(e) = connection.write_packet(&packet) {
+            warn!("Failed to send {packet:?}: {e}, disconnecting");
+            connection.disconnect(DisconnectCause::SendFailed(e.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use bevy_app::prelude::*;
+    use ussr_buf::{read_frame, VarDecode};
+    use ussr_protocol::State;
+
+    use super::*;
+    use crate::{memory, process_data, read_data, send_data, ClientDisconnected, MAX_PACKET_SIZE};
+
+    #[test]
+    fn test_status() {
+        let mut app = App::new();
+        app.init_resource::<Status>()
+            .add_event::<PacketReceived>()
+            .add_event::<ClientDisconnected>()
+            .add_systems(
+                Update,
+                (read_data, process_data, respond_status, send_data).chain(),
+            );
+        let (entity, mut client) = memory::connect(app.world_mut(), State::Handshaking);
+
+        #[rustfmt::skip]
+        client.write_all(&[
+            // Handshake
+            0x0F, 0x00, 0x2F,
+            0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+            0x63, 0xDD, 0x01,
+            // Status request
+            0x01, 0x00,
+        ])
+        .unwrap();
+        app.update();
+
+        let received: Vec<u8> = client.read_available();
+        let frame: Vec<u8> = read_frame(&mut &received[..], MAX_PACKET_SIZE).unwrap();
+        let mut reader: &[u8] = &frame;
+        assert_eq!(u32::var_decode(&mut reader).unwrap(), 0x00);
+        assert!(String::from_utf8_lossy(reader).contains("\"protocol\""));
+
+        client
+            .write_all(&[0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 42])
+            .unwrap();
+        app.update();
+        let mut pong = [0; 10];
+        client.read_exact(&mut pong).unwrap();
+        assert_eq!(pong, [0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 42]);
+        assert!(app
+            .world()
+            .get::<Connection>(entity)
+            .unwrap()
+            .closing
+            .is_none());
+    }
+}
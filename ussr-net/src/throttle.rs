@@ -0,0 +1,396 @@
+//! Limits on what clients can send, against floods and scanners.
+//!
+//! Connections are counted by [`ClientAddress`], once it is known.
+//! An IP address that opens connections too fast, or whose connection sends too much, is blocked for a while,
+//! and the connections it opens in the meantime are closed right away.
+
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use bevy_ecs::prelude::*;
+use tracing::{instrument, trace};
+use ussr_protocol::State;
+
+use crate::{ClientAddress, ClientDisconnected, Connection, DisconnectCause};
+
+/// A number of things allowed per period, which can all happen at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rate {
+    pub count: u32,
+    pub period: Duration,
+}
+
+impl Rate {
+    pub const fn per_second(count: u32) -> Rate {
+        Rate {
+            count,
+            period: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The frames, and their bytes, a connection may send in a state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PacketBudget {
+    pub packets: Rate,
+    pub bytes: Rate,
+}
+
+/// The limits on connections and the data they send.
+/// Without this resource, nothing is limited.
+#[derive(Resource, Debug, Clone)]
+pub struct RateLimits {
+    /// How fast an IP address may open connections.
+    pub connections_per_ip: Rate,
+    /// The maximum number of connections open at once from an IP address.
+    pub max_connections_per_ip: usize,
+    pub handshaking: PacketBudget,
+    pub status: PacketBudget,
+    pub login: PacketBudget,
+    pub play: PacketBudget,
+    /// How long an IP address is blocked after going over a limit.
+    pub block_duration: Duration,
+}
+
+impl Default for RateLimits {
+    fn default() -> RateLimits {
+        RateLimits {
+            connections_per_ip: Rate {
+                count: 10,
+                period: Duration::from_secs(10),
+            },
+            max_connections_per_ip: 16,
+            handshaking: PacketBudget {
+                packets: Rate::per_second(10),
+                // BungeeCord forwarding adds the profile of the player to the handshake
+                bytes: Rate::per_second(32 * 1024),
+            },
+            status: PacketBudget {
+                packets: Rate::per_second(10),
+                bytes: Rate::per_second(1024),
+            },
+            login: PacketBudget {
+                packets: Rate::per_second(10),
+                bytes: Rate::per_second(4 * 1024),
+            },
+            play: PacketBudget {
+                packets: Rate::per_second(500),
+                bytes: Rate::per_second(1024 * 1024),
+            },
+            block_duration: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RateLimits {
+    fn budget(&self, state: State) -> &PacketBudget {
+        match state {
+            State::Handshaking => &self.handshaking,
+            State::Status => &self.status,
+            State::Login => &self.login,
+            State::Play => &self.play,
+        }
+    }
+}
+
+/// A token bucket, which refills at a [`Rate`] up to its count.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(rate: Rate, now: Instant) -> Bucket {
+        Bucket {
+            tokens: rate.count.into(),
+            updated: now,
+        }
+    }
+
+    /// Takes `amount` tokens, if there are enough.
+    fn take(&mut self, rate: Rate, amount: f64, now: Instant) -> bool {
+        let refill: f64 =
+            now.duration_since(self.updated).as_secs_f64() / rate.period.as_secs_f64();
+        self.tokens = (self.tokens + refill * f64::from(rate.count)).min(rate.count.into());
+        self.updated = now;
+        if self.tokens < amount {
+            return false;
+        }
+        self.tokens -= amount;
+        true
+    }
+
+    fn is_full(&mut self, rate: Rate, now: Instant) -> bool {
+        self.take(rate, 0.0, now) && self.tokens >= f64::from(rate.count)
+    }
+}
+
+/// What a connection sent in its current state, see [`RateLimits::budget`].
+#[derive(Debug)]
+pub(crate) struct Usage {
+    state: State,
+    packets: Bucket,
+    bytes: Bucket,
+}
+
+impl Connection {
+    /// Counts a received frame of `length` bytes, returning `false` if it goes over the budget of the state.
+    pub(crate) fn count_frame(&mut self, limits: &RateLimits, length: usize) -> bool {
+        let now: Instant = Instant::now();
+        let budget: &PacketBudget = limits.budget(self.state);
+        let usage: &mut Usage = match &mut self.usage {
+            Some(usage) if usage.state == self.state => usage,
+            usage => usage.insert(Usage {
+                state: self.state,
+                packets: Bucket::full(budget.packets, now),
+                bytes: Bucket::full(budget.bytes, now),
+            }),
+        };
+        usage.packets.take(budget.packets, 1.0, now)
+            && usage.bytes.take(budget.bytes, length as f64, now)
+    }
+}
+
+/// The connections of an IP address.
+#[derive(Debug)]
+struct Client {
+    open: usize,
+    connections: Bucket,
+    blocked_until: Option<Instant>,
+}
+
+/// The clients that connected recently, which are forgotten once they are back to normal.
+#[derive(Resource, Debug)]
+pub(crate) struct Clients {
+    clients: HashMap<IpAddr, Client>,
+    /// The address each open connection is counted for.
+    counted: HashMap<Entity, IpAddr>,
+    last_cleanup: Instant,
+}
+
+impl Default for Clients {
+    fn default() -> Clients {
+        Clients {
+            clients: HashMap::new(),
+            counted: HashMap::new(),
+            last_cleanup: Instant::now(),
+        }
+    }
+}
+
+impl Clients {
+    fn client(&mut self, ip: IpAddr, limits: &RateLimits, now: Instant) -> &mut Client {
+        self.clients.entry(ip).or_insert_with(|| Client {
+            open: 0,
+            connections: Bucket::full(limits.connections_per_ip, now),
+            blocked_until: None,
+        })
+    }
+
+    fn block(&mut self, ip: IpAddr, limits: &RateLimits, now: Instant) {
+        trace!("Blocking {ip}");
+        self.client(ip, limits, now).blocked_until = Some(now + limits.block_duration);
+    }
+
+    fn release(&mut self, entity: Entity) -> Option<IpAddr> {
+        let ip: IpAddr = self.counted.remove(&entity)?;
+        if let Some(client) = self.clients.get_mut(&ip) {
+            client.open -= 1;
+        }
+        Some(ip)
+    }
+}
+
+/// A system that counts the connections of every [`ClientAddress`],
+/// closes the ones over the [`RateLimits`], and blocks the addresses of offenders.
+#[instrument(skip_all, level = "trace")]
+pub(crate) fn limit_clients(
+    limits: Res<RateLimits>,
+    mut clients: ResMut<Clients>,
+    mut events: EventReader<ClientDisconnected>,
+    mut query: Query<(Entity, &mut Connection, &ClientAddress), Changed<ClientAddress>>,
+) {
+    let now: Instant = Instant::now();
+
+    for event in events.read() {
+        let Some(ip) = clients.release(event.entity) else {
+            continue;
+        };
+        if event.cause == DisconnectCause::RateLimited {
+            clients.block(ip, &limits, now);
+        }
+    }
+
+    for (entity, mut connection, &ClientAddress(ip)) in &mut query {
+        // The address changes when it is forwarded by a proxy.
+        // Connections that are already closing are still counted, to block their address if they flooded.
+        clients.release(entity);
+        let client: &mut Client = clients.client(ip, &limits, now);
+        if client.blocked_until.is_some_and(|until| now < until) {
+            trace!("{ip} is blocked, disconnecting");
+            connection.disconnect(DisconnectCause::Throttled);
+            continue;
+        }
+        if !client.connections.take(limits.connections_per_ip, 1.0, now) {
+            trace!("{ip} connects too fast, disconnecting");
+            connection.disconnect(DisconnectCause::Throttled);
+            clients.block(ip, &limits, now);
+            continue;
+        }
+        if client.open >= limits.max_connections_per_ip {
+            trace!("{ip} has too many connections, disconnecting");
+            connection.disconnect(DisconnectCause::Throttled);
+            continue;
+        }
+        client.open += 1;
+        clients.counted.insert(entity, ip);
+    }
+
+    if now.duration_since(clients.last_cleanup) >= limits.connections_per_ip.period {
+        clients.last_cleanup = now;
+        clients.clients.retain(|_, client| {
+            client.open > 0
+                || client.blocked_until.is_some_and(|until| now < until)
+                || !client.connections.is_full(limits.connections_per_ip, now)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use bevy_app::prelude::*;
+
+    use super::*;
+    use crate::{memory, process_data::process_data, read_data, send_data, PacketReceived, Status};
+
+    fn app(limits: RateLimits) -> App {
+        let mut app = App::new();
+        app.init_resource::<Status>()
+            .init_resource::<Clients>()
+            .insert_resource(limits)
+            .add_event::<PacketReceived>()
+            .add_event::<ClientDisconnected>()
+            .add_systems(
+                Update,
+                (read_data, process_data, limit_clients, send_data).chain(),
+            );
+        app
+    }
+
+    fn connect(app: &mut App, ip: &str) -> (Entity, memory::MemoryClient) {
+        let (entity, client) = memory::connect(app.world_mut(), State::Handshaking);
+        app.world_mut()
+            .entity_mut(entity)
+            .insert(ClientAddress(ip.parse().unwrap()));
+        (entity, client)
+    }
+
+    fn causes(app: &App) -> Vec<(Entity, DisconnectCause)> {
+        let events = app.world().resource::<Events<ClientDisconnected>>();
+        events
+            .iter_current_update_events()
+            .map(|event| (event.entity, event.cause.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_bucket() {
+        let rate = Rate::per_second(10);
+        let start: Instant = Instant::now();
+        let mut bucket = Bucket::full(rate, start);
+        assert!(bucket.take(rate, 10.0, start));
+        assert!(!bucket.take(rate, 1.0, start));
+        assert!(bucket.take(rate, 5.0, start + Duration::from_millis(500)));
+        assert!(!bucket.is_full(rate, start + Duration::from_millis(500)));
+        // It doesn't fill past its count
+        assert!(bucket.is_full(rate, start + Duration::from_secs(60)));
+        assert!(!bucket.take(rate, 11.0, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_max_connections() {
+        let mut app = app(RateLimits {
+            max_connections_per_ip: 2,
+            ..RateLimits::default()
+        });
+        let _first = connect(&mut app, "192.0.2.1");
+        let (second, _second_client) = connect(&mut app, "192.0.2.1");
+        let (third, _third_client) = connect(&mut app, "192.0.2.1");
+        let _other = connect(&mut app, "192.0.2.2");
+        app.update();
+        assert_eq!(causes(&app), [(third, DisconnectCause::Throttled)]);
+
+        // A connection can be opened once another one is closed
+        app.world_mut()
+            .get_mut::<Connection>(second)
+            .unwrap()
+            .disconnect(DisconnectCause::Closed);
+        app.update();
+        let (_, _fourth_client) = connect(&mut app, "192.0.2.1");
+        app.update();
+        assert!(causes(&app).is_empty());
+    }
+
+    #[test]
+    fn test_connection_rate() {
+        let mut app = app(RateLimits {
+            connections_per_ip: Rate {
+                count: 2,
+                period: Duration::from_secs(60),
+            },
+            ..RateLimits::default()
+        });
+        let clients: Vec<_> = (0..3).map(|_| connect(&mut app, "192.0.2.1")).collect();
+        app.update();
+        assert_eq!(causes(&app), [(clients[2].0, DisconnectCause::Throttled)]);
+
+        // The address is blocked, even once its connections are closed
+        drop(clients);
+        app.update();
+        let (entity, _client) = connect(&mut app, "192.0.2.1");
+        app.update();
+        assert_eq!(causes(&app), [(entity, DisconnectCause::Throttled)]);
+        let (_, _client) = connect(&mut app, "192.0.2.2");
+        app.update();
+        assert!(causes(&app).is_empty());
+    }
+
+    #[test]
+    fn test_packet_budget() {
+        let mut app = app(RateLimits {
+            status: PacketBudget {
+                packets: Rate::per_second(2),
+                bytes: Rate::per_second(1024),
+            },
+            ..RateLimits::default()
+        });
+        let (entity, mut client) = connect(&mut app, "192.0.2.1");
+        // A status handshake, a status request and two pings, the last one going over the budget
+        #[rustfmt::skip]
+        client.write_all(&[
+            0x0F, 0x00, 0x2F,
+            0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+            0x63, 0xDD, 0x01,
+            0x01, 0x00,
+            0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 1,
+            0x09, 0x01, 0, 0, 0, 0, 0, 0, 0, 2,
+        ])
+        .unwrap();
+        app.update();
+        assert_eq!(causes(&app), [(entity, DisconnectCause::RateLimited)]);
+        let events = app.world().resource::<Events<PacketReceived>>();
+        assert_eq!(events.iter_current_update_events().count(), 3);
+
+        // Which blocks the address
+        app.update();
+        let (entity, _client) = connect(&mut app, "192.0.2.1");
+        app.update();
+        assert_eq!(causes(&app), [(entity, DisconnectCause::Throttled)]);
+    }
+}
@@ -0,0 +1,626 @@
+//! Socket IO, which runs outside of the schedule.
+//!
+//! The sockets of every connection are polled by a single [`IoThread`] with [`mio`],
+//! which exchanges data with the ECS through channels in a [`Transport`].
+//! The systems only move data between the channels and the buffers of a [`Connection`](crate::Connection),
+//! so they never wait on a socket.
+
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind, Read, Write},
+    net::Shutdown,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use async_channel::{Receiver, Sender, TryRecvError, TrySendError};
+use bevy_ecs::prelude::*;
+use bytes::BytesMut;
+use mio::{event::Source, Events, Interest, Poll, Registry, Token, Waker};
+use thiserror::Error;
+use tracing::{error, trace};
+
+use crate::listener::Stream;
+
+/// The size of the read buffer of the IO thread.
+const READ_BUFFER_SIZE: usize = 16 * 1024;
+
+/// The number of reads that can be waiting for the ECS before the IO thread stops reading a connection.
+const INCOMING_CAPACITY: usize = 64;
+
+/// The number of bytes that can be waiting to be written before a connection stops accepting more.
+/// This is about a full view distance of uncompressed chunks.
+pub(crate) const MAX_QUEUED_BYTES: usize = 16 * 1024 * 1024;
+
+/// The token of the [`Waker`] of the IO thread.
+const WAKER: Token = Token(usize::MAX);
+
+/// Why data couldn't be queued by [`Transport::send`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub(crate) enum QueueError {
+    #[error("connection closed")]
+    Closed,
+    /// The client doesn't read what is sent to it fast enough.
+    #[error("{0} bytes are still waiting to be written")]
+    Backlogged(usize),
+}
+
+/// The thread that does the IO of every connection.
+/// It stops once it is dropped and the last connection is closed.
+#[derive(Resource)]
+pub(crate) struct IoThread {
+    registrations: Sender<Registration>,
+    notifier: Notifier,
+    next_token: AtomicUsize,
+}
+
+/// A connection to add to the IO thread.
+struct Registration {
+    token: Token,
+    socket: Socket,
+    incoming: Sender<Vec<u8>>,
+    outgoing: Receiver<Vec<u8>>,
+    queued: Arc<AtomicUsize>,
+    paused: Arc<AtomicBool>,
+}
+
+/// Tells the IO thread that a connection has something to do.
+#[derive(Clone)]
+struct Notifier {
+    tokens: Sender<Token>,
+    waker: Arc<Waker>,
+}
+
+impl Notifier {
+    fn notify(&self, token: Token) {
+        // Fails once the IO thread stopped
+        if self.tokens.try_send(token).is_ok() {
+            let _ = self.waker.wake();
+        }
+    }
+}
+
+impl IoThread {
+    pub(crate) fn start() -> io::Result<IoThread> {
+        let poll: Poll = Poll::new()?;
+        let waker: Arc<Waker> = Arc::new(Waker::new(poll.registry(), WAKER)?);
+        let (registrations, registration_receiver) = async_channel::unbounded();
+        let (tokens, token_receiver) = async_channel::unbounded();
+
+        thread::Builder::new()
+            .name("ussr-net io".to_owned())
+            .spawn(move || run(poll, registration_receiver, token_receiver))?;
+
+        Ok(IoThread {
+            registrations,
+            notifier: Notifier { tokens, waker },
+            next_token: AtomicUsize::new(0),
+        })
+    }
+
+    /// Hands an accepted socket to the IO thread.
+    pub(crate) fn register(&self, stream: Stream) -> io::Result<Transport> {
+        stream.set_nonblocking(true)?;
+        let socket: Socket = Socket::from(stream);
+        let token = Token(self.next_token.fetch_add(1, Ordering::Relaxed));
+        let (incoming_sender, incoming) = async_channel::bounded(INCOMING_CAPACITY);
+        let (outgoing, outgoing_receiver) = async_channel::unbounded();
+        let queued: Arc<AtomicUsize> = Arc::default();
+        let paused: Arc<AtomicBool> = Arc::default();
+
+        self.registrations
+            .try_send(Registration {
+                token,
+                socket,
+                incoming: incoming_sender,
+                outgoing: outgoing_receiver,
+                queued: queued.clone(),
+                paused: paused.clone(),
+            })
+            .map_err(|_| io::Error::new(ErrorKind::BrokenPipe, "the IO thread stopped"))?;
+        self.notifier.waker.wake()?;
+
+        Ok(Transport {
+            incoming,
+            outgoing,
+            queued,
+            paused,
+            notifier: Some((token, self.notifier.clone())),
+        })
+    }
+}
+
+impl Drop for IoThread {
+    fn drop(&mut self) {
+        self.registrations.close();
+        let _ = self.notifier.waker.wake();
+    }
+}
+
+/// The ECS side of a connection.
+/// The connection is closed once the [`Transport`] is dropped and everything sent has been written.
+pub(crate) struct Transport {
+    incoming: Receiver<Vec<u8>>,
+    outgoing: Sender<Vec<u8>>,
+    /// The number of bytes sent that weren't written yet.
+    queued: Arc<AtomicUsize>,
+    /// Set by the IO thread when it stops reading because the incoming channel is full.
+    paused: Arc<AtomicBool>,
+    /// Wakes the IO thread, `None` for in-memory connections.
+    notifier: Option<(Token, Notifier)>,
+}
+
+impl Transport {
+    /// Creates an in-memory transport from the ends of the channels of its client.
+    /// The client subtracts what it reads from `queued`.
+    pub(crate) fn new(
+        incoming: Receiver<Vec<u8>>,
+        outgoing: Sender<Vec<u8>>,
+        queued: Arc<AtomicUsize>,
+    ) -> Transport {
+        Transport {
+            incoming,
+            outgoing,
+            queued,
+            paused: Arc::default(),
+            notifier: None,
+        }
+    }
+
+    /// Appends everything read since the last call to `buf`.
+    /// Returns `false` if the connection was closed.
+    pub(crate) fn receive(&self, buf: &mut BytesMut) -> bool {
+        let open: bool = loop {
+            match self.incoming.try_recv() {
+                Ok(data) => buf.extend_from_slice(&data),
+                Err(TryRecvError::Empty) => break true,
+                Err(TryRecvError::Closed) => break false,
+            }
+        };
+
+        // The IO thread stops reading while the channel is full.
+        // The flag is only cleared after draining, so a pause during the drain is seen by the next call.
+        if self.paused.swap(false, Ordering::AcqRel) {
+            self.notify();
+        }
+        open
+    }
+
+    /// Queues data to be written.
+    /// Fails if the connection was closed, or if more than [`MAX_QUEUED_BYTES`] are still waiting.
+    pub(crate) fn send(&self, data: Vec<u8>) -> Result<(), QueueError> {
+        let queued: usize = self.queued.load(Ordering::Relaxed);
+        if queued > MAX_QUEUED_BYTES {
+            return Err(QueueError::Backlogged(queued));
+        }
+
+        self.queued.fetch_add(data.len(), Ordering::Relaxed);
+        self.outgoing
+            .try_send(data)
+            .map_err(|_| QueueError::Closed)?;
+        self.notify();
+        Ok(())
+    }
+
+    fn notify(&self) {
+        if let Some((token, notifier)) = &self.notifier {
+            notifier.notify(*token);
+        }
+    }
+}
+
+impl Drop for Transport {
+    fn drop(&mut self) {
+        // The IO thread writes what is left, and closes the connection once it sees the closed channel
+        self.outgoing.close();
+        self.incoming.close();
+        self.notify();
+    }
+}
+
+/// An accepted socket registered with [`mio`].
+enum Socket {
+    Tcp(mio::net::TcpStream),
+    #[cfg(unix)]
+    Unix(mio::net::UnixStream),
+}
+
+impl From<Stream> for Socket {
+    /// The stream must be non-blocking.
+    fn from(stream: Stream) -> Socket {
+        match stream {
+            Stream::Tcp(stream) => Socket::Tcp(mio::net::TcpStream::from_std(stream)),
+            #[cfg(unix)]
+            Stream::Unix(stream) => Socket::Unix(mio::net::UnixStream::from_std(stream)),
+        }
+    }
+}
+
+impl Socket {
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match self {
+            Socket::Tcp(stream) => stream.shutdown(how),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.shutdown(how),
+        }
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Socket::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Socket::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Socket::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+impl Source for Socket {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match self {
+            Socket::Tcp(stream) => stream.register(registry, token, interests),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.register(registry, token, interests),
+        }
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match self {
+            Socket::Tcp(stream) => stream.reregister(registry, token, interests),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.reregister(registry, token, interests),
+        }
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        match self {
+            Socket::Tcp(stream) => stream.deregister(registry),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.deregister(registry),
+        }
+    }
+}
+
+/// The IO thread side of a connection.
+struct IoConnection {
+    socket: Socket,
+    /// `None` once the client closed its side of the connection.
+    incoming: Option<Sender<Vec<u8>>>,
+    outgoing: Receiver<Vec<u8>>,
+    queued: Arc<AtomicUsize>,
+    /// Shared with [`Transport::paused`].
+    paused: Arc<AtomicBool>,
+    /// A read that didn't fit in the full incoming channel.
+    blocked: Option<Vec<u8>>,
+    /// What is left of the data being written.
+    pending: Vec<u8>,
+}
+
+impl IoConnection {
+    /// Reads and writes as much as possible.
+    /// Returns `false` once the connection is done.
+    fn process(&mut self, buf: &mut [u8]) -> bool {
+        self.read(buf);
+        self.write()
+    }
+
+    fn read(&mut self, buf: &mut [u8]) {
+        if let Some(data) = self.blocked.take() {
+            if !self.deliver(data) {
+                return;
+            }
+        }
+
+        while self.incoming.is_some() {
+            match self.socket.read(buf) {
+                Ok(0) => {
+                    trace!("Connection closed");
+                    self.incoming = None;
+                }
+                Ok(n) => {
+                    trace!("Read {n} bytes");
+                    if !self.deliver(buf[..n].to_vec()) {
+                        return;
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    trace!("Error: {e:?}");
+                    self.incoming = None;
+                }
+            }
+        }
+    }
+
+    /// Hands read data to the ECS.
+    /// Returns `false` if reading has to stop.
+    fn deliver(&mut self, data: Vec<u8>) -> bool {
+        let Some(incoming) = &self.incoming else {
+            return false;
+        };
+        match incoming.try_send(data) {
+            Ok(()) => true,
+            // Reading resumes once the ECS catches up, see `Transport::receive`
+            Err(TrySendError::Full(data)) => {
+                self.blocked = Some(data);
+                self.paused.store(true, Ordering::Release);
+                false
+            }
+            Err(TrySendError::Closed(_)) => {
+                self.incoming = None;
+                false
+            }
+        }
+    }
+
+    /// Returns `false` once everything was written and the transport was dropped, or if writing failed.
+    fn write(&mut self) -> bool {
+        loop {
+            if self.pending.is_empty() {
+                match self.outgoing.try_recv() {
+                    Ok(data) => self.pending = data,
+                    Err(TryRecvError::Empty) => return true,
+                    Err(TryRecvError::Closed) => return false,
+                }
+                continue;
+            }
+
+            match self.socket.write(&self.pending) {
+                Ok(0) => {
+                    trace!("Error: {:?}", io::Error::from(ErrorKind::WriteZero));
+                    return false;
+                }
+                Ok(n) => {
+                    trace!("Wrote {n} bytes");
+                    self.pending.drain(..n);
+                    self.queued.fetch_sub(n, Ordering::Relaxed);
+                }
+                // Writing resumes once the socket is writable again
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return true,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => {
+                    trace!("Error: {e:?}");
+                    return false;
+                }
+            }
+        }
+    }
+
+    fn close(mut self, registry: &Registry) {
+        let _ = self.socket.shutdown(Shutdown::Both);
+        let _ = registry.deregister(&mut self.socket);
+    }
+}
+
+fn run(mut poll: Poll, registrations: Receiver<Registration>, tokens: Receiver<Token>) {
+    let mut events = Events::with_capacity(1024);
+    let mut connections: HashMap<Token, IoConnection> = HashMap::new();
+    let mut buf: Vec<u8> = vec![0; READ_BUFFER_SIZE];
+    let mut stopping: bool = false;
+
+    loop {
+        if let Err(e) = poll.poll(&mut events, None) {
+            if e.kind() == ErrorKind::Interrupted {
+                continue;
+            }
+            error!("Failed to poll connections: {e}");
+            return;
+        }
+
+        let mut ready: Vec<Token> = events
+            .iter()
+            .map(|event| event.token())
+            .filter(|token| *token != WAKER)
+            .collect();
+
+        loop {
+            match registrations.try_recv() {
+                Ok(mut registration) => {
+                    let interests: Interest = Interest::READABLE | Interest::WRITABLE;
+                    match poll.registry().register(
+                        &mut registration.socket,
+                        registration.token,
+                        interests,
+                    ) {
+                        Ok(()) => {
+                            ready.push(registration.token);
+                            connections.insert(
+                                registration.token,
+                                IoConnection {
+                                    socket: registration.socket,
+                                    incoming: Some(registration.incoming),
+                                    outgoing: registration.outgoing,
+                                    queued: registration.queued,
+                                    paused: registration.paused,
+                                    blocked: None,
+                                    pending: Vec::new(),
+                                },
+                            );
+                        }
+                        // Dropping the channels closes the transport
+                        Err(e) => error!("Failed to register connection: {e}"),
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Closed) => {
+                    stopping = true;
+                    break;
+                }
+            }
+        }
+        while let Ok(token) = tokens.try_recv() {
+            ready.push(token);
+        }
+
+        for token in ready {
+            if let Some(connection) = connections.get_mut(&token) {
+                if !connection.process(&mut buf) {
+                    if let Some(connection) = connections.remove(&token) {
+                        connection.close(poll.registry());
+                    }
+                }
+            }
+        }
+
+        if stopping && connections.is_empty() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{TcpListener, TcpStream},
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+
+    fn connect(io: &IoThread) -> (Transport, TcpStream) {
+        let listener: TcpListener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client: TcpStream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        (io.register(Stream::Tcp(stream)).unwrap(), client)
+    }
+
+    fn receive(transport: &Transport, len: usize) -> BytesMut {
+        let mut buf = BytesMut::new();
+        let start: Instant = Instant::now();
+        while buf.len() < len {
+            assert!(transport.receive(&mut buf));
+            assert!(start.elapsed() < Duration::from_secs(5), "Timed out");
+            thread::yield_now();
+        }
+        buf
+    }
+
+    #[test]
+    fn test_tcp_transport() {
+        let io: IoThread = IoThread::start().unwrap();
+        let (transport, mut client) = connect(&io);
+        let (other, mut other_client) = connect(&io);
+
+        client.write_all(&[1, 2, 3]).unwrap();
+        other_client.write_all(&[4]).unwrap();
+        assert_eq!(receive(&transport, 3)[..], [1, 2, 3]);
+        assert_eq!(receive(&other, 1)[..], [4]);
+
+        // Everything sent is written before the connection is closed
+        transport.send(vec![4, 5]).unwrap();
+        transport.send(vec![6]).unwrap();
+        drop(transport);
+        let mut received: Vec<u8> = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert_eq!(received, [4, 5, 6]);
+
+        // Closing the client is seen by the transport
+        drop(other_client);
+        let mut buf = BytesMut::new();
+        let start: Instant = Instant::now();
+        while other.receive(&mut buf) {
+            assert!(start.elapsed() < Duration::from_secs(5), "Timed out");
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_incoming_capacity() {
+        let io: IoThread = IoThread::start().unwrap();
+        let (transport, mut client) = connect(&io);
+
+        // Reading stops while the channel is full, and resumes once it is drained
+        let data: Vec<u8> = (0..READ_BUFFER_SIZE * INCOMING_CAPACITY * 2)
+            .map(|i| i as u8)
+            .collect();
+        let writer = thread::spawn(move || client.write_all(&data).map(|()| client));
+        let start: Instant = Instant::now();
+        while !transport.incoming.is_full() {
+            assert!(start.elapsed() < Duration::from_secs(5), "Timed out");
+            thread::yield_now();
+        }
+        let received: BytesMut = receive(&transport, READ_BUFFER_SIZE * INCOMING_CAPACITY * 2);
+        writer.join().unwrap().unwrap();
+        assert!(received
+            .iter()
+            .enumerate()
+            .all(|(i, byte)| *byte == i as u8));
+    }
+
+    #[test]
+    fn test_drain_while_reading() {
+        let io: IoThread = IoThread::start().unwrap();
+        let (transport, mut client) = connect(&io);
+
+        // Draining without yielding makes the channel fill up while it is being drained,
+        // which must not stop the IO thread from reading
+        let len: usize = READ_BUFFER_SIZE * INCOMING_CAPACITY * 16;
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let writer = thread::spawn(move || {
+            for chunk in data.chunks(READ_BUFFER_SIZE / 4) {
+                client.write_all(chunk)?;
+            }
+            Ok::<_, io::Error>(client)
+        });
+        let mut received = BytesMut::new();
+        let start: Instant = Instant::now();
+        while received.len() < len {
+            assert!(transport.receive(&mut received));
+            assert!(start.elapsed() < Duration::from_secs(10), "Timed out");
+        }
+        writer.join().unwrap().unwrap();
+        assert!(received
+            .iter()
+            .enumerate()
+            .all(|(i, byte)| *byte == i as u8));
+    }
+
+    #[test]
+    fn test_backlog() {
+        let io: IoThread = IoThread::start().unwrap();
+        let (transport, _client) = connect(&io);
+
+        // The client never reads, so the data piles up once the socket buffers are full
+        let start: Instant = Instant::now();
+        let error: QueueError = loop {
+            if let Err(e) = transport.send(vec![0; 1024 * 1024]) {
+                break e;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5), "Timed out");
+        };
+        assert!(matches!(error, QueueError::Backlogged(queued) if queued > MAX_QUEUED_BYTES));
+    }
+}
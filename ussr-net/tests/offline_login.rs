@@ -0,0 +1,99 @@
+//! Logs in a client over a loopback socket, through the whole offline login.
+
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpStream},
+    thread,
+    time::Duration,
+};
+
+use bevy_app::prelude::*;
+use bevy_ecs::prelude::*;
+use ussr_buf::{read_frame, VarDecode};
+use ussr_net::{
+    ListenAddress, Listeners, Player, PlayerUuid, Username, UssrNetPlugin, MAX_PACKET_SIZE,
+};
+
+/// Reads the frames sent to the client, until there are `count` of them.
+fn read_frames(app: &mut App, client: &mut TcpStream, count: usize) -> Vec<Vec<u8>> {
+    let mut buf: Vec<u8> = Vec::new();
+    for _ in 0..1000 {
+        app.update();
+        let mut chunk = [0; 1024];
+        match client.read(&mut chunk) {
+            Ok(0) => panic!("Connection closed"),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => panic!("{e}"),
+        }
+
+        let mut reader: &[u8] = &buf;
+        let frames: Vec<Vec<u8>> =
+            std::iter::from_fn(|| read_frame(&mut reader, MAX_PACKET_SIZE).ok())
+                .take(count)
+                .collect();
+        if frames.len() == count {
+            return frames;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    panic!("Timed out");
+}
+
+fn string(reader: &mut &[u8]) -> String {
+    let len: usize = usize::var_decode(reader).unwrap();
+    let (string, rest) = reader.split_at(len);
+    *reader = rest;
+    String::from_utf8(string.to_vec()).unwrap()
+}
+
+#[test]
+fn test_offline_login() {
+    let mut app = App::new();
+    app.add_plugins(UssrNetPlugin {
+        listeners: vec![SocketAddr::from((Ipv4Addr::LOCALHOST, 0)).into()],
+        ..UssrNetPlugin::default()
+    });
+
+    let [ListenAddress::Tcp(address)] = app.world().resource::<Listeners>().local_addresses()[..]
+    else {
+        panic!("Expected a single TCP listener");
+    };
+    let mut client: TcpStream = TcpStream::connect(address).unwrap();
+    client.set_nonblocking(true).unwrap();
+    #[rustfmt::skip]
+    client.write_all(&[
+        // Handshake
+        0x0F, 0x00, 0x2F,
+        0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+        0x63, 0xDD, 0x02,
+        // Login start
+        0x07, 0x00, 0x05, b'N', b'o', b't', b'c', b'h',
+    ])
+    .unwrap();
+
+    let frames: Vec<Vec<u8>> = read_frames(&mut app, &mut client, 2);
+
+    // Login success
+    let mut login_success: &[u8] = &frames[0];
+    assert_eq!(u32::var_decode(&mut login_success).unwrap(), 0x02);
+    assert_eq!(
+        string(&mut login_success),
+        "b50ad385-829d-3141-a216-7e7d7539ba7f"
+    );
+    assert_eq!(string(&mut login_success), "Notch");
+    assert!(login_success.is_empty());
+
+    // Join game, which can only be sent in the play state, with the entity ID of the player
+    let mut join_game: &[u8] = &frames[1];
+    assert_eq!(u32::var_decode(&mut join_game).unwrap(), 0x01);
+    let entity_id: u32 = u32::from_be_bytes(join_game[..4].try_into().unwrap());
+
+    let mut query = app
+        .world_mut()
+        .query_filtered::<(Entity, &Username, &PlayerUuid), With<Player>>();
+    let (entity, username, uuid) = query.single(app.world());
+    assert_eq!(entity.index(), entity_id);
+    assert_eq!(username.0, "Notch");
+    assert_eq!(uuid.0.to_string(), "b50ad385-829d-3141-a216-7e7d7539ba7f");
+}
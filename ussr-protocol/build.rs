@@ -0,0 +1,245 @@
+//! Generates packet modules from the protocol specs in `protocol/`.
+//!
+//! Every `protocol/{version}.json` becomes `{version}.rs` in `OUT_DIR`, which contains a module for each state
+//! with a `#[packets]` mod for each direction, the same way as the hand-written packet modules.
+//! The generated code expects `Decode`, `Encode`, `packets` and everything used by the field types to be in scope.
+//!
+//! A spec looks like this:
+//!
+//! ```json
+//! {
+//!     "handshaking": { "clientbound": [], "serverbound": [...] },
+//!     "status": { ... },
+//!     "login": { ... },
+//!     "play": {
+//!         "doc": "Module documentation",
+//!         "imports": ["crate::common::PackedPosition"],
+//!         "clientbound": [
+//!             {
+//!                 "name": "KeepAlive",
+//!                 "id": 0,
+//!                 "derive": ["Copy", "Eq"],
+//!                 "fields": [{ "name": "id", "type": "i32", "var": true }]
+//!             }
+//!         ],
+//!         "serverbound": [...]
+//!     }
+//! }
+//! ```
+//!
+//! Packets without an `id` get the ID after the previous packet.
+//! Packets with `"codec": "manual"` don't derive `Decode` and `Encode`, so they can be implemented by hand.
+//! Fields can be `var`, have a `max_len`, be arrays with a `length` type (and `var_length`), or use `with` functions.
+
+use std::{env, fmt::Write, fs, path::Path};
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    handshaking: StateSpec,
+    status: StateSpec,
+    login: StateSpec,
+    play: StateSpec,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StateSpec {
+    #[serde(default)]
+    doc: Option<String>,
+    #[serde(default)]
+    imports: Vec<String>,
+    clientbound: Vec<PacketSpec>,
+    serverbound: Vec<PacketSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PacketSpec {
+    name: String,
+    #[serde(default)]
+    id: Option<u32>,
+    #[serde(default)]
+    doc: Option<String>,
+    #[serde(default)]
+    derive: Vec<String>,
+    #[serde(default)]
+    codec: Codec,
+    #[serde(default)]
+    fields: Vec<FieldSpec>,
+}
+
+#[derive(Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Codec {
+    #[default]
+    Derive,
+    Manual,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FieldSpec {
+    name: String,
+    #[serde(rename = "type")]
+    ty: String,
+    #[serde(default)]
+    doc: Option<String>,
+    #[serde(default)]
+    var: bool,
+    #[serde(default)]
+    max_len: Option<usize>,
+    #[serde(default)]
+    length: Option<String>,
+    #[serde(default)]
+    var_length: bool,
+    #[serde(default)]
+    with: Option<(String, String)>,
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=protocol");
+
+    let out_dir: String = env::var("OUT_DIR").unwrap();
+    let mut entries: Vec<_> = fs::read_dir("protocol")
+        .expect("Failed to read the protocol directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let spec: Spec = serde_json::from_str(&fs::read_to_string(&path).unwrap())
+            .unwrap_or_else(|e| panic!("Invalid protocol spec {}: {e}", path.display()));
+        let name = path.file_stem().unwrap().to_str().unwrap();
+        let out = Path::new(&out_dir).join(format!("{name}.rs"));
+        fs::write(out, generate(&spec)).unwrap();
+    }
+}
+
+fn generate(spec: &Spec) -> String {
+    let mut out = String::new();
+    for (name, state) in [
+        ("handshaking", &spec.handshaking),
+        ("status", &spec.status),
+        ("login", &spec.login),
+        ("play", &spec.play),
+    ] {
+        generate_state(&mut out, name, state);
+    }
+    out
+}
+
+fn generate_state(out: &mut String, name: &str, state: &StateSpec) {
+    let state_name: String = name[..1].to_uppercase() + &name[1..];
+
+    if let Some(doc) = &state.doc {
+        write_doc(out, "", doc);
+    }
+    writeln!(out, "pub mod {name} {{").unwrap();
+    writeln!(out, "    use super::*;").unwrap();
+    for import in &state.imports {
+        writeln!(out, "    use {import};").unwrap();
+    }
+
+    for (direction, packets) in [
+        ("clientbound", &state.clientbound),
+        ("serverbound", &state.serverbound),
+    ] {
+        writeln!(out).unwrap();
+        writeln!(out, "    #[packets({state_name})]").unwrap();
+        writeln!(out, "    pub mod {direction} {{").unwrap();
+        if !packets.is_empty() {
+            writeln!(out, "        use super::*;").unwrap();
+        }
+        for packet in packets {
+            writeln!(out).unwrap();
+            generate_packet(out, packet);
+        }
+        writeln!(out, "    }}").unwrap();
+    }
+
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+}
+
+fn generate_packet(out: &mut String, packet: &PacketSpec) {
+    const INDENT: &str = "        ";
+
+    // Keep the usual order of derives
+    let mut derives: Vec<&str> = vec!["Debug", "Clone"];
+    let has = |name: &str| packet.derive.iter().any(|derive| derive == name);
+    if has("Copy") {
+        derives.push("Copy");
+    }
+    derives.push("PartialEq");
+    if has("Eq") {
+        derives.push("Eq");
+    }
+    derives.extend(
+        packet
+            .derive
+            .iter()
+            .map(String::as_str)
+            .filter(|derive| !["Copy", "Eq"].contains(derive)),
+    );
+    if packet.codec == Codec::Derive {
+        derives.extend(["Decode", "Encode"]);
+    }
+
+    if let Some(doc) = &packet.doc {
+        write_doc(out, INDENT, doc);
+    }
+    writeln!(out, "{INDENT}#[derive({})]", derives.join(", ")).unwrap();
+    if let Some(id) = packet.id {
+        writeln!(out, "{INDENT}#[id({id:#04x})]").unwrap();
+    }
+    writeln!(out, "{INDENT}pub struct {} {{", packet.name).unwrap();
+
+    for field in &packet.fields {
+        if let Some(doc) = &field.doc {
+            write_doc(out, "            ", doc);
+        }
+        if let Some(length) = &field.length {
+            let item: &str = field
+                .ty
+                .strip_prefix("Vec<")
+                .and_then(|ty| ty.strip_suffix('>'))
+                .unwrap_or_else(|| {
+                    panic!(
+                        "{}.{} has a length but isn't a Vec",
+                        packet.name, field.name
+                    )
+                });
+            let var: &str = if field.var_length { "var, " } else { "" };
+            writeln!(out, "            #[array({var}{length}, {item})]").unwrap();
+        }
+        if field.var {
+            writeln!(out, "            #[var]").unwrap();
+        }
+        if let Some((decode, encode)) = &field.with {
+            writeln!(out, "            #[with({decode}, {encode})]").unwrap();
+        }
+        if let Some(max_len) = field.max_len {
+            writeln!(out, "            #[buf(max_len = {max_len})]").unwrap();
+        }
+        writeln!(out, "            pub {}: {},", field.name, field.ty).unwrap();
+    }
+
+    writeln!(out, "{INDENT}}}").unwrap();
+}
+
+fn write_doc(out: &mut String, indent: &str, doc: &str) {
+    for line in doc.lines() {
+        if line.is_empty() {
+            writeln!(out, "{indent}///").unwrap();
+        } else {
+            writeln!(out, "{indent}/// {line}").unwrap();
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Records a session between a client and a server into a capture, see [`ussr_protocol::capture`].
+//!
+//! ```text
+//! cargo run -p ussr-protocol --example record -- <listen address> <server address> <capture>
+//! ```
+//!
+//! The proxy accepts a single connection on the listen address and forwards it to the server.
+//! The server must be in offline mode with compression disabled
+//! (`online-mode=false` and `network-compression-threshold=-1` for vanilla),
+//! since encrypted and compressed frames can't be recorded.
+
+use std::{
+    env,
+    fs::File,
+    io::{self, BufWriter},
+    net::{Shutdown, TcpListener, TcpStream},
+    process::ExitCode,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use ussr_buf::{read_frame, write_frame, DecodeError, VarDecode};
+use ussr_protocol::{capture::CaptureWriter, Direction, ProtocolVersion, State};
+
+/// The maximum size of a frame in bytes.
+const MAX_FRAME_SIZE: usize = 2097151;
+
+struct Session {
+    state: State,
+    capture: CaptureWriter<BufWriter<File>>,
+}
+
+type SharedSession = Arc<Mutex<Session>>;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [listen_address, server_address, path] = &args[..] else {
+        eprintln!("usage: record <listen address> <server address> <capture>");
+        return ExitCode::FAILURE;
+    };
+
+    match record(listen_address, server_address, path) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn record(listen_address: &str, server_address: &str, path: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(listen_address)?;
+    println!("Waiting for a client on {}", listener.local_addr()?);
+    let (client, address) = listener.accept()?;
+    println!("Recording {address}");
+    let server = TcpStream::connect(server_address)?;
+
+    let session: SharedSession = Arc::new(Mutex::new(Session {
+        state: State::Handshaking,
+        capture: CaptureWriter::new(BufWriter::new(File::create(path)?))?,
+    }));
+
+    let serverbound = {
+        let (reader, writer) = (client.try_clone()?, server.try_clone()?);
+        let session: SharedSession = session.clone();
+        thread::spawn(move || forward(Direction::Serverbound, reader, writer, &session))
+    };
+    let clientbound = forward(Direction::Clientbound, server, client, &session);
+    let serverbound = serverbound.join().expect("The serverbound thread panicked");
+
+    session.lock().unwrap().capture.flush()?;
+    println!("Wrote {path}");
+    serverbound.and(clientbound)
+}
+
+/// Forwards and records frames until either side closes the connection.
+fn forward(
+    direction: Direction,
+    mut reader: TcpStream,
+    mut writer: TcpStream,
+    session: &Mutex<Session>,
+) -> io::Result<()> {
+    let result: io::Result<()> = copy_frames(direction, &mut reader, &mut writer, session);
+    // Closing one direction ends the other one
+    let _ = reader.shutdown(Shutdown::Both);
+    let _ = writer.shutdown(Shutdown::Both);
+    result
+}
+
+fn copy_frames(
+    direction: Direction,
+    reader: &mut TcpStream,
+    writer: &mut TcpStream,
+    session: &Mutex<Session>,
+) -> io::Result<()> {
+    loop {
+        let frame: Vec<u8> = match read_frame(reader, MAX_FRAME_SIZE) {
+            Ok(frame) => frame,
+            Err(DecodeError::Io(e)) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(io::Error::other(e)),
+        };
+
+        {
+            let mut session = session.lock().unwrap();
+            let state: State = session.state;
+            session.capture.write_frame(direction, state, &frame)?;
+            session.state = next_state(direction, state, &frame)?;
+        }
+        write_frame(writer, &frame)?;
+    }
+}
+
+/// Follows the state of the connection, and refuses frames that can't be recorded.
+fn next_state(direction: Direction, state: State, frame: &[u8]) -> io::Result<State> {
+    let mut reader: &[u8] = frame;
+    let id: u32 = u32::var_decode(&mut reader).map_err(io::Error::other)?;
+
+    match (direction, state, id) {
+        // Every version shares the handshake
+        (Direction::Serverbound, State::Handshaking, _) => {
+            let packet = ProtocolVersion::LATEST
+                .decode_serverbound(state, id, &mut reader)
+                .map_err(io::Error::other)?;
+            state.next_serverbound(&packet).map_err(io::Error::other)
+        }
+        // The IDs of the login packets are the same in every version
+        (Direction::Clientbound, State::Login, 0x01) => Err(io::Error::other(
+            "the server enabled encryption, it must be in offline mode",
+        )),
+        (Direction::Clientbound, State::Login, 0x02) => Ok(State::Play),
+        (Direction::Clientbound, State::Login, 0x03) => Err(io::Error::other(
+            "the server enabled compression, it must be disabled",
+        )),
+        _ => Ok(state),
+    }
+}
@@ -0,0 +1,257 @@
+//! A format for recording the frames of a connection, so that they can be inspected and replayed.
+//!
+//! A capture starts with [`MAGIC`], followed by the frames.
+//! Each frame is the time since the start of the capture in microseconds as a `u64`,
+//! the [`Direction`] and [`State`] as `u8`s, and the frame (the packet ID followed by its body) as a VarInt length and the bytes.
+
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use thiserror::Error;
+use ussr_buf::{read_bytes, DecodeError, VarDecode, VarEncode};
+
+use crate::{neutral, Direction, ProtocolVersion, State};
+
+/// The start of every capture, which includes the version of the format.
+pub const MAGIC: &[u8; 8] = b"USSRCAP\x01";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedFrame {
+    /// The time since the start of the capture.
+    pub time: Duration,
+    pub direction: Direction,
+    /// The state of the connection when the frame was sent.
+    pub state: State,
+    /// The packet ID followed by its body, without the length prefix.
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Capture {
+    pub frames: Vec<CapturedFrame>,
+}
+
+#[derive(Debug, Error)]
+pub enum CaptureError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("not a capture")]
+    InvalidMagic,
+
+    #[error("frame {index}: {source}")]
+    Frame { index: usize, source: DecodeError },
+}
+
+impl Capture {
+    pub fn read(reader: &mut impl Read) -> Result<Capture, CaptureError> {
+        let mut buf: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        let mut buf: &[u8] = buf
+            .strip_prefix(MAGIC.as_slice())
+            .ok_or(CaptureError::InvalidMagic)?;
+
+        let mut frames: Vec<CapturedFrame> = Vec::new();
+        while !buf.is_empty() {
+            let frame: CapturedFrame =
+                decode_frame(&mut buf).map_err(|source| CaptureError::Frame {
+                    index: frames.len(),
+                    source,
+                })?;
+            frames.push(frame);
+        }
+        Ok(Capture { frames })
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        self.frames
+            .iter()
+            .try_for_each(|frame| encode_frame(frame, writer))
+    }
+
+    /// Returns the version chosen by the handshake of the capture, if it is supported.
+    pub fn protocol_version(&self) -> Option<ProtocolVersion> {
+        let frame: &CapturedFrame = self.frames.iter().find(|frame| {
+            frame.direction == Direction::Serverbound && frame.state == State::Handshaking
+        })?;
+
+        // The handshake is the same in every version
+        let mut reader: &[u8] = &frame.data;
+        let id: u32 = u32::var_decode(&mut reader).ok()?;
+        match ProtocolVersion::LATEST.decode_serverbound(State::Handshaking, id, &mut reader) {
+            Ok(neutral::Serverbound::Handshake {
+                protocol_version, ..
+            }) => ProtocolVersion::from_protocol_version(protocol_version),
+            _ => None,
+        }
+    }
+
+    /// Formats every frame as a pretty-printed packet, in the version chosen by the handshake.
+    /// Frames that can't be decoded show the error and the raw bytes instead.
+    pub fn render(&self) -> String {
+        let version: ProtocolVersion = self.protocol_version().unwrap_or(ProtocolVersion::LATEST);
+
+        let mut out = String::new();
+        for frame in &self.frames {
+            writeln!(
+                out,
+                "[{:.3}s] {:?} {:?}",
+                frame.time.as_secs_f64(),
+                frame.direction,
+                frame.state
+            )
+            .unwrap();
+            match version.debug_packet(frame.direction, frame.state, &frame.data) {
+                Ok(packet) => writeln!(out, "{packet}").unwrap(),
+                Err(e) => writeln!(out, "error: {e}\n{:02X?}", frame.data).unwrap(),
+            }
+        }
+        out
+    }
+}
+
+/// Writes a capture as frames are sent.
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+    start: Instant,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Starts a capture, writing [`MAGIC`].
+    pub fn new(mut writer: W) -> io::Result<CaptureWriter<W>> {
+        writer.write_all(MAGIC)?;
+        Ok(CaptureWriter {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn write_frame(
+        &mut self,
+        direction: Direction,
+        state: State,
+        data: &[u8],
+    ) -> io::Result<()> {
+        encode_frame(
+            &CapturedFrame {
+                time: self.start.elapsed(),
+                direction,
+                state,
+                data: data.to_vec(),
+            },
+            &mut self.writer,
+        )
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn decode_frame(reader: &mut impl Read) -> Result<CapturedFrame, DecodeError> {
+    let time = Duration::from_micros(reader.read_u64::<BE>()?);
+    let direction: Direction = match reader.read_u8()? {
+        0 => Direction::Serverbound,
+        1 => Direction::Clientbound,
+        value => return Err(DecodeError::InvalidEnumVariant(value.into())),
+    };
+    let state: State = reader.read_u8()?.try_into()?;
+    let length: usize = usize::var_decode(reader)?;
+    let data: Vec<u8> = read_bytes(reader, length)?;
+
+    Ok(CapturedFrame {
+        time,
+        direction,
+        state,
+        data,
+    })
+}
+
+fn encode_frame(frame: &CapturedFrame, writer: &mut impl Write) -> io::Result<()> {
+    writer.write_u64::<BE>(frame.time.as_micros().try_into().unwrap_or(u64::MAX))?;
+    writer.write_u8(match frame.direction {
+        Direction::Serverbound => 0,
+        Direction::Clientbound => 1,
+    })?;
+    writer.write_u8(frame.state as u8)?;
+    frame.data.len().var_encode(writer)?;
+    writer.write_all(&frame.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_capture() -> Capture {
+        let frame = |millis: u64, direction: Direction, state: State, data: &[u8]| CapturedFrame {
+            time: Duration::from_millis(millis),
+            direction,
+            state,
+            data: data.to_vec(),
+        };
+
+        #[rustfmt::skip]
+        let frames = vec![
+            frame(0, Direction::Serverbound, State::Handshaking, &[
+                0x00, 0x04,
+                0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+                0x63, 0xDD,
+                0x01,
+            ]),
+            frame(1, Direction::Serverbound, State::Status, &[0x00]),
+            frame(5, Direction::Serverbound, State::Status, &[0x01, 0, 0, 0, 0, 0, 0, 0, 42]),
+            frame(6, Direction::Clientbound, State::Status, &[0x01, 0, 0, 0, 0, 0, 0, 0, 42]),
+            frame(7, Direction::Clientbound, State::Status, &[0x01, 0]),
+        ];
+        Capture { frames }
+    }
+
+    #[test]
+    fn test_read_write() {
+        let capture: Capture = status_capture();
+        let mut buf: Vec<u8> = Vec::new();
+        capture.write(&mut buf).unwrap();
+        assert_eq!(Capture::read(&mut &buf[..]).unwrap(), capture);
+
+        assert!(matches!(
+            Capture::read(&mut &b"USSRCAP\x02"[..]),
+            Err(CaptureError::InvalidMagic)
+        ));
+        assert!(matches!(
+            Capture::read(&mut &buf[..buf.len() - 1]),
+            Err(CaptureError::Frame { index: 4, .. })
+        ));
+
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer
+            .write_frame(Direction::Serverbound, State::Status, &[0x00])
+            .unwrap();
+        let capture: Capture = Capture::read(&mut &writer.into_inner()[..]).unwrap();
+        assert_eq!(capture.frames[0].data, [0x00]);
+    }
+
+    #[cfg(feature = "v1_7_2")]
+    #[test]
+    fn test_render() {
+        let capture: Capture = status_capture();
+        assert_eq!(capture.protocol_version(), Some(ProtocolVersion::V1_7_2));
+
+        let rendered: String = capture.render();
+        assert!(rendered.starts_with("[0.000s] Serverbound Handshaking\nHandshake(\n    Handshake {\n        protocol_version: 4,"));
+        assert!(rendered.contains(
+            "[0.006s] Clientbound Status\nPingResponse(\n    PingResponse {\n        payload: 42,"
+        ));
+        assert!(rendered.ends_with(
+            "error: PingResponse.payload at offset 1: failed to fill whole buffer\n[01, 00]\n"
+        ));
+    }
+}
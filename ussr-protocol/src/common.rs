@@ -0,0 +1,353 @@
+//! Types that are encoded the same way by every version that uses them.
+
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use ussr_buf::{Decode, DecodeError, Encode};
+use ussr_nbt::owned::Nbt;
+use uuid::Uuid;
+
+/// A block position encoded as three `i32`s.
+/// This is the position format used by 1.7, and by entity metadata in 1.8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Decode, Encode)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub const fn new(x: i32, y: i32, z: i32) -> Position {
+        Position { x, y, z }
+    }
+}
+
+impl From<PackedPosition> for Position {
+    fn from(position: PackedPosition) -> Self {
+        Position::new(position.x, position.y, position.z)
+    }
+}
+
+/// A block position packed into a single `u64`.
+/// `x` and `z` are 26 bits wide and `y` is 12 bits wide.
+/// This is the position format used by 1.8 and later.
+///
+/// Encoding truncates every coordinate to its width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PackedPosition {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl PackedPosition {
+    pub const fn new(x: i32, y: i32, z: i32) -> PackedPosition {
+        PackedPosition { x, y, z }
+    }
+
+    pub const fn to_u64(self) -> u64 {
+        (self.x as u64 & 0x3FFFFFF) << 38
+            | (self.y as u64 & 0xFFF) << 26
+            | self.z as u64 & 0x3FFFFFF
+    }
+
+    pub const fn from_u64(value: u64) -> PackedPosition {
+        let value: i64 = value as i64;
+        PackedPosition {
+            x: (value >> 38) as i32,
+            y: (value << 26 >> 52) as i32,
+            z: (value << 38 >> 38) as i32,
+        }
+    }
+}
+
+impl From<Position> for PackedPosition {
+    fn from(position: Position) -> Self {
+        PackedPosition::new(position.x, position.y, position.z)
+    }
+}
+
+impl Decode for PackedPosition {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(PackedPosition::from_u64(reader.read_u64::<BE>()?))
+    }
+}
+
+impl Encode for PackedPosition {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_u64::<BE>(self.to_u64())
+    }
+}
+
+/// A rotation angle in steps of 1/256 of a full turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Angle(pub u8);
+
+impl Angle {
+    pub fn from_degrees(degrees: f32) -> Angle {
+        Angle((degrees.rem_euclid(360.0) * 256.0 / 360.0) as i32 as u8)
+    }
+
+    pub fn to_degrees(self) -> f32 {
+        self.0 as f32 * 360.0 / 256.0
+    }
+}
+
+impl Decode for Angle {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(Angle(reader.read_u8()?))
+    }
+}
+
+impl Encode for Angle {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_u8(self.0)
+    }
+}
+
+/// A fixed-point number stored in an `i32` with 5 fractional bits.
+/// Used for absolute entity coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FixedPoint(pub i32);
+
+impl FixedPoint {
+    pub fn from_f64(value: f64) -> FixedPoint {
+        FixedPoint((value * 32.0).floor() as i32)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 32.0
+    }
+}
+
+impl Decode for FixedPoint {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(FixedPoint(reader.read_i32::<BE>()?))
+    }
+}
+
+impl Encode for FixedPoint {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_i32::<BE>(self.0)
+    }
+}
+
+/// A fixed-point number stored in an `i8` with 5 fractional bits.
+/// Used for relative entity movement, so it covers a range of -4 to just under 4 blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ByteFixedPoint(pub i8);
+
+impl ByteFixedPoint {
+    pub fn from_f64(value: f64) -> ByteFixedPoint {
+        ByteFixedPoint((value * 32.0).floor() as i8)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 32.0
+    }
+}
+
+impl Decode for ByteFixedPoint {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(ByteFixedPoint(reader.read_i8()?))
+    }
+}
+
+impl Encode for ByteFixedPoint {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_i8(self.0)
+    }
+}
+
+/// An item stack in an inventory slot, the encoding of the slot depends on the version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemStack {
+    pub id: i16,
+    pub count: u8,
+    pub damage: i16,
+    pub nbt: Option<Nbt>,
+}
+
+/// The values of [`EntityMetadata`], whose types depend on the version.
+pub trait MetadataValueCodec: Sized {
+    /// The type id of the value, which is stored in the upper 3 bits of the entry header.
+    fn type_id(&self) -> u8;
+
+    /// Decodes a value of the type with the given id.
+    fn decode_value(type_id: u8, reader: &mut impl Read) -> Result<Self, DecodeError>;
+
+    /// Encodes the value without its header.
+    fn encode_value(&self, writer: &mut impl Write) -> io::Result<()>;
+}
+
+/// Entity metadata, a list of indexed and typed values.
+///
+/// Each entry starts with a header byte, where the upper 3 bits are the type and the lower 5 bits are the index.
+/// The list is terminated with `0x7F`.
+///
+/// Entries are kept sorted by index, and there is at most one entry per index.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntityMetadata<V> {
+    entries: Vec<(u8, V)>,
+}
+
+impl<V> EntityMetadata<V> {
+    /// The byte that terminates the metadata.
+    pub const TERMINATOR: u8 = 0x7F;
+
+    /// The maximum index of an entry.
+    pub const MAX_INDEX: u8 = 0x1F;
+
+    pub const fn new() -> EntityMetadata<V> {
+        EntityMetadata {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Inserts a value, replacing the previous value at the same index.
+    ///
+    /// # Panics
+    /// Panics if `index` is greater than [`EntityMetadata::MAX_INDEX`].
+    pub fn insert(&mut self, index: u8, value: V) {
+        assert!(
+            index <= Self::MAX_INDEX,
+            "metadata index {index} is out of range"
+        );
+
+        match self.entries.binary_search_by_key(&index, |(i, _)| *i) {
+            Ok(i) => self.entries[i].1 = value,
+            Err(i) => self.entries.insert(i, (index, value)),
+        }
+    }
+
+    pub fn get(&self, index: u8) -> Option<&V> {
+        self.entries
+            .binary_search_by_key(&index, |(i, _)| *i)
+            .ok()
+            .map(|i| &self.entries[i].1)
+    }
+
+    pub fn remove(&mut self, index: u8) -> Option<V> {
+        self.entries
+            .binary_search_by_key(&index, |(i, _)| *i)
+            .ok()
+            .map(|i| self.entries.remove(i).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &V)> {
+        self.entries.iter().map(|(index, value)| (*index, value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn with(mut self, index: u8, value: V) -> EntityMetadata<V> {
+        self.insert(index, value);
+        self
+    }
+}
+
+impl<V> Default for EntityMetadata<V> {
+    fn default() -> Self {
+        EntityMetadata::new()
+    }
+}
+
+impl<V: MetadataValueCodec> Decode for EntityMetadata<V> {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let mut metadata: EntityMetadata<V> = EntityMetadata::new();
+
+        loop {
+            let header: u8 = reader.read_u8()?;
+            if header == Self::TERMINATOR {
+                return Ok(metadata);
+            }
+
+            let value: V = V::decode_value(header >> 5, reader)?;
+            metadata.insert(header & Self::MAX_INDEX, value);
+        }
+    }
+}
+
+impl<V: MetadataValueCodec> Encode for EntityMetadata<V> {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        for (index, value) in self.iter() {
+            writer.write_u8(value.type_id() << 5 | index)?;
+            value.encode_value(writer)?;
+        }
+
+        writer.write_u8(Self::TERMINATOR)
+    }
+}
+
+/// An attribute modifier of an entity property.
+#[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+pub struct AttributeModifier {
+    pub uuid: Uuid,
+    pub amount: f64,
+    /// 0 adds `amount`, 1 adds `amount` percent, 2 multiplies by `1 + amount`.
+    pub operation: i8,
+}
+
+/// A destroyed block in an explosion, relative to the center of the explosion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Decode, Encode)]
+pub struct ExplosionRecord {
+    pub x: i8,
+    pub y: i8,
+    pub z: i8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+pub struct Statistic {
+    pub name: String,
+    #[var]
+    pub value: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::round_trip;
+
+    #[test]
+    fn test_position() {
+        assert_eq!(
+            round_trip(Position::new(-1, 64, 300)),
+            [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 64, 0, 0, 0x01, 0x2C]
+        );
+    }
+
+    #[test]
+    fn test_packed_position() {
+        let position = PackedPosition::new(-1, 64, 300);
+        assert_eq!(
+            round_trip(position),
+            (0x3FFFFFFu64 << 38 | 64 << 26 | 300).to_be_bytes()
+        );
+        round_trip(PackedPosition::new(-33554432, -2048, 33554431));
+        assert_eq!(Position::from(position), Position::new(-1, 64, 300));
+    }
+
+    #[test]
+    fn test_angle() {
+        assert_eq!(round_trip(Angle::from_degrees(90.0)), [64]);
+        assert_eq!(Angle::from_degrees(-90.0), Angle(192));
+        assert_eq!(Angle(128).to_degrees(), 180.0);
+    }
+
+    #[test]
+    fn test_fixed_point() {
+        assert_eq!(
+            round_trip(FixedPoint::from_f64(-1.5)),
+            (-48i32).to_be_bytes()
+        );
+        assert_eq!(FixedPoint(16).to_f64(), 0.5);
+        assert_eq!(round_trip(ByteFixedPoint::from_f64(2.0)), [64]);
+        assert_eq!(ByteFixedPoint(-8).to_f64(), -0.25);
+    }
+}
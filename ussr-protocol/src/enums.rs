@@ -1,10 +1,6 @@
 use std::io::{self, Read, Write};
 
 use byteorder::{ReadBytesExt, WriteBytesExt};
-#[cfg(feature = "async")]
-use futures_lite::{AsyncReadExt, AsyncWriteExt};
-#[cfg(feature = "async")]
-use ussr_buf::{AsyncDecode, AsyncEncode, AsyncEncodeExt};
 use ussr_buf::{Decode, DecodeError, Encode};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -15,6 +11,20 @@ pub enum State {
     Play = 3,
 }
 
+impl TryFrom<u8> for State {
+    type Error = DecodeError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(State::Handshaking),
+            1 => Ok(State::Status),
+            2 => Ok(State::Login),
+            3 => Ok(State::Play),
+            _ => Err(DecodeError::InvalidEnumVariant(value.into())),
+        }
+    }
+}
+
 impl From<NextState> for State {
     fn from(next_state: NextState) -> Self {
         match next_state {
@@ -37,7 +47,7 @@ impl TryFrom<u8> for NextState {
         match value {
             1 => Ok(NextState::Status),
             2 => Ok(NextState::Login),
-            _ => Err(DecodeError::InvalidEnumVariant),
+            _ => Err(DecodeError::InvalidEnumVariant(value.into())),
         }
     }
 }
@@ -54,20 +64,6 @@ impl Encode for NextState {
     }
 }
 
-#[cfg(feature = "async")]
-impl AsyncDecode for NextState {
-    async fn decode(reader: &mut (impl AsyncReadExt + Unpin + Send)) -> Result<Self, DecodeError> {
-        <u8 as AsyncDecode>::decode(reader).await?.try_into()
-    }
-}
-
-#[cfg(feature = "async")]
-impl AsyncEncode for NextState {
-    async fn encode(&self, writer: &mut (impl AsyncWriteExt + Unpin + Send)) -> io::Result<()> {
-        writer.encode(*self as u8).await
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Gamemode {
     Survival = 0,
@@ -83,7 +79,7 @@ impl TryFrom<u8> for Gamemode {
             0 => Ok(Gamemode::Survival),
             1 => Ok(Gamemode::Creative),
             2 => Ok(Gamemode::Adventure),
-            _ => Err(DecodeError::InvalidEnumVariant),
+            _ => Err(DecodeError::InvalidEnumVariant(value.into())),
         }
     }
 }
@@ -100,20 +96,6 @@ impl Encode for Gamemode {
     }
 }
 
-#[cfg(feature = "async")]
-impl AsyncDecode for Gamemode {
-    async fn decode(reader: &mut (impl AsyncReadExt + Unpin + Send)) -> Result<Self, DecodeError> {
-        <u8 as AsyncDecode>::decode(reader).await?.try_into()
-    }
-}
-
-#[cfg(feature = "async")]
-impl AsyncEncode for Gamemode {
-    async fn encode(&self, writer: &mut (impl AsyncWriteExt + Unpin + Send)) -> io::Result<()> {
-        writer.encode(*self as u8).await
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Dimension {
     Nether = -1,
@@ -129,7 +111,7 @@ impl TryFrom<i8> for Dimension {
             -1 => Ok(Dimension::Nether),
             0 => Ok(Dimension::Overworld),
             1 => Ok(Dimension::End),
-            _ => Err(DecodeError::InvalidEnumVariant),
+            _ => Err(DecodeError::InvalidEnumVariant(value.into())),
         }
     }
 }
@@ -146,20 +128,6 @@ impl Encode for Dimension {
     }
 }
 
-#[cfg(feature = "async")]
-impl AsyncDecode for Dimension {
-    async fn decode(reader: &mut (impl AsyncReadExt + Unpin + Send)) -> Result<Self, DecodeError> {
-        <i8 as AsyncDecode>::decode(reader).await?.try_into()
-    }
-}
-
-#[cfg(feature = "async")]
-impl AsyncEncode for Dimension {
-    async fn encode(&self, writer: &mut (impl AsyncWriteExt + Unpin + Send)) -> io::Result<()> {
-        writer.encode(*self as i8).await
-    }
-}
-
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Difficulty {
     Peaceful = 0,
@@ -177,7 +145,7 @@ impl TryFrom<u8> for Difficulty {
             1 => Ok(Difficulty::Easy),
             2 => Ok(Difficulty::Normal),
             3 => Ok(Difficulty::Hard),
-            _ => Err(DecodeError::InvalidEnumVariant),
+            _ => Err(DecodeError::InvalidEnumVariant(value.into())),
         }
     }
 }
@@ -193,17 +161,3 @@ impl Encode for Difficulty {
         writer.write_u8(*self as u8)
     }
 }
-
-#[cfg(feature = "async")]
-impl AsyncDecode for Difficulty {
-    async fn decode(reader: &mut (impl AsyncReadExt + Unpin + Send)) -> Result<Self, DecodeError> {
-        <u8 as AsyncDecode>::decode(reader).await?.try_into()
-    }
-}
-
-#[cfg(feature = "async")]
-impl AsyncEncode for Difficulty {
-    async fn encode(&self, writer: &mut (impl AsyncWriteExt + Unpin + Send)) -> io::Result<()> {
-        writer.encode(*self as u8).await
-    }
-}
@@ -0,0 +1,15 @@
+//! Packet fields that are JSON strings.
+
+use std::io::{self, Read, Write};
+
+use serde::{de::DeserializeOwned, Serialize};
+use ussr_buf::{decode_string, DecodeError, Encode, MAX_STRING_LENGTH};
+
+pub(crate) fn decode_json<T: DeserializeOwned>(reader: &mut impl Read) -> Result<T, DecodeError> {
+    let json: String = decode_string(reader, MAX_STRING_LENGTH)?;
+    serde_json::from_str(&json).map_err(|e| DecodeError::InvalidJson(e.to_string()))
+}
+
+pub(crate) fn encode_json(value: &impl Serialize, writer: &mut impl Write) -> io::Result<()> {
+    serde_json::to_string(value)?.encode(writer)
+}
@@ -0,0 +1,202 @@
+//! The server list ping of clients before 1.7.
+//!
+//! These clients start the connection with [`LEGACY_PING_ID`] instead of a frame,
+//! and expect a kick packet with the status as a UTF-16 string, after which the connection is closed.
+//! A modern frame starts with `0xFE` too if its length is 254, 382, 510…, so like vanilla,
+//! data that doesn't decode as a legacy ping has to be processed as frames instead.
+
+use std::io::{self, Cursor, ErrorKind, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use ussr_buf::{read_bytes, DecodeError};
+
+use crate::status::ServerStatus;
+
+pub const LEGACY_PING_ID: u8 = 0xFE;
+const PLUGIN_MESSAGE_ID: u8 = 0xFA;
+const KICK_ID: u8 = 0xFF;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LegacyPing {
+    /// Beta 1.8 to 1.3 only send `0xFE`.
+    V1_3,
+    /// 1.4 and 1.5 send `0xFE 0x01`.
+    V1_4,
+    /// 1.6 sends `0xFE 0x01` followed by a `MC|PingHost` plugin message.
+    V1_6 {
+        protocol_version: u8,
+        host: String,
+        port: i32,
+    },
+}
+
+impl LegacyPing {
+    /// Decodes a legacy ping from the first bytes of a connection, which start with [`LEGACY_PING_ID`].
+    /// Like vanilla, the kind of ping depends on how many bytes have been received.
+    ///
+    /// Returns the ping and its length, or `None` if the plugin message of 1.6 is incomplete.
+    pub fn decode(buf: &[u8]) -> Result<Option<(LegacyPing, usize)>, DecodeError> {
+        let mut reader: Cursor<&[u8]> = Cursor::new(buf);
+        let expect = |reader: &mut Cursor<&[u8]>, expected: u8| match reader.read_u8()? {
+            byte if byte == expected => Ok(()),
+            byte => Err(DecodeError::InvalidEnumVariant(byte.into())),
+        };
+
+        expect(&mut reader, LEGACY_PING_ID)?;
+        if reader.position() as usize == buf.len() {
+            return Ok(Some((LegacyPing::V1_3, 1)));
+        }
+        expect(&mut reader, 0x01)?;
+        if reader.position() as usize == buf.len() {
+            return Ok(Some((LegacyPing::V1_4, 2)));
+        }
+        expect(&mut reader, PLUGIN_MESSAGE_ID)?;
+
+        match decode_ping_host(&mut reader) {
+            Ok(ping) => Ok(Some((ping, reader.position() as usize))),
+            Err(DecodeError::Io(e)) if e.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Encodes the kick packet with the status for this kind of ping.
+    pub fn encode_response(
+        &self,
+        status: &ServerStatus,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        let response: String = match self {
+            // `§` separates the fields
+            LegacyPing::V1_3 => format!(
+                "{}§{}§{}",
+                status.description.to_string().replace('§', ""),
+                status.players.online,
+                status.players.max,
+            ),
+            LegacyPing::V1_4 | LegacyPing::V1_6 { .. } => format!(
+                "§1\0{}\0{}\0{}\0{}\0{}",
+                status.version.protocol,
+                status.version.name,
+                status.description.to_legacy(),
+                status.players.online,
+                status.players.max,
+            ),
+        };
+
+        writer.write_u8(KICK_ID)?;
+        encode_utf16(&response, writer)
+    }
+}
+
+/// Decodes the `MC|PingHost` plugin message sent by 1.6, without the packet ID.
+fn decode_ping_host(reader: &mut impl Read) -> Result<LegacyPing, DecodeError> {
+    // The channel is always `MC|PingHost`
+    decode_utf16(reader)?;
+    let length: usize = reader.read_u16::<BE>()?.into();
+    let data: Vec<u8> = read_bytes(reader, length)?;
+
+    // The data is complete, so running out of it means that its length is wrong
+    decode_ping_host_data(&mut &data[..]).map_err(|e| match e {
+        DecodeError::Io(_) => DecodeError::InvalidArrayLength,
+        e => e,
+    })
+}
+
+fn decode_ping_host_data(data: &mut &[u8]) -> Result<LegacyPing, DecodeError> {
+    let ping = LegacyPing::V1_6 {
+        protocol_version: data.read_u8()?,
+        host: decode_utf16(data)?,
+        port: data.read_i32::<BE>()?,
+    };
+    if !data.is_empty() {
+        return Err(DecodeError::TrailingBytes(data.len()));
+    }
+    Ok(ping)
+}
+
+/// Decodes a string prefixed with its length in UTF-16 code units.
+fn decode_utf16(reader: &mut impl Read) -> Result<String, DecodeError> {
+    let length: u16 = reader.read_u16::<BE>()?;
+    let units: Vec<u16> = (0..length)
+        .map(|_| reader.read_u16::<BE>())
+        .collect::<io::Result<_>>()?;
+    String::from_utf16(&units).map_err(|_| DecodeError::InvalidUtf8)
+}
+
+fn encode_utf16(string: &str, writer: &mut impl Write) -> io::Result<()> {
+    let units: Vec<u16> = string.encode_utf16().collect();
+    let length: u16 = units
+        .len()
+        .try_into()
+        .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "string is too long"))?;
+    writer.write_u16::<BE>(length)?;
+    units
+        .iter()
+        .try_for_each(|&unit| writer.write_u16::<BE>(unit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextComponent;
+
+    fn ping_host(host: &str, port: i32) -> Vec<u8> {
+        let mut data: Vec<u8> = vec![78];
+        encode_utf16(host, &mut data).unwrap();
+        data.write_i32::<BE>(port).unwrap();
+
+        let mut buf: Vec<u8> = vec![LEGACY_PING_ID, 0x01, PLUGIN_MESSAGE_ID];
+        encode_utf16("MC|PingHost", &mut buf).unwrap();
+        buf.write_u16::<BE>(data.len() as u16).unwrap();
+        buf.extend_from_slice(&data);
+        buf
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(
+            LegacyPing::decode(&[0xFE]).unwrap(),
+            Some((LegacyPing::V1_3, 1))
+        );
+        assert_eq!(
+            LegacyPing::decode(&[0xFE, 0x01]).unwrap(),
+            Some((LegacyPing::V1_4, 2))
+        );
+
+        let buf: Vec<u8> = ping_host("localhost", 25565);
+        assert_eq!(
+            LegacyPing::decode(&buf).unwrap(),
+            Some((
+                LegacyPing::V1_6 {
+                    protocol_version: 78,
+                    host: "localhost".to_owned(),
+                    port: 25565,
+                },
+                buf.len()
+            ))
+        );
+        assert_eq!(LegacyPing::decode(&buf[..buf.len() - 1]).unwrap(), None);
+        assert!(LegacyPing::decode(&[0xFE, 0x02]).is_err());
+    }
+
+    #[test]
+    fn test_response() {
+        let status = ServerStatus {
+            description: TextComponent::text("Hi").bold(true),
+            ..ServerStatus::default()
+        };
+        let expected: String = format!(
+            "§1\x00{}\x00{}\x00§lHi\x000\x0020",
+            status.version.protocol, status.version.name
+        );
+
+        let mut buf: Vec<u8> = Vec::new();
+        LegacyPing::V1_4.encode_response(&status, &mut buf).unwrap();
+        assert_eq!(buf[0], KICK_ID);
+        assert_eq!(decode_utf16(&mut &buf[1..]).unwrap(), expected);
+
+        let mut buf: Vec<u8> = Vec::new();
+        LegacyPing::V1_3.encode_response(&status, &mut buf).unwrap();
+        assert_eq!(decode_utf16(&mut &buf[1..]).unwrap(), "Hi§0§20");
+    }
+}
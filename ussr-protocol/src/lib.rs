@@ -1,12 +1,30 @@
+pub mod capture;
+pub mod common;
+pub mod enums;
+mod json;
+pub mod legacy_ping;
+pub mod neutral;
+pub mod status;
+#[cfg(test)]
+mod test_utils;
+pub mod text;
+mod transition;
 mod versions;
 
-use std::io;
+use std::io::{self, Write};
 
 use thiserror::Error;
-use ussr_buf::DecodeError;
+use ussr_buf::{Decode, DecodeError, Encode, VarEncode};
 
-pub use enums::State;
-pub use versions::*;
+pub use enums::{NextState, State};
+pub use status::ServerStatus;
+pub use text::TextComponent;
+pub use transition::UnexpectedPacket;
+#[cfg(feature = "v1_7_2")]
+pub use versions::v1_7_2;
+#[cfg(feature = "v1_8_9")]
+pub use versions::v1_8_9;
+pub use versions::ProtocolVersion;
 
 //? A possible future optimization is to make packets immutable (e.g. use `Box<[T]>` instead of `Vec<T>`)
 
@@ -16,23 +34,93 @@ pub enum Direction {
     Clientbound,
 }
 
+/// A packet of a specific protocol version, implemented by `#[packets]`.
+///
+/// The direction is also a type, so that functions can only accept packets going one way:
+///
+/// ```compile_fail
+/// use ussr_protocol::{v1_8_9::packets::handshaking::serverbound::Handshake, Clientbound, Packet};
+///
+/// fn send<P: Packet<Direction = Clientbound>>(packet: &P) {}
+///
+/// # let handshake: Handshake = unimplemented!();
+/// send(&handshake);
+/// ```
+pub trait Packet: Decode + Encode {
+    const ID: u32;
+    const STATE: State;
+    const DIRECTION: Direction = <Self::Direction as PacketDirection>::DIRECTION;
+    const NAME: &'static str;
+
+    /// Either [`Clientbound`] or [`Serverbound`].
+    type Direction: PacketDirection;
+
+    /// Encodes the ID of the packet followed by its body.
+    fn encode_with_id(&self, writer: &mut impl Write) -> io::Result<()> {
+        Self::ID.var_encode(writer)?;
+        self.encode(writer)
+    }
+}
+
+/// The direction of a packet as a type, see [`Packet::Direction`].
+pub trait PacketDirection: private::Sealed {
+    const DIRECTION: Direction;
+}
+
+/// Packets sent by the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clientbound {}
+
+/// Packets sent by the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Serverbound {}
+
+impl PacketDirection for Clientbound {
+    const DIRECTION: Direction = Direction::Clientbound;
+}
+
+impl PacketDirection for Serverbound {
+    const DIRECTION: Direction = Direction::Serverbound;
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::Clientbound {}
+    impl Sealed for super::Serverbound {}
+}
+
 #[derive(Debug, Error)]
 pub enum PacketDecodeError {
     #[error(transparent)]
     Io(#[from] io::Error),
 
-    #[error("Unknown packet id {packet_id} in state {state:?}")]
+    #[error("unknown packet id {packet_id:#04x} in state {state:?}")]
     UnknownPacketId { packet_id: u32, state: State },
 
-    #[error("Couldn't decode packet: {0}")]
-    Decode(DecodeError),
+    #[error("unsupported protocol version {0}")]
+    UnsupportedProtocolVersion(i32),
+
+    #[error(transparent)]
+    UnexpectedPacket(#[from] UnexpectedPacket),
+
+    /// `offset` is where the field that failed starts within the frame.
+    /// If the field is not known, it is where decoding stopped instead.
+    #[error(
+        "{}at offset {offset}: {}",
+        .source.path().map(|path| path + " ").unwrap_or_default(),
+        .source.cause()
+    )]
+    Decode { offset: usize, source: DecodeError },
 }
 
-impl From<DecodeError> for PacketDecodeError {
-    fn from(value: DecodeError) -> Self {
-        match value {
-            DecodeError::Io(e) => PacketDecodeError::Io(e),
-            e => PacketDecodeError::Decode(e),
+impl PacketDecodeError {
+    /// Creates a [`PacketDecodeError::Decode`] for a packet body that starts at `body_offset` within the frame.
+    /// `consumed` is the number of bytes of the body that were read before the error.
+    pub fn decode(source: DecodeError, body_offset: usize, consumed: usize) -> PacketDecodeError {
+        PacketDecodeError::Decode {
+            offset: body_offset + source.offset().unwrap_or(consumed),
+            source,
         }
     }
 }
@@ -0,0 +1,245 @@
+//! Packets that don't depend on the protocol version.
+//!
+//! Every supported version converts the packets it receives into [`Serverbound`],
+//! and [`Clientbound`] into the packets it sends, see [`ProtocolVersion`](crate::ProtocolVersion).
+//! Play packets that only exist in some versions, or that aren't needed by the server yet, are not part of this model,
+//! they are carried as the packet of their version by [`Serverbound::Other`] and [`Clientbound::Other`].
+
+use uuid::Uuid;
+
+#[cfg(feature = "v1_7_2")]
+use crate::v1_7_2;
+#[cfg(feature = "v1_8_9")]
+use crate::v1_8_9;
+use crate::{
+    enums::{Difficulty, Dimension, Gamemode, NextState, State},
+    ServerStatus, TextComponent,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Serverbound {
+    // Handshaking
+    Handshake {
+        protocol_version: i32,
+        server_address: String,
+        server_port: u16,
+        next_state: NextState,
+    },
+
+    // Status
+    StatusRequest,
+    PingRequest {
+        payload: u64,
+    },
+
+    // Login
+    LoginStart {
+        username: String,
+    },
+    EncryptionResponse {
+        shared_secret: Vec<u8>,
+        verify_token: Vec<u8>,
+    },
+
+    // Play
+    KeepAlive {
+        id: i32,
+    },
+    ChatMessage {
+        message: String,
+    },
+    Player {
+        on_ground: bool,
+    },
+    PlayerPosition {
+        x: f64,
+        /// The position of the player's feet.
+        y: f64,
+        z: f64,
+        on_ground: bool,
+    },
+    PlayerLook {
+        yaw: f32,
+        pitch: f32,
+        on_ground: bool,
+    },
+    PlayerPositionAndLook {
+        x: f64,
+        /// The position of the player's feet.
+        y: f64,
+        z: f64,
+        yaw: f32,
+        pitch: f32,
+        on_ground: bool,
+    },
+    HeldItemChange {
+        slot: i16,
+    },
+    ClientSettings {
+        locale: String,
+        view_distance: i8,
+        /// 0 enabled, 1 commands only, 2 hidden.
+        chat_mode: i8,
+        chat_colours: bool,
+        show_cape: bool,
+    },
+    ClientStatus {
+        /// 0 respawn, 1 request stats, 2 open inventory achievement.
+        action_id: i32,
+    },
+    PluginMessage {
+        channel: String,
+        data: Vec<u8>,
+    },
+    /// A play packet that is not part of this model.
+    Other(OtherServerbound),
+}
+
+impl Serverbound {
+    /// Returns the state in which the packet is sent.
+    pub fn state(&self) -> State {
+        match self {
+            Serverbound::Handshake { .. } => State::Handshaking,
+            Serverbound::StatusRequest | Serverbound::PingRequest { .. } => State::Status,
+            Serverbound::LoginStart { .. } | Serverbound::EncryptionResponse { .. } => State::Login,
+            Serverbound::KeepAlive { .. }
+            | Serverbound::ChatMessage { .. }
+            | Serverbound::Player { .. }
+            | Serverbound::PlayerPosition { .. }
+            | Serverbound::PlayerLook { .. }
+            | Serverbound::PlayerPositionAndLook { .. }
+            | Serverbound::HeldItemChange { .. }
+            | Serverbound::ClientSettings { .. }
+            | Serverbound::ClientStatus { .. }
+            | Serverbound::PluginMessage { .. }
+            | Serverbound::Other(_) => State::Play,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Clientbound {
+    // Status
+    StatusResponse {
+        response: ServerStatus,
+    },
+    PingResponse {
+        payload: u64,
+    },
+
+    // Login
+    LoginDisconnect {
+        reason: TextComponent,
+    },
+    EncryptionRequest {
+        server_id: String,
+        public_key: Vec<u8>,
+        verify_token: Vec<u8>,
+    },
+    LoginSuccess {
+        /// Sent without dashes to 1.7.2, and with dashes to later versions.
+        uuid: Uuid,
+        username: String,
+    },
+    /// Only exists since 1.8, see [`ProtocolVersion::supports_compression`](crate::ProtocolVersion::supports_compression).
+    SetCompression {
+        /// Packets of this size or larger are compressed, -1 disables compression.
+        threshold: i32,
+    },
+
+    // Play
+    KeepAlive {
+        id: i32,
+    },
+    JoinGame {
+        entity_id: i32,
+        hardcore: bool,
+        gamemode: Gamemode,
+        dimension: Dimension,
+        difficulty: Difficulty,
+        max_players: u8,
+        level_type: String,
+        /// Ignored by versions before 1.8.
+        reduced_debug_info: bool,
+    },
+    ChatMessage {
+        message: TextComponent,
+        /// 0 chat box, 1 system message, 2 above the hotbar.
+        /// Versions before 1.8 show every message in the chat box.
+        position: i8,
+    },
+    TimeUpdate {
+        world_age: i64,
+        time_of_day: i64,
+    },
+    SpawnPosition {
+        x: i32,
+        y: i32,
+        z: i32,
+    },
+    PlayerPositionAndLook {
+        x: f64,
+        /// The position of the player's feet.
+        y: f64,
+        z: f64,
+        yaw: f32,
+        pitch: f32,
+    },
+    HeldItemChange {
+        slot: i8,
+    },
+    PluginMessage {
+        channel: String,
+        data: Vec<u8>,
+    },
+    Disconnect {
+        reason: TextComponent,
+    },
+    /// A play packet that is not part of this model.
+    /// It can only be sent to connections of the same version.
+    Other(OtherClientbound),
+}
+
+impl Clientbound {
+    /// Returns the state in which the packet is sent.
+    pub fn state(&self) -> State {
+        match self {
+            Clientbound::StatusResponse { .. } | Clientbound::PingResponse { .. } => State::Status,
+            Clientbound::LoginDisconnect { .. }
+            | Clientbound::EncryptionRequest { .. }
+            | Clientbound::LoginSuccess { .. }
+            | Clientbound::SetCompression { .. } => State::Login,
+            Clientbound::KeepAlive { .. }
+            | Clientbound::JoinGame { .. }
+            | Clientbound::ChatMessage { .. }
+            | Clientbound::TimeUpdate { .. }
+            | Clientbound::SpawnPosition { .. }
+            | Clientbound::PlayerPositionAndLook { .. }
+            | Clientbound::HeldItemChange { .. }
+            | Clientbound::PluginMessage { .. }
+            | Clientbound::Disconnect { .. }
+            | Clientbound::Other(_) => State::Play,
+        }
+    }
+}
+
+/// A serverbound play packet of a specific version.
+/// The packets are boxed, since some of them are much larger than the packets of the neutral model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OtherServerbound {
+    /// A packet of 1.7.2 or 1.7.6, which share their packets.
+    #[cfg(feature = "v1_7_2")]
+    V1_7_2(Box<v1_7_2::packets::play::serverbound::PlayPackets>),
+    #[cfg(feature = "v1_8_9")]
+    V1_8_9(Box<v1_8_9::packets::play::serverbound::PlayPackets>),
+}
+
+/// A clientbound play packet of a specific version, boxed like [`OtherServerbound`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum OtherClientbound {
+    /// A packet of 1.7.2 or 1.7.6, which share their packets.
+    #[cfg(feature = "v1_7_2")]
+    V1_7_2(Box<v1_7_2::packets::play::clientbound::PlayPackets>),
+    #[cfg(feature = "v1_8_9")]
+    V1_8_9(Box<v1_8_9::packets::play::clientbound::PlayPackets>),
+}
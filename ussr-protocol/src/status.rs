@@ -0,0 +1,145 @@
+//! The response to a server list ping.
+
+use std::io::{self, Read, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use ussr_buf::{Decode, DecodeError, Encode};
+use uuid::Uuid;
+
+use crate::{
+    json::{decode_json, encode_json},
+    ProtocolVersion, TextComponent,
+};
+
+/// The server list entry of a server, sent as JSON in the status response.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerStatus {
+    pub version: StatusVersion,
+    pub players: StatusPlayers,
+    pub description: TextComponent,
+    /// A 64x64 PNG as a data URI, see [`ServerStatus::set_favicon_png`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusVersion {
+    pub name: String,
+    /// The client shows the server as incompatible if this is not its protocol version.
+    pub protocol: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusPlayers {
+    pub max: i32,
+    pub online: i32,
+    /// Shown when hovering over the player count.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample: Vec<PlayerSample>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerSample {
+    pub name: String,
+    pub id: Uuid,
+}
+
+impl ServerStatus {
+    const FAVICON_PREFIX: &'static str = "data:image/png;base64,";
+
+    /// Sets the favicon to a base64 data URI of `png`.
+    pub fn set_favicon_png(&mut self, png: &[u8]) {
+        self.favicon = Some(format!("{}{}", Self::FAVICON_PREFIX, STANDARD.encode(png)));
+    }
+
+    /// Returns the PNG in the favicon, if there is a valid one.
+    pub fn favicon_png(&self) -> Option<Vec<u8>> {
+        let base64: &str = self.favicon.as_ref()?.strip_prefix(Self::FAVICON_PREFIX)?;
+        STANDARD.decode(base64).ok()
+    }
+}
+
+impl Default for ServerStatus {
+    fn default() -> Self {
+        ServerStatus {
+            version: StatusVersion {
+                name: ProtocolVersion::LATEST.name().to_owned(),
+                protocol: ProtocolVersion::LATEST.protocol_version(),
+            },
+            players: StatusPlayers {
+                max: 20,
+                online: 0,
+                sample: Vec::new(),
+            },
+            description: TextComponent::text("A Minecraft Server"),
+            favicon: None,
+        }
+    }
+}
+
+impl Decode for ServerStatus {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        decode_json(reader)
+    }
+}
+
+impl Encode for ServerStatus {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        encode_json(self, writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        let mut status = ServerStatus {
+            version: StatusVersion {
+                name: "1.7.2".to_owned(),
+                protocol: 4,
+            },
+            players: StatusPlayers {
+                max: 100,
+                online: 1,
+                sample: vec![PlayerSample {
+                    name: "Notch".to_owned(),
+                    id: Uuid::from_u128(0x069a79f4_44e9_4726_a5be_fca90e38aaf5),
+                }],
+            },
+            description: TextComponent::text("Hello"),
+            favicon: None,
+        };
+        assert_eq!(
+            serde_json::to_string(&status).unwrap(),
+            r#"{"version":{"name":"1.7.2","protocol":4},"players":{"max":100,"online":1,"sample":[{"name":"Notch","id":"069a79f4-44e9-4726-a5be-fca90e38aaf5"}]},"description":{"text":"Hello"}}"#
+        );
+
+        status.set_favicon_png(b"\x89PNG");
+        assert_eq!(
+            status.favicon.as_deref(),
+            Some("data:image/png;base64,iVBORw==")
+        );
+        assert_eq!(status.favicon_png().as_deref(), Some(&b"\x89PNG"[..]));
+
+        let mut buf: Vec<u8> = Vec::new();
+        status.encode(&mut buf).unwrap();
+        assert_eq!(ServerStatus::decode(&mut &buf[..]).unwrap(), status);
+    }
+
+    #[test]
+    fn test_vanilla_response() {
+        // Vanilla sends the description as a plain string
+        let status: ServerStatus = serde_json::from_str(
+            r#"{"description":"A Minecraft Server","players":{"max":20,"online":0},"version":{"name":"1.8.9","protocol":47}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            status.description,
+            TextComponent::text("A Minecraft Server")
+        );
+        assert!(status.players.sample.is_empty());
+    }
+}
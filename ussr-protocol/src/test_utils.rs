@@ -0,0 +1,32 @@
+//! Helpers shared by the tests of the types and packets.
+
+use std::fmt::Debug;
+
+use ussr_buf::{Decode, Encode, VarDecode};
+
+use crate::Packet;
+
+/// Encodes `value`, checks that it decodes back to itself, and returns the encoded bytes.
+pub(crate) fn round_trip<T: Decode + Encode + PartialEq + Debug>(value: T) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    value.encode(&mut buf).unwrap();
+
+    let mut reader: &[u8] = &buf;
+    assert_eq!(T::decode(&mut reader).unwrap(), value);
+    assert!(reader.is_empty());
+
+    buf
+}
+
+/// Like [`round_trip`], but the returned bytes start with the ID of the packet.
+pub(crate) fn round_trip_packet<P: Packet + PartialEq + Debug>(packet: P) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    packet.encode_with_id(&mut buf).unwrap();
+
+    let mut reader: &[u8] = &buf;
+    assert_eq!(u32::var_decode(&mut reader).unwrap(), P::ID);
+    assert_eq!(P::decode(&mut reader).unwrap(), packet);
+    assert!(reader.is_empty());
+
+    buf
+}
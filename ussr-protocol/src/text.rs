@@ -0,0 +1,646 @@
+//! JSON text components, used for chat messages and disconnect reasons.
+//!
+//! A [`TextComponent`] has some content, a [`Style`] and children in `extra`, which inherit the style.
+//! It can be converted to and from legacy strings with `§` formatting codes,
+//! and [`Display`](fmt::Display) writes it as plain text.
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use serde::{Deserialize, Serialize};
+use ussr_buf::{Decode, DecodeError, Encode};
+
+use crate::json::{decode_json, encode_json};
+
+/// The character that starts a legacy formatting code.
+pub const LEGACY_PREFIX: char = '§';
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "RawComponent")]
+pub struct TextComponent {
+    #[serde(flatten)]
+    pub content: Content,
+    #[serde(flatten)]
+    pub style: Style,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub extra: Vec<TextComponent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Content {
+    Text {
+        text: String,
+    },
+    Translate {
+        translate: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        with: Vec<TextComponent>,
+    },
+    Score {
+        score: Score,
+    },
+    Selector {
+        selector: String,
+    },
+}
+
+impl Default for Content {
+    fn default() -> Self {
+        Content::Text {
+            text: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Score {
+    pub name: String,
+    pub objective: String,
+    /// The resolved value, set by the server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// The style of a component. `None` inherits the style of the parent.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Style {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub underlined: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub obfuscated: Option<bool>,
+    /// Text inserted into the chat box when the component is shift-clicked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub insertion: Option<String>,
+    #[serde(rename = "clickEvent", skip_serializing_if = "Option::is_none")]
+    pub click_event: Option<ClickEvent>,
+    #[serde(rename = "hoverEvent", skip_serializing_if = "Option::is_none")]
+    pub hover_event: Option<HoverEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Black,
+    DarkBlue,
+    DarkGreen,
+    DarkAqua,
+    DarkRed,
+    DarkPurple,
+    Gold,
+    Gray,
+    DarkGray,
+    Blue,
+    Green,
+    Aqua,
+    Red,
+    LightPurple,
+    Yellow,
+    White,
+    Reset,
+}
+
+impl Color {
+    const COLORS: [Color; 16] = [
+        Color::Black,
+        Color::DarkBlue,
+        Color::DarkGreen,
+        Color::DarkAqua,
+        Color::DarkRed,
+        Color::DarkPurple,
+        Color::Gold,
+        Color::Gray,
+        Color::DarkGray,
+        Color::Blue,
+        Color::Green,
+        Color::Aqua,
+        Color::Red,
+        Color::LightPurple,
+        Color::Yellow,
+        Color::White,
+    ];
+
+    /// Returns the legacy formatting code of the color.
+    pub fn legacy_code(self) -> char {
+        match self {
+            Color::Reset => 'r',
+            color => char::from_digit(color as u32, 16).unwrap(),
+        }
+    }
+
+    /// Returns the color with the given legacy formatting code.
+    pub fn from_legacy_code(code: char) -> Option<Color> {
+        match code.to_ascii_lowercase() {
+            'r' => Some(Color::Reset),
+            code => code.to_digit(16).map(|digit| Color::COLORS[digit as usize]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClickEvent {
+    pub action: ClickAction,
+    pub value: String,
+}
+
+impl ClickEvent {
+    pub fn open_url(url: impl Into<String>) -> ClickEvent {
+        ClickEvent {
+            action: ClickAction::OpenUrl,
+            value: url.into(),
+        }
+    }
+
+    pub fn run_command(command: impl Into<String>) -> ClickEvent {
+        ClickEvent {
+            action: ClickAction::RunCommand,
+            value: command.into(),
+        }
+    }
+
+    pub fn suggest_command(command: impl Into<String>) -> ClickEvent {
+        ClickEvent {
+            action: ClickAction::SuggestCommand,
+            value: command.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickAction {
+    OpenUrl,
+    OpenFile,
+    RunCommand,
+    SuggestCommand,
+    /// Only works in books.
+    ChangePage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HoverEvent {
+    pub action: HoverAction,
+    /// The text to show, or the achievement, item or entity as SNBT in a text component.
+    pub value: Box<TextComponent>,
+}
+
+impl HoverEvent {
+    pub fn show_text(text: impl Into<TextComponent>) -> HoverEvent {
+        HoverEvent {
+            action: HoverAction::ShowText,
+            value: Box::new(text.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoverAction {
+    ShowText,
+    ShowAchievement,
+    ShowItem,
+    ShowEntity,
+}
+
+impl TextComponent {
+    pub fn text(text: impl Into<String>) -> TextComponent {
+        TextComponent::new(Content::Text { text: text.into() })
+    }
+
+    pub fn translate(
+        key: impl Into<String>,
+        with: impl IntoIterator<Item = TextComponent>,
+    ) -> TextComponent {
+        TextComponent::new(Content::Translate {
+            translate: key.into(),
+            with: with.into_iter().collect(),
+        })
+    }
+
+    pub fn score(name: impl Into<String>, objective: impl Into<String>) -> TextComponent {
+        TextComponent::new(Content::Score {
+            score: Score {
+                name: name.into(),
+                objective: objective.into(),
+                value: None,
+            },
+        })
+    }
+
+    pub fn selector(selector: impl Into<String>) -> TextComponent {
+        TextComponent::new(Content::Selector {
+            selector: selector.into(),
+        })
+    }
+
+    fn new(content: Content) -> TextComponent {
+        TextComponent {
+            content,
+            style: Style::default(),
+            extra: Vec::new(),
+        }
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.style.color = Some(color);
+        self
+    }
+
+    pub fn bold(mut self, bold: bool) -> Self {
+        self.style.bold = Some(bold);
+        self
+    }
+
+    pub fn italic(mut self, italic: bool) -> Self {
+        self.style.italic = Some(italic);
+        self
+    }
+
+    pub fn underlined(mut self, underlined: bool) -> Self {
+        self.style.underlined = Some(underlined);
+        self
+    }
+
+    pub fn strikethrough(mut self, strikethrough: bool) -> Self {
+        self.style.strikethrough = Some(strikethrough);
+        self
+    }
+
+    pub fn obfuscated(mut self, obfuscated: bool) -> Self {
+        self.style.obfuscated = Some(obfuscated);
+        self
+    }
+
+    pub fn insertion(mut self, insertion: impl Into<String>) -> Self {
+        self.style.insertion = Some(insertion.into());
+        self
+    }
+
+    pub fn click_event(mut self, click_event: ClickEvent) -> Self {
+        self.style.click_event = Some(click_event);
+        self
+    }
+
+    pub fn hover_event(mut self, hover_event: HoverEvent) -> Self {
+        self.style.hover_event = Some(hover_event);
+        self
+    }
+
+    /// Appends a child component.
+    pub fn extra(mut self, child: impl Into<TextComponent>) -> Self {
+        self.extra.push(child.into());
+        self
+    }
+
+    /// Parses a legacy string with `§` formatting codes.
+    /// Unknown codes are kept as text.
+    pub fn from_legacy(legacy: &str) -> TextComponent {
+        let mut parts: Vec<TextComponent> = Vec::new();
+        let mut style = Style::default();
+        let mut text = String::new();
+
+        let mut chars = legacy.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != LEGACY_PREFIX {
+                text.push(c);
+                continue;
+            }
+            let Some(&code) = chars.peek() else {
+                text.push(c);
+                continue;
+            };
+
+            let previous: Style = style.clone();
+            match code.to_ascii_lowercase() {
+                'k' => style.obfuscated = Some(true),
+                'l' => style.bold = Some(true),
+                'm' => style.strikethrough = Some(true),
+                'n' => style.underlined = Some(true),
+                'o' => style.italic = Some(true),
+                'r' => style = Style::default(),
+                // Colors reset the formatting
+                code => match Color::from_legacy_code(code) {
+                    Some(color) => {
+                        style = Style {
+                            color: Some(color),
+                            ..Style::default()
+                        }
+                    }
+                    None => {
+                        text.push(c);
+                        continue;
+                    }
+                },
+            }
+            chars.next();
+
+            if !text.is_empty() {
+                let mut part = TextComponent::text(std::mem::take(&mut text));
+                part.style = previous;
+                parts.push(part);
+            }
+        }
+        if !text.is_empty() || parts.is_empty() {
+            let mut part = TextComponent::text(text);
+            part.style = style;
+            parts.push(part);
+        }
+
+        if parts.len() == 1 {
+            parts.pop().unwrap()
+        } else {
+            TextComponent {
+                extra: parts,
+                ..TextComponent::default()
+            }
+        }
+    }
+
+    /// Converts the component to a legacy string with `§` formatting codes.
+    /// Click and hover events are lost.
+    pub fn to_legacy(&self) -> String {
+        let mut legacy = String::new();
+        let mut current = Formatting::default();
+        self.write_legacy(&mut legacy, &mut current, Formatting::default());
+        legacy
+    }
+
+    fn write_legacy(&self, legacy: &mut String, current: &mut Formatting, parent: Formatting) {
+        let formatting: Formatting = parent.inherit(&self.style);
+        let mut text = String::new();
+        self.write_content(&mut text).unwrap();
+
+        if !text.is_empty() {
+            if formatting != *current {
+                formatting.write_codes(legacy, *current != Formatting::default());
+                *current = formatting;
+            }
+            legacy.push_str(&text);
+        }
+
+        for child in &self.extra {
+            child.write_legacy(legacy, current, formatting);
+        }
+    }
+
+    /// Writes the content without the children.
+    fn write_content(&self, f: &mut impl fmt::Write) -> fmt::Result {
+        match &self.content {
+            Content::Text { text } => f.write_str(text),
+            // The translations are only known by the client
+            Content::Translate { translate, with } => {
+                f.write_str(translate)?;
+                if !with.is_empty() {
+                    f.write_char('(')?;
+                    for (i, argument) in with.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f, "{argument}")?;
+                    }
+                    f.write_char(')')?;
+                }
+                Ok(())
+            }
+            Content::Score { score } => f.write_str(score.value.as_deref().unwrap_or_default()),
+            Content::Selector { selector } => f.write_str(selector),
+        }
+    }
+}
+
+/// Writes the component as plain text, without formatting.
+impl fmt::Display for TextComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_content(f)?;
+        self.extra.iter().try_for_each(|child| child.fmt(f))
+    }
+}
+
+impl From<String> for TextComponent {
+    fn from(text: String) -> Self {
+        TextComponent::text(text)
+    }
+}
+
+impl From<&str> for TextComponent {
+    fn from(text: &str) -> Self {
+        TextComponent::text(text)
+    }
+}
+
+impl Decode for TextComponent {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        decode_json(reader)
+    }
+}
+
+impl Encode for TextComponent {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        encode_json(self, writer)
+    }
+}
+
+/// The JSON forms of a component: a string is a text component, and an array is the first component with the rest as children.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawComponent {
+    Text(String),
+    Array(Vec<TextComponent>),
+    Object {
+        #[serde(flatten)]
+        content: Content,
+        #[serde(flatten)]
+        style: Style,
+        #[serde(default)]
+        extra: Vec<TextComponent>,
+    },
+}
+
+impl TryFrom<RawComponent> for TextComponent {
+    type Error = &'static str;
+
+    fn try_from(raw: RawComponent) -> Result<Self, Self::Error> {
+        match raw {
+            RawComponent::Text(text) => Ok(TextComponent::text(text)),
+            RawComponent::Array(components) => {
+                let mut components = components.into_iter();
+                let mut first: TextComponent =
+                    components.next().ok_or("empty text component array")?;
+                first.extra.extend(components);
+                Ok(first)
+            }
+            RawComponent::Object {
+                content,
+                style,
+                extra,
+            } => Ok(TextComponent {
+                content,
+                style,
+                extra,
+            }),
+        }
+    }
+}
+
+/// The effective formatting of a component, after inheriting the style of its parents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Formatting {
+    color: Option<Color>,
+    obfuscated: bool,
+    bold: bool,
+    strikethrough: bool,
+    underlined: bool,
+    italic: bool,
+}
+
+impl Formatting {
+    fn inherit(self, style: &Style) -> Formatting {
+        Formatting {
+            color: match style.color {
+                Some(Color::Reset) => None,
+                Some(color) => Some(color),
+                None => self.color,
+            },
+            obfuscated: style.obfuscated.unwrap_or(self.obfuscated),
+            bold: style.bold.unwrap_or(self.bold),
+            strikethrough: style.strikethrough.unwrap_or(self.strikethrough),
+            underlined: style.underlined.unwrap_or(self.underlined),
+            italic: style.italic.unwrap_or(self.italic),
+        }
+    }
+
+    /// Writes the codes that switch to this formatting.
+    /// A color code resets the formatting, so without a color the formatting is reset with `§r` if `reset` is set.
+    fn write_codes(self, legacy: &mut String, reset: bool) {
+        match self.color {
+            Some(color) => {
+                legacy.push(LEGACY_PREFIX);
+                legacy.push(color.legacy_code());
+            }
+            None if reset => {
+                legacy.push(LEGACY_PREFIX);
+                legacy.push('r');
+            }
+            None => {}
+        }
+
+        for (enabled, code) in [
+            (self.obfuscated, 'k'),
+            (self.bold, 'l'),
+            (self.strikethrough, 'm'),
+            (self.underlined, 'n'),
+            (self.italic, 'o'),
+        ] {
+            if enabled {
+                legacy.push(LEGACY_PREFIX);
+                legacy.push(code);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        let component = TextComponent::text("Hello, ")
+            .color(Color::Gold)
+            .extra(
+                TextComponent::text("world")
+                    .bold(true)
+                    .click_event(ClickEvent::run_command("/hello"))
+                    .hover_event(HoverEvent::show_text("Click me")),
+            )
+            .extra(TextComponent::translate(
+                "chat.type.text",
+                [TextComponent::selector("@p"), "!".into()],
+            ));
+
+        let json: String = serde_json::to_string(&component).unwrap();
+        assert_eq!(
+            json,
+            r#"{"text":"Hello, ","color":"gold","extra":[{"text":"world","bold":true,"clickEvent":{"action":"run_command","value":"/hello"},"hoverEvent":{"action":"show_text","value":{"text":"Click me"}}},{"translate":"chat.type.text","with":[{"selector":"@p"},{"text":"!"}]}]}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<TextComponent>(&json).unwrap(),
+            component
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        assert_eq!(
+            serde_json::from_str::<TextComponent>(r#""plain""#).unwrap(),
+            TextComponent::text("plain")
+        );
+        assert_eq!(
+            serde_json::from_str::<TextComponent>(r#"["a", {"text": "b", "italic": true}]"#)
+                .unwrap(),
+            TextComponent::text("a").extra(TextComponent::text("b").italic(true))
+        );
+        assert_eq!(
+            serde_json::from_str::<TextComponent>(
+                r#"{"score": {"name": "Notch", "objective": "kills", "value": "3"}}"#
+            )
+            .unwrap()
+            .to_string(),
+            "3"
+        );
+        assert!(serde_json::from_str::<TextComponent>("[]").is_err());
+        assert!(serde_json::from_str::<TextComponent>(r#"{"color": "red"}"#).is_err());
+    }
+
+    #[test]
+    fn test_legacy() {
+        let component = TextComponent::from_legacy("§6Hello §lworld§r!");
+        assert_eq!(
+            component,
+            TextComponent::text("")
+                .extra(TextComponent::text("Hello ").color(Color::Gold))
+                .extra(TextComponent::text("world").color(Color::Gold).bold(true))
+                .extra(TextComponent::text("!"))
+        );
+        assert_eq!(component.to_legacy(), "§6Hello §6§lworld§r!");
+        assert_eq!(component.to_string(), "Hello world!");
+
+        assert_eq!(
+            TextComponent::from_legacy("100§ and §zmore"),
+            TextComponent::text("100§ and §zmore")
+        );
+        assert_eq!(
+            TextComponent::text("a")
+                .italic(true)
+                .extra(TextComponent::text("b").color(Color::Red))
+                .to_legacy(),
+            "§oa§c§ob"
+        );
+    }
+
+    #[test]
+    fn test_codec() {
+        let component = TextComponent::text("Bye").color(Color::Red);
+        let mut buf: Vec<u8> = Vec::new();
+        component.encode(&mut buf).unwrap();
+        assert_eq!(TextComponent::decode(&mut &buf[..]).unwrap(), component);
+
+        let mut buf: Vec<u8> = Vec::new();
+        "{".encode(&mut buf).unwrap();
+        assert!(matches!(
+            TextComponent::decode(&mut &buf[..]),
+            Err(DecodeError::InvalidJson(_))
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+//! Checked transitions between the states of a connection.
+//!
+//! ```text
+//! Handshaking --Handshake--> Status | Login
+//! Login --LoginSuccess--> Play
+//! ```
+//!
+//! Every other packet keeps the state, but is only allowed in the state it belongs to.
+//!
+//! Received packets are decoded by the ID in the state of the connection, so they always belong to it.
+//! The ID of a packet from another state is either unknown in the current state,
+//! which fails with [`PacketDecodeError::UnknownPacketId`](crate::PacketDecodeError::UnknownPacketId),
+//! or it is decoded as the packet with the same ID in the current state.
+//! [`State::next_serverbound`] only rejects packets that weren't decoded in this state.
+
+use thiserror::Error;
+
+use crate::{neutral, Direction, State};
+
+/// A packet was sent in a state it doesn't belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("unexpected {direction:?} {packet_state:?} packet in state {state:?}")]
+pub struct UnexpectedPacket {
+    pub direction: Direction,
+    /// The state the packet belongs to.
+    pub packet_state: State,
+    /// The state of the connection.
+    pub state: State,
+}
+
+impl State {
+    /// Returns the state after the client sent `packet` in this state.
+    pub fn next_serverbound(
+        self,
+        packet: &neutral::Serverbound,
+    ) -> Result<State, UnexpectedPacket> {
+        self.expect(Direction::Serverbound, packet.state())?;
+
+        Ok(match *packet {
+            neutral::Serverbound::Handshake { next_state, .. } => next_state.into(),
+            _ => self,
+        })
+    }
+
+    /// Returns the state after the server sent `packet` in this state.
+    pub fn next_clientbound(
+        self,
+        packet: &neutral::Clientbound,
+    ) -> Result<State, UnexpectedPacket> {
+        self.expect(Direction::Clientbound, packet.state())?;
+
+        Ok(match *packet {
+            neutral::Clientbound::LoginSuccess { .. } => State::Play,
+            _ => self,
+        })
+    }
+
+    fn expect(self, direction: Direction, packet_state: State) -> Result<(), UnexpectedPacket> {
+        if packet_state == self {
+            Ok(())
+        } else {
+            Err(UnexpectedPacket {
+                direction,
+                packet_state,
+                state: self,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NextState;
+
+    fn handshake(next_state: NextState) -> neutral::Serverbound {
+        neutral::Serverbound::Handshake {
+            protocol_version: 4,
+            server_address: "localhost".to_owned(),
+            server_port: 25565,
+            next_state,
+        }
+    }
+
+    #[test]
+    fn test_transitions() {
+        assert_eq!(
+            State::Handshaking.next_serverbound(&handshake(NextState::Status)),
+            Ok(State::Status)
+        );
+        assert_eq!(
+            State::Handshaking.next_serverbound(&handshake(NextState::Login)),
+            Ok(State::Login)
+        );
+        assert_eq!(
+            State::Login.next_serverbound(&neutral::Serverbound::LoginStart {
+                username: "Notch".to_owned(),
+            }),
+            Ok(State::Login)
+        );
+        assert_eq!(
+            State::Login.next_clientbound(&neutral::Clientbound::LoginSuccess {
+                uuid: uuid::Uuid::nil(),
+                username: "Notch".to_owned(),
+            }),
+            Ok(State::Play)
+        );
+        assert_eq!(
+            State::Play.next_serverbound(&neutral::Serverbound::KeepAlive { id: 1 }),
+            Ok(State::Play)
+        );
+    }
+
+    #[test]
+    fn test_unexpected_packets() {
+        assert_eq!(
+            State::Login.next_serverbound(&neutral::Serverbound::KeepAlive { id: 1 }),
+            Err(UnexpectedPacket {
+                direction: Direction::Serverbound,
+                packet_state: State::Play,
+                state: State::Login,
+            })
+        );
+        assert!(State::Play
+            .next_serverbound(&handshake(NextState::Login))
+            .is_err());
+        assert!(State::Status
+            .next_clientbound(&neutral::Clientbound::KeepAlive { id: 1 })
+            .is_err());
+    }
+}
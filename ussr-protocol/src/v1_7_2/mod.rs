@@ -1,3 +1,5 @@
-pub mod enums;
+pub(crate) mod convert;
 pub mod packets;
 pub mod types;
+
+pub use crate::enums;
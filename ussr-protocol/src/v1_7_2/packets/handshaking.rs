@@ -1,19 +1,103 @@
 use super::*;
 
-#[packets(Handshake)]
+#[packets(Handshaking)]
 pub mod clientbound {}
 
-#[packets(Handshake)]
+#[packets(Handshaking)]
 pub mod serverbound {
     use super::*;
 
     #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct Handshake {
         #[var]
         pub protocol_version: i32,
+        /// Vanilla allows 255 characters, but BungeeCord forwarding appends the profile of the player.
+        #[buf(max_len = 32767)]
         pub server_address: String,
         pub server_port: u16,
         pub next_state: enums::NextState,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use ussr_buf::VarDecode;
+
+    use super::*;
+    use crate::{Direction, Packet, PacketDecodeError, State};
+
+    #[rustfmt::skip]
+    const FRAME: &[u8] = &[
+        0x00,
+        0x04,
+        0x09, b'l', b'o', b'c', b'a', b'l', b'h', b'o', b's', b't',
+        0x63, 0xDD,
+        0x02,
+    ];
+
+    #[test]
+    fn test_dispatch() {
+        let mut reader: Cursor<&[u8]> = Cursor::new(FRAME);
+        let id: u32 = u32::var_decode(&mut reader).unwrap();
+        let packet = serverbound::HandshakingPackets::decode(id, &mut reader).unwrap();
+        assert_eq!(packet.id(), serverbound::Handshake::ID);
+        assert_eq!(
+            packet,
+            serverbound::Handshake {
+                protocol_version: 4,
+                server_address: "localhost".to_owned(),
+                server_port: 25565,
+                next_state: enums::NextState::Login,
+            }
+            .into()
+        );
+
+        let mut buf: Vec<u8> = Vec::new();
+        packet.encode(&mut buf).unwrap();
+        assert_eq!(buf, FRAME);
+
+        assert!(matches!(
+            serverbound::HandshakingPackets::decode(0x01, &mut &FRAME[1..]),
+            Err(PacketDecodeError::UnknownPacketId {
+                packet_id: 0x01,
+                state: State::Handshaking,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_metadata() {
+        assert_eq!(serverbound::Handshake::ID, 0x00);
+        assert_eq!(serverbound::Handshake::STATE, State::Handshaking);
+        assert_eq!(serverbound::Handshake::DIRECTION, Direction::Serverbound);
+        assert_eq!(serverbound::Handshake::NAME, "Handshake");
+        assert_eq!(
+            serverbound::HandshakingPackets::from(serverbound::Handshake {
+                protocol_version: 4,
+                server_address: String::new(),
+                server_port: 25565,
+                next_state: enums::NextState::Status,
+            })
+            .name(),
+            "Handshake"
+        );
+    }
+
+    #[test]
+    fn test_error_context() {
+        let mut frame: Vec<u8> = FRAME.to_vec();
+        *frame.last_mut().unwrap() = 0x07;
+
+        let e = serverbound::HandshakingPackets::decode(0x00, &mut &frame[1..]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Handshake.next_state at offset 14: invalid enum variant 7"
+        );
+
+        // Errors without a field fall back to where decoding stopped
+        let e = PacketDecodeError::decode(u64::decode(&mut &frame[..3]).unwrap_err(), 0, 3);
+        assert_eq!(e.to_string(), "at offset 3: failed to fill whole buffer");
+    }
+}
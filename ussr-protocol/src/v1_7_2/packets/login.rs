@@ -5,13 +5,11 @@ pub mod clientbound {
     use super::*;
 
     #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct Disconnect {
-        pub reason: String,
+        pub reason: TextComponent,
     }
 
     #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct EncryptionRequest {
         pub server_id: String,
 
@@ -23,7 +21,6 @@ pub mod clientbound {
     }
 
     #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct LoginSuccess {
         pub uuid: String,
         pub username: String,
@@ -35,18 +32,63 @@ pub mod serverbound {
     use super::*;
 
     #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct LoginStart {
+        #[buf(max_len = 16)]
         pub username: String,
     }
 
     #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct EncryptionResponse {
         #[array(u16, u8)]
+        #[buf(max_len = 128)]
         pub shared_secret: Vec<u8>,
 
         #[array(u16, u8)]
+        #[buf(max_len = 128)]
         pub verify_token: Vec<u8>,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ussr_buf::DecodeError;
+
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let mut buf: Vec<u8> = Vec::new();
+        "Notch".encode(&mut buf).unwrap();
+        assert_eq!(
+            serverbound::LoginStart::decode(&mut &buf[..]).unwrap(),
+            serverbound::LoginStart {
+                username: "Notch".to_owned()
+            }
+        );
+
+        let mut buf: Vec<u8> = Vec::new();
+        "ThisNameIsWayTooLong".encode(&mut buf).unwrap();
+        assert!(matches!(
+            serverbound::LoginStart::decode(&mut &buf[..])
+                .unwrap_err()
+                .cause(),
+            DecodeError::LengthLimitExceeded {
+                field: "username",
+                length: 20,
+                max_length: 16,
+            }
+        ));
+
+        // A huge length must be rejected before anything is allocated or read
+        assert!(matches!(
+            serverbound::EncryptionResponse::decode(&mut &[0xFF, 0xFF][..])
+                .unwrap_err()
+                .cause(),
+            DecodeError::LengthLimitExceeded {
+                field: "shared_secret",
+                length: 65535,
+                max_length: 128,
+            }
+        ));
+    }
+}
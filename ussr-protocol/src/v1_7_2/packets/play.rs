@@ -1,32 +1,143 @@
+use std::io::{self, Read, Write};
+
+use ussr_buf::{decode_array, read_bytes, DecodeError, DecodeExt, EncodeExt};
+use ussr_nbt::owned::Nbt;
+
 use super::*;
+use crate::{
+    v1_7_2::types::{
+        self, decode_gzip_nbt, encode_gzip_nbt, Angle, ByteFixedPoint, FixedPoint, Slot,
+    },
+    TextComponent,
+};
 
-#[packets(Play)]
-pub mod clientbound {
-    use std::io::{self, Read, Write};
+pub use crate::common::{AttributeModifier, ExplosionRecord, Statistic};
+
+/// A single block change in [`clientbound::MultiBlockChange`], packed into 4 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BlockChangeRecord {
+    /// Relative to the chunk, 0-15.
+    pub x: u8,
+    /// Relative to the chunk, 0-15.
+    pub z: u8,
+    pub y: u8,
+    /// Only the lower 12 bits are used.
+    pub block_id: u16,
+    /// Only the lower 4 bits are used.
+    pub metadata: u8,
+}
+
+impl BlockChangeRecord {
+    pub const fn to_u32(self) -> u32 {
+        (self.x as u32 & 0xF) << 28
+            | (self.z as u32 & 0xF) << 24
+            | (self.y as u32) << 16
+            | (self.block_id as u32 & 0xFFF) << 4
+            | (self.metadata as u32 & 0xF)
+    }
+
+    pub const fn from_u32(value: u32) -> BlockChangeRecord {
+        BlockChangeRecord {
+            x: (value >> 28) as u8,
+            z: (value >> 24 & 0xF) as u8,
+            y: (value >> 16) as u8,
+            block_id: (value >> 4 & 0xFFF) as u16,
+            metadata: (value & 0xF) as u8,
+        }
+    }
+}
 
-    #[cfg(feature = "async")]
-    use futures_lite::{AsyncReadExt, AsyncWriteExt};
-    #[cfg(feature = "async")]
-    use ussr_buf::{AsyncDecodeExt, AsyncEncodeExt};
-    use ussr_buf::{DecodeError, DecodeExt, EncodeExt};
+impl Decode for BlockChangeRecord {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(BlockChangeRecord::from_u32(u32::decode(reader)?))
+    }
+}
+
+impl Encode for BlockChangeRecord {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        self.to_u32().encode(writer)
+    }
+}
+
+/// The position and sections of a chunk column in [`clientbound::MapChunkBulk`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Decode, Encode)]
+pub struct ChunkMeta {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub primary_bit_map: u16,
+    pub add_bit_map: u16,
+}
+
+/// An attribute of an entity in [`clientbound::EntityProperties`].
+#[derive(Debug, Clone, PartialEq, Decode, Encode)]
+pub struct EntityProperty {
+    pub key: String,
+    pub value: f64,
+
+    #[array(i16, AttributeModifier)]
+    pub modifiers: Vec<AttributeModifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateScoreAction {
+    Update { score_name: String, value: i32 },
+    Remove,
+}
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TeamAction {
+    Create {
+        display_name: String,
+        prefix: String,
+        suffix: String,
+        friendly_fire: i8,
+        players: Vec<String>,
+    },
+    Remove,
+    Update {
+        display_name: String,
+        prefix: String,
+        suffix: String,
+        friendly_fire: i8,
+    },
+    AddPlayers(Vec<String>),
+    RemovePlayers(Vec<String>),
+}
+
+fn decode_players(reader: &mut impl Read) -> Result<Vec<String>, DecodeError> {
+    let length: usize = i16::decode(reader)?
+        .try_into()
+        .map_err(|_| DecodeError::InvalidArrayLength)?;
+    decode_array(reader, length, String::decode)
+}
+
+fn encode_players(players: &[String], writer: &mut impl Write) -> io::Result<()> {
+    writer.encode(players.len() as i16)?;
+    players.iter().try_for_each(|player| player.encode(writer))
+}
+
+fn encode_block_entity_nbt(nbt: &Option<Nbt>, writer: &mut impl Write) -> io::Result<()> {
+    encode_gzip_nbt(nbt.as_ref(), writer)
+}
+
+#[packets(Play)]
+pub mod clientbound {
     use super::*;
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct KeepAlive {
-        id: i32,
+        pub id: i32,
     }
 
     #[derive(Debug, Clone, PartialEq, Eq)]
     pub struct JoinGame {
-        entity_id: i32,
-        hardcore: bool,
-        gamemode: enums::Gamemode,
-        dimension: enums::Dimension,
-        difficulty: enums::Difficulty,
-        max_players: u8,
-        level_type: String,
+        pub entity_id: i32,
+        pub hardcore: bool,
+        pub gamemode: enums::Gamemode,
+        pub dimension: enums::Dimension,
+        pub difficulty: enums::Difficulty,
+        pub max_players: u8,
+        pub level_type: String,
     }
 
     impl Decode for JoinGame {
@@ -59,47 +170,1831 @@ pub mod clientbound {
         }
     }
 
-    #[cfg(feature = "async")]
-    impl AsyncDecode for JoinGame {
-        async fn decode(
-            reader: &mut (impl AsyncReadExt + Unpin + Send),
-        ) -> Result<Self, DecodeError> {
-            let entity_id: i32 = reader.decode().await?;
-            let gamemode: u8 = reader.decode().await?;
-            let hardcore: bool = gamemode & 0x8 != 0;
-            let gamemode: enums::Gamemode = (gamemode & !0x8).try_into()?;
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct ChatMessage {
+        pub message: TextComponent,
+    }
 
-            Ok(JoinGame {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct TimeUpdate {
+        pub world_age: i64,
+        /// Negative if the daylight cycle is stopped.
+        pub time_of_day: i64,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct EntityEquipment {
+        pub entity_id: i32,
+        /// 0 held item, 1-4 armor from boots to helmet.
+        pub slot: i16,
+        pub item: Slot,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct SpawnPosition {
+        pub x: i32,
+        pub y: i32,
+        pub z: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct UpdateHealth {
+        pub health: f32,
+        pub food: i16,
+        pub food_saturation: f32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct Respawn {
+        pub dimension: i32,
+        pub difficulty: enums::Difficulty,
+        pub gamemode: enums::Gamemode,
+        pub level_type: String,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct PlayerPositionAndLook {
+        pub x: f64,
+        /// The position of the player's eyes.
+        pub y: f64,
+        pub z: f64,
+        pub yaw: f32,
+        pub pitch: f32,
+        pub on_ground: bool,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct HeldItemChange {
+        pub slot: i8,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct UseBed {
+        pub entity_id: i32,
+        pub x: i32,
+        pub y: u8,
+        pub z: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct Animation {
+        #[var]
+        pub entity_id: i32,
+        pub animation: u8,
+    }
+
+    /// 1.7.6 sends the properties of the player's profile after the name,
+    /// see [`SpawnPlayer::decode_v1_7_6`] and [`SpawnPlayer::encode_v1_7_6`].
+    /// [`Decode`] and [`Encode`] use the layout of 1.7.2, without the properties.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct SpawnPlayer {
+        pub entity_id: i32,
+        pub uuid: String,
+        pub name: String,
+        /// Only sent to 1.7.6.
+        pub properties: Vec<types::ProfileProperty>,
+        pub x: FixedPoint,
+        pub y: FixedPoint,
+        pub z: FixedPoint,
+        pub yaw: Angle,
+        pub pitch: Angle,
+        pub current_item: i16,
+        pub metadata: types::EntityMetadata,
+    }
+
+    impl SpawnPlayer {
+        pub fn decode_v1_7_6(reader: &mut impl Read) -> Result<Self, DecodeError> {
+            Self::decode_body(reader, true)
+        }
+
+        pub fn encode_v1_7_6(&self, writer: &mut impl Write) -> io::Result<()> {
+            self.encode_body(writer, true)
+        }
+
+        fn decode_body(reader: &mut impl Read, properties: bool) -> Result<Self, DecodeError> {
+            let entity_id: i32 = reader.var_decode()?;
+            let uuid: String = reader.decode()?;
+            let name: String = reader.decode()?;
+
+            let properties: Vec<types::ProfileProperty> = if properties {
+                let count: i32 = reader.var_decode()?;
+                let count: usize = count
+                    .try_into()
+                    .map_err(|_| DecodeError::InvalidArrayLength)?;
+                decode_array(reader, count, types::ProfileProperty::decode)?
+            } else {
+                Vec::new()
+            };
+
+            Ok(SpawnPlayer {
                 entity_id,
-                hardcore,
-                gamemode,
-                dimension: reader.decode().await?,
-                difficulty: reader.decode().await?,
-                max_players: reader.decode().await?,
-                level_type: reader.decode().await?,
+                uuid,
+                name,
+                properties,
+                x: reader.decode()?,
+                y: reader.decode()?,
+                z: reader.decode()?,
+                yaw: reader.decode()?,
+                pitch: reader.decode()?,
+                current_item: reader.decode()?,
+                metadata: reader.decode()?,
+            })
+        }
+
+        fn encode_body(&self, writer: &mut impl Write, properties: bool) -> io::Result<()> {
+            writer.var_encode(self.entity_id)?;
+            writer.encode(&self.uuid)?;
+            writer.encode(&self.name)?;
+
+            if properties {
+                writer.var_encode(self.properties.len() as i32)?;
+                self.properties
+                    .iter()
+                    .try_for_each(|property| property.encode(writer))?;
+            }
+
+            writer.encode(self.x)?;
+            writer.encode(self.y)?;
+            writer.encode(self.z)?;
+            writer.encode(self.yaw)?;
+            writer.encode(self.pitch)?;
+            writer.encode(self.current_item)?;
+            writer.encode(&self.metadata)
+        }
+    }
+
+    impl Decode for SpawnPlayer {
+        fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+            Self::decode_body(reader, false)
+        }
+    }
+
+    impl Encode for SpawnPlayer {
+        fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+            self.encode_body(writer, false)
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct CollectItem {
+        pub collected_entity_id: i32,
+        pub collector_entity_id: i32,
+    }
+
+    /// The velocity is only sent if `data` is positive, and is decoded as zero otherwise.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SpawnObject {
+        pub entity_id: i32,
+        pub kind: i8,
+        pub x: FixedPoint,
+        pub y: FixedPoint,
+        pub z: FixedPoint,
+        pub pitch: Angle,
+        pub yaw: Angle,
+        pub data: i32,
+        pub velocity_x: i16,
+        pub velocity_y: i16,
+        pub velocity_z: i16,
+    }
+
+    impl Decode for SpawnObject {
+        fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+            let mut packet = SpawnObject {
+                entity_id: reader.var_decode()?,
+                kind: reader.decode()?,
+                x: reader.decode()?,
+                y: reader.decode()?,
+                z: reader.decode()?,
+                pitch: reader.decode()?,
+                yaw: reader.decode()?,
+                data: reader.decode()?,
+                velocity_x: 0,
+                velocity_y: 0,
+                velocity_z: 0,
+            };
+
+            if packet.data > 0 {
+                packet.velocity_x = reader.decode()?;
+                packet.velocity_y = reader.decode()?;
+                packet.velocity_z = reader.decode()?;
+            }
+
+            Ok(packet)
+        }
+    }
+
+    impl Encode for SpawnObject {
+        fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+            writer.var_encode(self.entity_id)?;
+            writer.encode(self.kind)?;
+            writer.encode(self.x)?;
+            writer.encode(self.y)?;
+            writer.encode(self.z)?;
+            writer.encode(self.pitch)?;
+            writer.encode(self.yaw)?;
+            writer.encode(self.data)?;
+
+            if self.data > 0 {
+                writer.encode(self.velocity_x)?;
+                writer.encode(self.velocity_y)?;
+                writer.encode(self.velocity_z)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct SpawnMob {
+        #[var]
+        pub entity_id: i32,
+        pub kind: u8,
+        pub x: FixedPoint,
+        pub y: FixedPoint,
+        pub z: FixedPoint,
+        pub yaw: Angle,
+        pub pitch: Angle,
+        pub head_yaw: Angle,
+        pub velocity_x: i16,
+        pub velocity_y: i16,
+        pub velocity_z: i16,
+        pub metadata: types::EntityMetadata,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct SpawnPainting {
+        #[var]
+        pub entity_id: i32,
+        pub title: String,
+        pub x: i32,
+        pub y: i32,
+        pub z: i32,
+        pub direction: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct SpawnExperienceOrb {
+        #[var]
+        pub entity_id: i32,
+        pub x: FixedPoint,
+        pub y: FixedPoint,
+        pub z: FixedPoint,
+        pub count: i16,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EntityVelocity {
+        pub entity_id: i32,
+        pub velocity_x: i16,
+        pub velocity_y: i16,
+        pub velocity_z: i16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct DestroyEntities {
+        #[array(u8, i32)]
+        pub entity_ids: Vec<i32>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct Entity {
+        pub entity_id: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EntityRelativeMove {
+        pub entity_id: i32,
+        pub dx: ByteFixedPoint,
+        pub dy: ByteFixedPoint,
+        pub dz: ByteFixedPoint,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EntityLook {
+        pub entity_id: i32,
+        pub yaw: Angle,
+        pub pitch: Angle,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EntityLookAndRelativeMove {
+        pub entity_id: i32,
+        pub dx: ByteFixedPoint,
+        pub dy: ByteFixedPoint,
+        pub dz: ByteFixedPoint,
+        pub yaw: Angle,
+        pub pitch: Angle,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EntityTeleport {
+        pub entity_id: i32,
+        pub x: FixedPoint,
+        pub y: FixedPoint,
+        pub z: FixedPoint,
+        pub yaw: Angle,
+        pub pitch: Angle,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EntityHeadLook {
+        pub entity_id: i32,
+        pub head_yaw: Angle,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EntityStatus {
+        pub entity_id: i32,
+        pub status: i8,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct AttachEntity {
+        pub entity_id: i32,
+        /// -1 to detach.
+        pub vehicle_id: i32,
+        pub leash: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct EntityMetadata {
+        pub entity_id: i32,
+        pub metadata: types::EntityMetadata,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EntityEffect {
+        pub entity_id: i32,
+        pub effect_id: i8,
+        pub amplifier: i8,
+        pub duration: i16,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct RemoveEntityEffect {
+        pub entity_id: i32,
+        pub effect_id: i8,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct SetExperience {
+        /// Between 0 and 1.
+        pub experience_bar: f32,
+        pub level: i16,
+        pub total_experience: i16,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct EntityProperties {
+        pub entity_id: i32,
+
+        #[array(i32, EntityProperty)]
+        pub properties: Vec<EntityProperty>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct ChunkData {
+        pub chunk_x: i32,
+        pub chunk_z: i32,
+        pub ground_up_continuous: bool,
+        pub primary_bit_map: u16,
+        pub add_bit_map: u16,
+
+        /// Zlib compressed.
+        #[array(i32, u8)]
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct MultiBlockChange {
+        pub chunk_x: i32,
+        pub chunk_z: i32,
+        pub records: Vec<BlockChangeRecord>,
+    }
+
+    impl Decode for MultiBlockChange {
+        fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+            let chunk_x: i32 = reader.decode()?;
+            let chunk_z: i32 = reader.decode()?;
+            let count: i16 = reader.decode()?;
+            let size: i32 = reader.decode()?;
+
+            // Every record takes up 4 bytes
+            let count: usize = count
+                .try_into()
+                .map_err(|_| DecodeError::InvalidArrayLength)?;
+            if size as i64 != count as i64 * 4 {
+                return Err(DecodeError::InvalidArrayLength);
+            }
+
+            Ok(MultiBlockChange {
+                chunk_x,
+                chunk_z,
+                records: decode_array(reader, count, BlockChangeRecord::decode)?,
+            })
+        }
+    }
+
+    impl Encode for MultiBlockChange {
+        fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+            writer.encode(self.chunk_x)?;
+            writer.encode(self.chunk_z)?;
+            writer.encode(self.records.len() as i16)?;
+            writer.encode(self.records.len() as i32 * 4)?;
+            self.records
+                .iter()
+                .try_for_each(|record| record.encode(writer))
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct BlockChange {
+        pub x: i32,
+        pub y: u8,
+        pub z: i32,
+        #[var]
+        pub block_id: i32,
+        pub block_metadata: u8,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct BlockAction {
+        pub x: i32,
+        pub y: i16,
+        pub z: i32,
+        pub byte_1: u8,
+        pub byte_2: u8,
+        #[var]
+        pub block_id: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct BlockBreakAnimation {
+        #[var]
+        pub entity_id: i32,
+        pub x: i32,
+        pub y: i32,
+        pub z: i32,
+        /// 0-9, anything else removes the animation.
+        pub destroy_stage: i8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct MapChunkBulk {
+        pub sky_light_sent: bool,
+        /// Zlib compressed data of all chunk columns.
+        pub data: Vec<u8>,
+        pub chunks: Vec<ChunkMeta>,
+    }
+
+    impl Decode for MapChunkBulk {
+        fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+            let count: usize = i16::decode(reader)?
+                .try_into()
+                .map_err(|_| DecodeError::InvalidArrayLength)?;
+            let length: usize = i32::decode(reader)?
+                .try_into()
+                .map_err(|_| DecodeError::InvalidArrayLength)?;
+
+            Ok(MapChunkBulk {
+                sky_light_sent: reader.decode()?,
+                data: read_bytes(reader, length)?,
+                chunks: decode_array(reader, count, ChunkMeta::decode)?,
+            })
+        }
+    }
+
+    impl Encode for MapChunkBulk {
+        fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+            writer.encode(self.chunks.len() as i16)?;
+            writer.encode(self.data.len() as i32)?;
+            writer.encode(self.sky_light_sent)?;
+            writer.write_all(&self.data)?;
+            self.chunks
+                .iter()
+                .try_for_each(|chunk| chunk.encode(writer))
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct Explosion {
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+        pub radius: f32,
+
+        #[array(i32, ExplosionRecord)]
+        pub records: Vec<ExplosionRecord>,
+
+        pub player_motion_x: f32,
+        pub player_motion_y: f32,
+        pub player_motion_z: f32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct Effect {
+        pub effect_id: i32,
+        pub x: i32,
+        pub y: i8,
+        pub z: i32,
+        pub data: i32,
+        pub disable_relative_volume: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct SoundEffect {
+        pub sound_name: String,
+        /// Multiplied by 8.
+        pub x: i32,
+        /// Multiplied by 8.
+        pub y: i32,
+        /// Multiplied by 8.
+        pub z: i32,
+        pub volume: f32,
+        /// 63 is 100%.
+        pub pitch: u8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct Particle {
+        pub particle_name: String,
+        pub x: f32,
+        pub y: f32,
+        pub z: f32,
+        pub offset_x: f32,
+        pub offset_y: f32,
+        pub offset_z: f32,
+        pub speed: f32,
+        pub count: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct ChangeGameState {
+        pub reason: u8,
+        pub value: f32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct SpawnGlobalEntity {
+        #[var]
+        pub entity_id: i32,
+        /// Always 1 (thunderbolt).
+        pub kind: i8,
+        pub x: FixedPoint,
+        pub y: FixedPoint,
+        pub z: FixedPoint,
+    }
+
+    /// `entity_id` is only sent for horse windows (inventory type 11), and is decoded as zero otherwise.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct OpenWindow {
+        pub window_id: u8,
+        pub inventory_type: u8,
+        pub window_title: String,
+        pub slot_count: u8,
+        pub use_provided_title: bool,
+        pub entity_id: i32,
+    }
+
+    impl OpenWindow {
+        pub const HORSE_INVENTORY_TYPE: u8 = 11;
+    }
+
+    impl Decode for OpenWindow {
+        fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+            let window_id: u8 = reader.decode()?;
+            let inventory_type: u8 = reader.decode()?;
+
+            Ok(OpenWindow {
+                window_id,
+                inventory_type,
+                window_title: reader.decode()?,
+                slot_count: reader.decode()?,
+                use_provided_title: reader.decode()?,
+                entity_id: if inventory_type == OpenWindow::HORSE_INVENTORY_TYPE {
+                    reader.decode()?
+                } else {
+                    0
+                },
             })
         }
     }
 
-    #[cfg(feature = "async")]
-    impl AsyncEncode for JoinGame {
-        async fn encode(&self, writer: &mut (impl AsyncWriteExt + Unpin + Send)) -> io::Result<()> {
-            writer.encode(self.entity_id).await?;
-            writer
-                .encode(self.gamemode as u8 | (self.hardcore as u8) << 3)
-                .await?;
-            writer.encode(self.dimension).await?;
-            writer.encode(self.difficulty).await?;
-            writer.encode(self.max_players).await?;
-            writer.encode(&self.level_type).await
+    impl Encode for OpenWindow {
+        fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+            writer.encode(self.window_id)?;
+            writer.encode(self.inventory_type)?;
+            writer.encode(&self.window_title)?;
+            writer.encode(self.slot_count)?;
+            writer.encode(self.use_provided_title)?;
+
+            if self.inventory_type == OpenWindow::HORSE_INVENTORY_TYPE {
+                writer.encode(self.entity_id)?;
+            }
+
+            Ok(())
         }
     }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct CloseWindow {
+        pub window_id: u8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct SetSlot {
+        /// -1 for the item held by the cursor.
+        pub window_id: i8,
+        pub slot: i16,
+        pub item: Slot,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct WindowItems {
+        pub window_id: u8,
+
+        #[array(i16, Slot)]
+        pub items: Vec<Slot>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct WindowProperty {
+        pub window_id: u8,
+        pub property: i16,
+        pub value: i16,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct ConfirmTransaction {
+        pub window_id: u8,
+        pub action_number: i16,
+        pub accepted: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct UpdateSign {
+        pub x: i32,
+        pub y: i16,
+        pub z: i32,
+        pub line_1: String,
+        pub line_2: String,
+        pub line_3: String,
+        pub line_4: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct Maps {
+        #[var]
+        pub item_damage: i32,
+
+        #[array(i16, u8)]
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct UpdateBlockEntity {
+        pub x: i32,
+        pub y: i16,
+        pub z: i32,
+        pub action: u8,
+
+        /// Gzipped, prefixed with its length as a short.
+        #[with(decode_gzip_nbt, encode_block_entity_nbt)]
+        pub nbt: Option<Nbt>,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct SignEditorOpen {
+        pub x: i32,
+        pub y: i32,
+        pub z: i32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct Statistics {
+        #[array(var, i32, Statistic)]
+        pub statistics: Vec<Statistic>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct PlayerListItem {
+        pub player_name: String,
+        /// `false` removes the player from the list.
+        pub online: bool,
+        pub ping: i16,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct PlayerAbilities {
+        /// 0x1 god mode, 0x2 flying, 0x4 can fly, 0x8 creative mode.
+        pub flags: i8,
+        pub flying_speed: f32,
+        pub walking_speed: f32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct TabComplete {
+        #[array(var, i32, String)]
+        pub matches: Vec<String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct ScoreboardObjective {
+        pub objective_name: String,
+        pub objective_value: String,
+        /// 0 create, 1 remove, 2 update.
+        pub mode: i8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct UpdateScore {
+        pub item_name: String,
+        pub action: UpdateScoreAction,
+    }
+
+    impl Decode for UpdateScore {
+        fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+            let item_name: String = reader.decode()?;
+            let action: UpdateScoreAction = match i8::decode(reader)? {
+                0 => UpdateScoreAction::Update {
+                    score_name: reader.decode()?,
+                    value: reader.decode()?,
+                },
+                1 => UpdateScoreAction::Remove,
+                action => return Err(DecodeError::InvalidEnumVariant(action.into())),
+            };
+
+            Ok(UpdateScore { item_name, action })
+        }
+    }
+
+    impl Encode for UpdateScore {
+        fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+            writer.encode(&self.item_name)?;
+            match &self.action {
+                UpdateScoreAction::Update { score_name, value } => {
+                    writer.encode(0i8)?;
+                    writer.encode(score_name)?;
+                    writer.encode(*value)
+                }
+                UpdateScoreAction::Remove => writer.encode(1i8),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct DisplayScoreboard {
+        /// 0 list, 1 sidebar, 2 below name.
+        pub position: i8,
+        pub score_name: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Teams {
+        pub team_name: String,
+        pub action: TeamAction,
+    }
+
+    impl Decode for Teams {
+        fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+            let team_name: String = reader.decode()?;
+            let action: TeamAction = match i8::decode(reader)? {
+                0 => TeamAction::Create {
+                    display_name: reader.decode()?,
+                    prefix: reader.decode()?,
+                    suffix: reader.decode()?,
+                    friendly_fire: reader.decode()?,
+                    players: decode_players(reader)?,
+                },
+                1 => TeamAction::Remove,
+                2 => TeamAction::Update {
+                    display_name: reader.decode()?,
+                    prefix: reader.decode()?,
+                    suffix: reader.decode()?,
+                    friendly_fire: reader.decode()?,
+                },
+                3 => TeamAction::AddPlayers(decode_players(reader)?),
+                4 => TeamAction::RemovePlayers(decode_players(reader)?),
+                mode => return Err(DecodeError::InvalidEnumVariant(mode.into())),
+            };
+
+            Ok(Teams { team_name, action })
+        }
+    }
+
+    impl Encode for Teams {
+        fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+            writer.encode(&self.team_name)?;
+            match &self.action {
+                TeamAction::Create {
+                    display_name,
+                    prefix,
+                    suffix,
+                    friendly_fire,
+                    players,
+                } => {
+                    writer.encode(0i8)?;
+                    writer.encode(display_name)?;
+                    writer.encode(prefix)?;
+                    writer.encode(suffix)?;
+                    writer.encode(*friendly_fire)?;
+                    encode_players(players, writer)
+                }
+                TeamAction::Remove => writer.encode(1i8),
+                TeamAction::Update {
+                    display_name,
+                    prefix,
+                    suffix,
+                    friendly_fire,
+                } => {
+                    writer.encode(2i8)?;
+                    writer.encode(display_name)?;
+                    writer.encode(prefix)?;
+                    writer.encode(suffix)?;
+                    writer.encode(*friendly_fire)
+                }
+                TeamAction::AddPlayers(players) => {
+                    writer.encode(3i8)?;
+                    encode_players(players, writer)
+                }
+                TeamAction::RemovePlayers(players) => {
+                    writer.encode(4i8)?;
+                    encode_players(players, writer)
+                }
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct PluginMessage {
+        pub channel: String,
+
+        #[array(i16, u8)]
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct Disconnect {
+        pub reason: TextComponent,
+    }
 }
 
 #[packets(Play)]
 pub mod serverbound {
     use super::*;
 
-    // #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
-    // #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct KeepAlive {
+        pub id: i32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct ChatMessage {
+        #[buf(max_len = 100)]
+        pub message: String,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct UseEntity {
+        pub target: i32,
+        /// 0 for a right click (interact), 1 for a left click (attack).
+        pub mouse: i8,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct Player {
+        pub on_ground: bool,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct PlayerPosition {
+        pub x: f64,
+        pub feet_y: f64,
+        pub head_y: f64,
+        pub z: f64,
+        pub on_ground: bool,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct PlayerLook {
+        pub yaw: f32,
+        pub pitch: f32,
+        pub on_ground: bool,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct PlayerPositionAndLook {
+        pub x: f64,
+        pub feet_y: f64,
+        pub head_y: f64,
+        pub z: f64,
+        pub yaw: f32,
+        pub pitch: f32,
+        pub on_ground: bool,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct PlayerDigging {
+        /// 0 started, 1 cancelled, 2 finished digging, 3 drop item stack, 4 drop item, 5 shoot arrow / finish eating.
+        pub status: i8,
+        pub x: i32,
+        pub y: u8,
+        pub z: i32,
+        pub face: i8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct PlayerBlockPlacement {
+        pub x: i32,
+        pub y: u8,
+        pub z: i32,
+        /// -1 if the player is using the held item instead of placing it.
+        pub direction: i8,
+        pub held_item: Slot,
+        pub cursor_x: i8,
+        pub cursor_y: i8,
+        pub cursor_z: i8,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct HeldItemChange {
+        pub slot: i16,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct Animation {
+        pub entity_id: i32,
+        pub animation: i8,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EntityAction {
+        pub entity_id: i32,
+        /// 1 crouch, 2 uncrouch, 3 leave bed, 4 start sprinting, 5 stop sprinting.
+        pub action_id: i8,
+        /// Only used by horses.
+        pub jump_boost: i32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct SteerVehicle {
+        pub sideways: f32,
+        pub forward: f32,
+        pub jump: bool,
+        pub unmount: bool,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct CloseWindow {
+        pub window_id: i8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct ClickWindow {
+        pub window_id: i8,
+        pub slot: i16,
+        pub button: i8,
+        pub action_number: i16,
+        pub mode: i8,
+        pub clicked_item: Slot,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct ConfirmTransaction {
+        pub window_id: i8,
+        pub action_number: i16,
+        pub accepted: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Decode, Encode)]
+    pub struct CreativeInventoryAction {
+        pub slot: i16,
+        pub clicked_item: Slot,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct EnchantItem {
+        pub window_id: i8,
+        /// The position of the enchantment in the enchantment table window, starting at 0.
+        pub enchantment: i8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct UpdateSign {
+        pub x: i32,
+        pub y: i16,
+        pub z: i32,
+        #[buf(max_len = 15)]
+        pub line_1: String,
+        #[buf(max_len = 15)]
+        pub line_2: String,
+        #[buf(max_len = 15)]
+        pub line_3: String,
+        #[buf(max_len = 15)]
+        pub line_4: String,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Decode, Encode)]
+    pub struct PlayerAbilities {
+        /// 0x1 god mode, 0x2 flying, 0x4 can fly, 0x8 creative mode.
+        pub flags: i8,
+        pub flying_speed: f32,
+        pub walking_speed: f32,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct TabComplete {
+        pub text: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct ClientSettings {
+        #[buf(max_len = 7)]
+        pub locale: String,
+        /// 0 far, 1 normal, 2 short, 3 tiny.
+        pub view_distance: i8,
+        /// 0 enabled, 1 commands only, 2 hidden.
+        pub chat_mode: i8,
+        pub chat_colours: bool,
+        pub difficulty: enums::Difficulty,
+        pub show_cape: bool,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
+    pub struct ClientStatus {
+        /// 0 respawn, 1 request stats, 2 open inventory achievement.
+        pub action_id: i8,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+    pub struct PluginMessage {
+        #[buf(max_len = 20)]
+        pub channel: String,
+
+        #[array(i16, u8)]
+        pub data: Vec<u8>,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ussr_nbt::owned::{Compound, Tag};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{
+        test_utils::round_trip_packet,
+        v1_7_2::types::{ItemStack, MetadataValue},
+        Packet,
+    };
+
+    fn item() -> Slot {
+        ItemStack {
+            id: 276,
+            count: 1,
+            damage: 3,
+            nbt: None,
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_serverbound_ids() {
+        assert_eq!(serverbound::KeepAlive::ID, 0x00);
+        assert_eq!(serverbound::PlayerDigging::ID, 0x07);
+        assert_eq!(serverbound::CloseWindow::ID, 0x0D);
+        assert_eq!(serverbound::UpdateSign::ID, 0x12);
+        assert_eq!(serverbound::PluginMessage::ID, 0x17);
+    }
+
+    #[test]
+    fn test_serverbound_round_trip() {
+        round_trip_packet(serverbound::KeepAlive { id: 123456 });
+        round_trip_packet(serverbound::ChatMessage {
+            message: "Hello, world!".to_owned(),
+        });
+        round_trip_packet(serverbound::UseEntity {
+            target: 42,
+            mouse: 1,
+        });
+        round_trip_packet(serverbound::Player { on_ground: true });
+        round_trip_packet(serverbound::PlayerPosition {
+            x: 12.5,
+            feet_y: 64.0,
+            head_y: 65.62,
+            z: -3.25,
+            on_ground: false,
+        });
+        round_trip_packet(serverbound::PlayerLook {
+            yaw: 90.0,
+            pitch: -45.5,
+            on_ground: true,
+        });
+        round_trip_packet(serverbound::PlayerPositionAndLook {
+            x: -100.0,
+            feet_y: 70.0,
+            head_y: 71.62,
+            z: 250.75,
+            yaw: 180.0,
+            pitch: 10.0,
+            on_ground: true,
+        });
+        round_trip_packet(serverbound::PlayerDigging {
+            status: 2,
+            x: -17,
+            y: 255,
+            z: 300,
+            face: 1,
+        });
+        round_trip_packet(serverbound::PlayerBlockPlacement {
+            x: 10,
+            y: 63,
+            z: -10,
+            direction: 1,
+            held_item: item(),
+            cursor_x: 8,
+            cursor_y: 16,
+            cursor_z: 0,
+        });
+        round_trip_packet(serverbound::PlayerBlockPlacement {
+            x: -1,
+            y: 255,
+            z: -1,
+            direction: -1,
+            held_item: Slot::EMPTY,
+            cursor_x: -1,
+            cursor_y: -1,
+            cursor_z: -1,
+        });
+        round_trip_packet(serverbound::HeldItemChange { slot: 8 });
+        round_trip_packet(serverbound::Animation {
+            entity_id: 7,
+            animation: 1,
+        });
+        round_trip_packet(serverbound::EntityAction {
+            entity_id: 7,
+            action_id: 4,
+            jump_boost: 0,
+        });
+        round_trip_packet(serverbound::SteerVehicle {
+            sideways: 0.98,
+            forward: -0.98,
+            jump: true,
+            unmount: false,
+        });
+        round_trip_packet(serverbound::CloseWindow { window_id: 0 });
+        round_trip_packet(serverbound::ClickWindow {
+            window_id: 1,
+            slot: 36,
+            button: 0,
+            action_number: 12,
+            mode: 0,
+            clicked_item: item(),
+        });
+        round_trip_packet(serverbound::ConfirmTransaction {
+            window_id: 1,
+            action_number: 12,
+            accepted: true,
+        });
+        round_trip_packet(serverbound::CreativeInventoryAction {
+            slot: 36,
+            clicked_item: item(),
+        });
+        round_trip_packet(serverbound::EnchantItem {
+            window_id: 2,
+            enchantment: 1,
+        });
+        round_trip_packet(serverbound::UpdateSign {
+            x: 5,
+            y: 64,
+            z: 5,
+            line_1: "Welcome".to_owned(),
+            line_2: String::new(),
+            line_3: "to the".to_owned(),
+            line_4: "server!".to_owned(),
+        });
+        round_trip_packet(serverbound::PlayerAbilities {
+            flags: 0x2 | 0x4,
+            flying_speed: 0.05,
+            walking_speed: 0.1,
+        });
+        round_trip_packet(serverbound::TabComplete {
+            text: "/gamemode cr".to_owned(),
+        });
+        round_trip_packet(serverbound::ClientSettings {
+            locale: "en_US".to_owned(),
+            view_distance: 1,
+            chat_mode: 0,
+            chat_colours: true,
+            difficulty: enums::Difficulty::Normal,
+            show_cape: true,
+        });
+        round_trip_packet(serverbound::ClientStatus { action_id: 0 });
+        round_trip_packet(serverbound::PluginMessage {
+            channel: "MC|Brand".to_owned(),
+            data: b"\x07vanilla".to_vec(),
+        });
+    }
+
+    #[test]
+    fn test_serverbound_layout() {
+        let mut buf: Vec<u8> = Vec::new();
+        serverbound::PlayPackets::from(serverbound::PlayerDigging {
+            status: 0,
+            x: 1,
+            y: 2,
+            z: -1,
+            face: 5,
+        })
+        .encode(&mut buf)
+        .unwrap();
+        assert_eq!(
+            buf,
+            [0x07, 0x00, 0, 0, 0, 1, 0x02, 0xFF, 0xFF, 0xFF, 0xFF, 0x05]
+        );
+
+        // The chat message limit is counted in characters
+        let mut buf: Vec<u8> = Vec::new();
+        "a".repeat(101).encode(&mut buf).unwrap();
+        assert!(
+            serverbound::PlayPackets::decode(serverbound::ChatMessage::ID, &mut &buf[..]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_clientbound_ids() {
+        assert_eq!(clientbound::KeepAlive::ID, 0x00);
+        assert_eq!(clientbound::SpawnPlayer::ID, 0x0C);
+        assert_eq!(clientbound::EntityMetadata::ID, 0x1C);
+        assert_eq!(clientbound::ChunkData::ID, 0x21);
+        assert_eq!(clientbound::MapChunkBulk::ID, 0x26);
+        assert_eq!(clientbound::OpenWindow::ID, 0x2D);
+        assert_eq!(clientbound::PluginMessage::ID, 0x3F);
+        assert_eq!(clientbound::Disconnect::ID, 0x40);
+    }
+
+    #[test]
+    fn test_clientbound_round_trip() {
+        let metadata = types::EntityMetadata::new()
+            .with_byte(0, 0)
+            .with_short(1, 300)
+            .with_float(6, 20.0)
+            .with_string(10, "Notch");
+
+        round_trip_packet(clientbound::KeepAlive { id: -5 });
+        round_trip_packet(clientbound::JoinGame {
+            entity_id: 1,
+            hardcore: true,
+            gamemode: enums::Gamemode::Survival,
+            dimension: enums::Dimension::Nether,
+            difficulty: enums::Difficulty::Hard,
+            max_players: 20,
+            level_type: "default".to_owned(),
+        });
+        round_trip_packet(clientbound::ChatMessage {
+            message: TextComponent::text("Hello"),
+        });
+        round_trip_packet(clientbound::TimeUpdate {
+            world_age: 123456789,
+            time_of_day: -6000,
+        });
+        round_trip_packet(clientbound::EntityEquipment {
+            entity_id: 3,
+            slot: 0,
+            item: item(),
+        });
+        round_trip_packet(clientbound::SpawnPosition { x: 0, y: 64, z: -8 });
+        round_trip_packet(clientbound::UpdateHealth {
+            health: 19.5,
+            food: 17,
+            food_saturation: 2.5,
+        });
+        round_trip_packet(clientbound::Respawn {
+            dimension: -1,
+            difficulty: enums::Difficulty::Easy,
+            gamemode: enums::Gamemode::Creative,
+            level_type: "flat".to_owned(),
+        });
+        round_trip_packet(clientbound::PlayerPositionAndLook {
+            x: 0.5,
+            y: 65.62,
+            z: 0.5,
+            yaw: 0.0,
+            pitch: 0.0,
+            on_ground: false,
+        });
+        round_trip_packet(clientbound::HeldItemChange { slot: 4 });
+        round_trip_packet(clientbound::UseBed {
+            entity_id: 3,
+            x: 10,
+            y: 64,
+            z: 10,
+        });
+        round_trip_packet(clientbound::Animation {
+            entity_id: 300,
+            animation: 1,
+        });
+        round_trip_packet(clientbound::SpawnPlayer {
+            entity_id: 300,
+            uuid: "069a79f4-44e9-4726-a5be-fca90e38aaf5".to_owned(),
+            name: "Notch".to_owned(),
+            properties: Vec::new(),
+            x: FixedPoint::from_f64(0.5),
+            y: FixedPoint::from_f64(64.0),
+            z: FixedPoint::from_f64(-0.5),
+            yaw: Angle::from_degrees(90.0),
+            pitch: Angle(0),
+            current_item: 0,
+            metadata: metadata.clone(),
+        });
+        round_trip_packet(clientbound::CollectItem {
+            collected_entity_id: 5,
+            collector_entity_id: 3,
+        });
+        round_trip_packet(clientbound::SpawnObject {
+            entity_id: 6,
+            kind: 60,
+            x: FixedPoint(32),
+            y: FixedPoint(2048),
+            z: FixedPoint(-32),
+            pitch: Angle(0),
+            yaw: Angle(64),
+            data: 3,
+            velocity_x: 100,
+            velocity_y: -200,
+            velocity_z: 300,
+        });
+        round_trip_packet(clientbound::SpawnObject {
+            entity_id: 7,
+            kind: 2,
+            x: FixedPoint(0),
+            y: FixedPoint(0),
+            z: FixedPoint(0),
+            pitch: Angle(0),
+            yaw: Angle(0),
+            data: 0,
+            velocity_x: 0,
+            velocity_y: 0,
+            velocity_z: 0,
+        });
+        round_trip_packet(clientbound::SpawnMob {
+            entity_id: 8,
+            kind: 50,
+            x: FixedPoint(320),
+            y: FixedPoint(2048),
+            z: FixedPoint(320),
+            yaw: Angle(10),
+            pitch: Angle(20),
+            head_yaw: Angle(30),
+            velocity_x: 0,
+            velocity_y: -100,
+            velocity_z: 0,
+            metadata: metadata.clone(),
+        });
+        round_trip_packet(clientbound::SpawnPainting {
+            entity_id: 9,
+            title: "Kebab".to_owned(),
+            x: 1,
+            y: 65,
+            z: 1,
+            direction: 2,
+        });
+        round_trip_packet(clientbound::SpawnExperienceOrb {
+            entity_id: 10,
+            x: FixedPoint(0),
+            y: FixedPoint(2048),
+            z: FixedPoint(0),
+            count: 7,
+        });
+        round_trip_packet(clientbound::EntityVelocity {
+            entity_id: 8,
+            velocity_x: 8000,
+            velocity_y: 0,
+            velocity_z: -8000,
+        });
+        round_trip_packet(clientbound::DestroyEntities {
+            entity_ids: vec![6, 7, 8],
+        });
+        round_trip_packet(clientbound::Entity { entity_id: 8 });
+        round_trip_packet(clientbound::EntityRelativeMove {
+            entity_id: 8,
+            dx: ByteFixedPoint(32),
+            dy: ByteFixedPoint(0),
+            dz: ByteFixedPoint(-32),
+        });
+        round_trip_packet(clientbound::EntityLook {
+            entity_id: 8,
+            yaw: Angle(128),
+            pitch: Angle(0),
+        });
+        round_trip_packet(clientbound::EntityLookAndRelativeMove {
+            entity_id: 8,
+            dx: ByteFixedPoint(1),
+            dy: ByteFixedPoint(2),
+            dz: ByteFixedPoint(3),
+            yaw: Angle(4),
+            pitch: Angle(5),
+        });
+        round_trip_packet(clientbound::EntityTeleport {
+            entity_id: 8,
+            x: FixedPoint(-1000),
+            y: FixedPoint(2048),
+            z: FixedPoint(1000),
+            yaw: Angle(0),
+            pitch: Angle(255),
+        });
+        round_trip_packet(clientbound::EntityHeadLook {
+            entity_id: 8,
+            head_yaw: Angle(200),
+        });
+        round_trip_packet(clientbound::EntityStatus {
+            entity_id: 8,
+            status: 2,
+        });
+        round_trip_packet(clientbound::AttachEntity {
+            entity_id: 3,
+            vehicle_id: -1,
+            leash: false,
+        });
+        round_trip_packet(clientbound::EntityMetadata {
+            entity_id: 8,
+            metadata: metadata
+                .clone()
+                .with(12, MetadataValue::Int(-1))
+                .with_slot(13, item()),
+        });
+        round_trip_packet(clientbound::EntityEffect {
+            entity_id: 3,
+            effect_id: 1,
+            amplifier: 0,
+            duration: 600,
+        });
+        round_trip_packet(clientbound::RemoveEntityEffect {
+            entity_id: 3,
+            effect_id: 1,
+        });
+        round_trip_packet(clientbound::SetExperience {
+            experience_bar: 0.5,
+            level: 3,
+            total_experience: 30,
+        });
+        round_trip_packet(clientbound::EntityProperties {
+            entity_id: 3,
+            properties: vec![
+                EntityProperty {
+                    key: "generic.movementSpeed".to_owned(),
+                    value: 0.1,
+                    modifiers: vec![AttributeModifier {
+                        uuid: Uuid::from_u128(0x662A6B8D_DA3E_4C1C_8813_96EA6097278D),
+                        amount: 0.3,
+                        operation: 2,
+                    }],
+                },
+                EntityProperty {
+                    key: "generic.maxHealth".to_owned(),
+                    value: 20.0,
+                    modifiers: vec![],
+                },
+            ],
+        });
+        round_trip_packet(clientbound::ChunkData {
+            chunk_x: 1,
+            chunk_z: -1,
+            ground_up_continuous: true,
+            primary_bit_map: 0b1111,
+            add_bit_map: 0,
+            data: vec![0x78, 0x9C, 0x03, 0x00, 0x00, 0x00, 0x00, 0x01],
+        });
+        round_trip_packet(clientbound::MultiBlockChange {
+            chunk_x: 2,
+            chunk_z: 3,
+            records: vec![
+                BlockChangeRecord {
+                    x: 15,
+                    z: 0,
+                    y: 255,
+                    block_id: 4095,
+                    metadata: 15,
+                },
+                BlockChangeRecord {
+                    x: 1,
+                    z: 2,
+                    y: 64,
+                    block_id: 35,
+                    metadata: 14,
+                },
+            ],
+        });
+        round_trip_packet(clientbound::BlockChange {
+            x: 100,
+            y: 70,
+            z: -100,
+            block_id: 1,
+            block_metadata: 0,
+        });
+        round_trip_packet(clientbound::BlockAction {
+            x: 0,
+            y: 64,
+            z: 0,
+            byte_1: 1,
+            byte_2: 1,
+            block_id: 54,
+        });
+        round_trip_packet(clientbound::BlockBreakAnimation {
+            entity_id: 3,
+            x: 5,
+            y: 64,
+            z: 5,
+            destroy_stage: 4,
+        });
+        round_trip_packet(clientbound::MapChunkBulk {
+            sky_light_sent: true,
+            data: vec![1, 2, 3, 4, 5],
+            chunks: vec![
+                ChunkMeta {
+                    chunk_x: 0,
+                    chunk_z: 0,
+                    primary_bit_map: 0x00FF,
+                    add_bit_map: 0,
+                },
+                ChunkMeta {
+                    chunk_x: -1,
+                    chunk_z: 0,
+                    primary_bit_map: 0x000F,
+                    add_bit_map: 0,
+                },
+            ],
+        });
+        round_trip_packet(clientbound::Explosion {
+            x: 0.0,
+            y: 64.0,
+            z: 0.0,
+            radius: 4.0,
+            records: vec![
+                ExplosionRecord { x: 0, y: -1, z: 0 },
+                ExplosionRecord { x: 1, y: 0, z: -1 },
+            ],
+            player_motion_x: 0.1,
+            player_motion_y: 0.2,
+            player_motion_z: 0.3,
+        });
+        round_trip_packet(clientbound::Effect {
+            effect_id: 2001,
+            x: 10,
+            y: 64,
+            z: 10,
+            data: 1,
+            disable_relative_volume: false,
+        });
+        round_trip_packet(clientbound::SoundEffect {
+            sound_name: "random.click".to_owned(),
+            x: 80,
+            y: 512,
+            z: 80,
+            volume: 1.0,
+            pitch: 63,
+        });
+        round_trip_packet(clientbound::Particle {
+            particle_name: "heart".to_owned(),
+            x: 0.5,
+            y: 65.0,
+            z: 0.5,
+            offset_x: 0.1,
+            offset_y: 0.1,
+            offset_z: 0.1,
+            speed: 0.0,
+            count: 5,
+        });
+        round_trip_packet(clientbound::ChangeGameState {
+            reason: 3,
+            value: 1.0,
+        });
+        round_trip_packet(clientbound::SpawnGlobalEntity {
+            entity_id: 11,
+            kind: 1,
+            x: FixedPoint(64),
+            y: FixedPoint(2048),
+            z: FixedPoint(64),
+        });
+        round_trip_packet(clientbound::OpenWindow {
+            window_id: 1,
+            inventory_type: 0,
+            window_title: "Chest".to_owned(),
+            slot_count: 27,
+            use_provided_title: true,
+            entity_id: 0,
+        });
+        round_trip_packet(clientbound::OpenWindow {
+            window_id: 2,
+            inventory_type: clientbound::OpenWindow::HORSE_INVENTORY_TYPE,
+            window_title: "Horse".to_owned(),
+            slot_count: 2,
+            use_provided_title: false,
+            entity_id: 12,
+        });
+        round_trip_packet(clientbound::CloseWindow { window_id: 1 });
+        round_trip_packet(clientbound::SetSlot {
+            window_id: -1,
+            slot: -1,
+            item: Slot::EMPTY,
+        });
+        round_trip_packet(clientbound::WindowItems {
+            window_id: 0,
+            items: vec![Slot::EMPTY, item(), Slot::EMPTY],
+        });
+        round_trip_packet(clientbound::WindowProperty {
+            window_id: 3,
+            property: 0,
+            value: 200,
+        });
+        round_trip_packet(clientbound::ConfirmTransaction {
+            window_id: 1,
+            action_number: 12,
+            accepted: false,
+        });
+        round_trip_packet(clientbound::UpdateSign {
+            x: 5,
+            y: 64,
+            z: 5,
+            line_1: "Welcome".to_owned(),
+            line_2: String::new(),
+            line_3: "to the".to_owned(),
+            line_4: "server!".to_owned(),
+        });
+        round_trip_packet(clientbound::Maps {
+            item_damage: 0,
+            data: vec![0, 0, 0, 0],
+        });
+        round_trip_packet(clientbound::UpdateBlockEntity {
+            x: 5,
+            y: 64,
+            z: 5,
+            action: 1,
+            nbt: None,
+        });
+        round_trip_packet(clientbound::UpdateBlockEntity {
+            x: 5,
+            y: 64,
+            z: 5,
+            action: 1,
+            nbt: Some(Nbt {
+                name: String::new(),
+                compound: Compound {
+                    tags: vec![
+                        ("id".to_owned(), Tag::String("MobSpawner".to_owned())),
+                        ("Delay".to_owned(), Tag::Short(20)),
+                    ],
+                },
+            }),
+        });
+        round_trip_packet(clientbound::SignEditorOpen { x: 5, y: 64, z: 5 });
+        round_trip_packet(clientbound::Statistics {
+            statistics: vec![
+                Statistic {
+                    name: "stat.leaveGame".to_owned(),
+                    value: 1,
+                },
+                Statistic {
+                    name: "stat.playOneMinute".to_owned(),
+                    value: 72000,
+                },
+            ],
+        });
+        round_trip_packet(clientbound::PlayerListItem {
+            player_name: "Notch".to_owned(),
+            online: true,
+            ping: 42,
+        });
+        round_trip_packet(clientbound::PlayerAbilities {
+            flags: 0x1 | 0x4 | 0x8,
+            flying_speed: 0.05,
+            walking_speed: 0.1,
+        });
+        round_trip_packet(clientbound::TabComplete {
+            matches: vec!["/gamemode".to_owned(), "/gamerule".to_owned()],
+        });
+        round_trip_packet(clientbound::ScoreboardObjective {
+            objective_name: "kills".to_owned(),
+            objective_value: "Kills".to_owned(),
+            mode: 0,
+        });
+        round_trip_packet(clientbound::UpdateScore {
+            item_name: "Notch".to_owned(),
+            action: UpdateScoreAction::Update {
+                score_name: "kills".to_owned(),
+                value: 3,
+            },
+        });
+        round_trip_packet(clientbound::UpdateScore {
+            item_name: "Notch".to_owned(),
+            action: UpdateScoreAction::Remove,
+        });
+        round_trip_packet(clientbound::DisplayScoreboard {
+            position: 1,
+            score_name: "kills".to_owned(),
+        });
+        for action in [
+            TeamAction::Create {
+                display_name: "Red".to_owned(),
+                prefix: "§c".to_owned(),
+                suffix: "§r".to_owned(),
+                friendly_fire: 1,
+                players: vec!["Notch".to_owned(), "jeb_".to_owned()],
+            },
+            TeamAction::Remove,
+            TeamAction::Update {
+                display_name: "Blue".to_owned(),
+                prefix: "§9".to_owned(),
+                suffix: "§r".to_owned(),
+                friendly_fire: 0,
+            },
+            TeamAction::AddPlayers(vec!["Dinnerbone".to_owned()]),
+            TeamAction::RemovePlayers(vec![]),
+        ] {
+            round_trip_packet(clientbound::Teams {
+                team_name: "red".to_owned(),
+                action,
+            });
+        }
+        round_trip_packet(clientbound::PluginMessage {
+            channel: "MC|Brand".to_owned(),
+            data: b"\x04ussr".to_vec(),
+        });
+        round_trip_packet(clientbound::Disconnect {
+            reason: TextComponent::text("Bye"),
+        });
+    }
+
+    #[test]
+    fn test_clientbound_layout() {
+        let encode = |packet: clientbound::PlayPackets| {
+            let mut buf: Vec<u8> = Vec::new();
+            packet.encode(&mut buf).unwrap();
+            buf
+        };
+
+        // The hardcore flag is bit 3 of the gamemode
+        let join_game = encode(
+            clientbound::JoinGame {
+                entity_id: 1,
+                hardcore: true,
+                gamemode: enums::Gamemode::Creative,
+                dimension: enums::Dimension::Overworld,
+                difficulty: enums::Difficulty::Normal,
+                max_players: 8,
+                level_type: "flat".to_owned(),
+            }
+            .into(),
+        );
+        assert_eq!(
+            join_game,
+            [0x01, 0, 0, 0, 1, 0x09, 0x00, 0x02, 0x08, 0x04, b'f', b'l', b'a', b't']
+        );
+
+        // The velocity is left out if the object data is zero
+        let spawn_object = encode(
+            clientbound::SpawnObject {
+                entity_id: 1,
+                kind: 2,
+                x: FixedPoint(0),
+                y: FixedPoint(0),
+                z: FixedPoint(0),
+                pitch: Angle(0),
+                yaw: Angle(0),
+                data: 0,
+                velocity_x: 0,
+                velocity_y: 0,
+                velocity_z: 0,
+            }
+            .into(),
+        );
+        assert_eq!(spawn_object.len(), 1 + 1 + 1 + 12 + 2 + 4);
+
+        let multi_block_change = encode(
+            clientbound::MultiBlockChange {
+                chunk_x: 0,
+                chunk_z: 0,
+                records: vec![BlockChangeRecord {
+                    x: 1,
+                    z: 2,
+                    y: 3,
+                    block_id: 4,
+                    metadata: 5,
+                }],
+            }
+            .into(),
+        );
+        assert_eq!(
+            multi_block_change[9..],
+            [0x00, 0x01, 0, 0, 0, 4, 0x12, 0x03, 0x00, 0x45]
+        );
+
+        // The data size of a multi block change must match the record count
+        let mut buf: Vec<u8> = multi_block_change[1..].to_vec();
+        buf[13] = 8;
+        assert!(
+            clientbound::PlayPackets::decode(clientbound::MultiBlockChange::ID, &mut &buf[..])
+                .is_err()
+        );
+    }
 }
@@ -5,14 +5,11 @@ pub mod clientbound {
     use super::*;
 
     #[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct StatusResponse {
-        /// JSON
-        pub response: String,
+        pub response: ServerStatus,
     }
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct PingResponse {
         pub payload: u64,
     }
@@ -23,11 +20,9 @@ pub mod serverbound {
     use super::*;
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct StatusRequest {}
 
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Decode, Encode)]
-    #[cfg_attr(feature = "async", derive(AsyncDecode, AsyncEncode))]
     pub struct PingRequest {
         pub payload: u64,
     }
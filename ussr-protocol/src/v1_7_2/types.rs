@@ -1 +1,307 @@
+use std::io::{self, Read, Write};
 
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use ussr_buf::{decode_string, Decode, DecodeError, Encode, MAX_STRING_LENGTH};
+use ussr_nbt::owned::Nbt;
+
+use crate::common::{self, MetadataValueCodec};
+pub use crate::common::{Angle, ByteFixedPoint, FixedPoint, ItemStack, PackedPosition, Position};
+
+/// An inventory slot, which might be empty.
+///
+/// An empty slot is encoded as an item id of `-1`.
+/// Otherwise, the id is followed by the count, the damage and a gzipped NBT compound prefixed with its `i16` length,
+/// where a length of `-1` means there is no NBT.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Slot(pub Option<ItemStack>);
+
+impl Slot {
+    pub const EMPTY: Slot = Slot(None);
+
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+impl From<ItemStack> for Slot {
+    fn from(item: ItemStack) -> Self {
+        Slot(Some(item))
+    }
+}
+
+impl Decode for Slot {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let id: i16 = reader.read_i16::<BE>()?;
+        if id < 0 {
+            return Ok(Slot::EMPTY);
+        }
+
+        Ok(Slot(Some(ItemStack {
+            id,
+            count: reader.read_u8()?,
+            damage: reader.read_i16::<BE>()?,
+            nbt: decode_gzip_nbt(reader)?,
+        })))
+    }
+}
+
+impl Encode for Slot {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        let Some(item) = &self.0 else {
+            return writer.write_i16::<BE>(-1);
+        };
+
+        writer.write_i16::<BE>(item.id)?;
+        writer.write_u8(item.count)?;
+        writer.write_i16::<BE>(item.damage)?;
+        encode_gzip_nbt(item.nbt.as_ref(), writer)
+    }
+}
+
+/// Decodes an NBT compound that is gzipped and prefixed with its `i16` length.
+/// A negative length means there is no NBT.
+pub fn decode_gzip_nbt(reader: &mut impl Read) -> Result<Option<Nbt>, DecodeError> {
+    let length: i16 = reader.read_i16::<BE>()?;
+    if length < 0 {
+        return Ok(None);
+    }
+
+    let mut bytes: Vec<u8> = vec![0; length as usize];
+    reader.read_exact(&mut bytes)?;
+
+    Ok(Some(Nbt::read(&mut GzDecoder::new(&bytes[..]))?))
+}
+
+/// Encodes an NBT compound gzipped and prefixed with its `i16` length.
+/// `None` is encoded as a length of `-1`.
+pub fn encode_gzip_nbt(nbt: Option<&Nbt>, writer: &mut impl Write) -> io::Result<()> {
+    let Some(nbt) = nbt else {
+        return writer.write_i16::<BE>(-1);
+    };
+
+    let mut encoder: GzEncoder<Vec<u8>> = GzEncoder::new(Vec::new(), Compression::default());
+    nbt.write(&mut encoder)?;
+    let bytes: Vec<u8> = encoder.finish()?;
+
+    let length: i16 = bytes
+        .len()
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "NBT is too large"))?;
+    writer.write_i16::<BE>(length)?;
+    writer.write_all(&bytes)
+}
+
+/// A property of a game profile, such as the skin.
+/// Sent by [`SpawnPlayer`](super::packets::play::clientbound::SpawnPlayer) since 1.7.6.
+#[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: String,
+}
+
+/// A single value in [`EntityMetadata`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Float(f32),
+    String(String),
+    Slot(Slot),
+    Position(Position),
+}
+
+impl MetadataValue {
+    /// The type id of the value, which is stored in the upper 3 bits of the entry header.
+    pub const fn type_id(&self) -> u8 {
+        match self {
+            MetadataValue::Byte(_) => 0,
+            MetadataValue::Short(_) => 1,
+            MetadataValue::Int(_) => 2,
+            MetadataValue::Float(_) => 3,
+            MetadataValue::String(_) => 4,
+            MetadataValue::Slot(_) => 5,
+            MetadataValue::Position(_) => 6,
+        }
+    }
+}
+
+impl MetadataValueCodec for MetadataValue {
+    fn type_id(&self) -> u8 {
+        MetadataValue::type_id(self)
+    }
+
+    fn decode_value(type_id: u8, reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(match type_id {
+            0 => MetadataValue::Byte(reader.read_i8()?),
+            1 => MetadataValue::Short(reader.read_i16::<BE>()?),
+            2 => MetadataValue::Int(reader.read_i32::<BE>()?),
+            3 => MetadataValue::Float(reader.read_f32::<BE>()?),
+            4 => MetadataValue::String(decode_string(reader, MAX_STRING_LENGTH)?),
+            5 => MetadataValue::Slot(Slot::decode(reader)?),
+            6 => MetadataValue::Position(Position::decode(reader)?),
+            type_id => return Err(DecodeError::InvalidEnumVariant(type_id.into())),
+        })
+    }
+
+    fn encode_value(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            MetadataValue::Byte(value) => writer.write_i8(*value),
+            MetadataValue::Short(value) => writer.write_i16::<BE>(*value),
+            MetadataValue::Int(value) => writer.write_i32::<BE>(*value),
+            MetadataValue::Float(value) => writer.write_f32::<BE>(*value),
+            MetadataValue::String(value) => value.encode(writer),
+            MetadataValue::Slot(value) => value.encode(writer),
+            MetadataValue::Position(value) => value.encode(writer),
+        }
+    }
+}
+
+/// Entity metadata with the value types of 1.7.
+pub type EntityMetadata = common::EntityMetadata<MetadataValue>;
+
+impl EntityMetadata {
+    pub fn with_byte(self, index: u8, value: i8) -> EntityMetadata {
+        self.with(index, MetadataValue::Byte(value))
+    }
+
+    pub fn with_short(self, index: u8, value: i16) -> EntityMetadata {
+        self.with(index, MetadataValue::Short(value))
+    }
+
+    pub fn with_int(self, index: u8, value: i32) -> EntityMetadata {
+        self.with(index, MetadataValue::Int(value))
+    }
+
+    pub fn with_float(self, index: u8, value: f32) -> EntityMetadata {
+        self.with(index, MetadataValue::Float(value))
+    }
+
+    pub fn with_string(self, index: u8, value: impl Into<String>) -> EntityMetadata {
+        self.with(index, MetadataValue::String(value.into()))
+    }
+
+    pub fn with_slot(self, index: u8, value: impl Into<Slot>) -> EntityMetadata {
+        self.with(index, MetadataValue::Slot(value.into()))
+    }
+
+    pub fn with_position(self, index: u8, value: Position) -> EntityMetadata {
+        self.with(index, MetadataValue::Position(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ussr_nbt::owned::{Compound, Tag};
+
+    use super::*;
+    use crate::test_utils::round_trip;
+
+    fn item_with_nbt() -> Slot {
+        Slot::from(ItemStack {
+            id: 276,
+            count: 1,
+            damage: 3,
+            nbt: Some(Nbt {
+                name: String::new(),
+                compound: Compound {
+                    tags: vec![("RepairCost".to_owned(), Tag::Int(2))],
+                },
+            }),
+        })
+    }
+
+    #[test]
+    fn test_slot() {
+        assert_eq!(round_trip(Slot::EMPTY), [0xFF, 0xFF]);
+        assert_eq!(
+            round_trip(Slot::from(ItemStack {
+                id: 1,
+                count: 64,
+                damage: 0,
+                nbt: None,
+            })),
+            [0, 1, 64, 0, 0, 0xFF, 0xFF]
+        );
+        round_trip(item_with_nbt());
+    }
+
+    #[test]
+    fn test_entity_metadata() {
+        // The default entries of a player, encoded by hand from the metadata format.
+        #[rustfmt::skip]
+        let player: &[u8] = &[
+            0x00, 0x00,
+            0x21, 0x01, 0x2C,
+            0x66, 0x41, 0xA0, 0x00, 0x00,
+            0x47, 0x00, 0x00, 0x00, 0x00,
+            0x08, 0x00,
+            0x09, 0x00,
+            0x10, 0x00,
+            0x71, 0x00, 0x00, 0x00, 0x00,
+            0x52, 0x00, 0x00, 0x00, 0x00,
+            0x7F,
+        ];
+        let metadata = EntityMetadata::new()
+            .with_byte(0, 0)
+            .with_short(1, 300)
+            .with_float(6, 20.0)
+            .with_int(7, 0)
+            .with_byte(8, 0)
+            .with_byte(9, 0)
+            .with_byte(16, 0)
+            .with_float(17, 0.0)
+            .with_int(18, 0);
+        assert_eq!(round_trip(metadata.clone()), player);
+        assert_eq!(EntityMetadata::decode(&mut &player[..]).unwrap(), metadata);
+
+        // A dropped stone item.
+        #[rustfmt::skip]
+        let item: &[u8] = &[
+            0x00, 0x00,
+            0x21, 0x01, 0x2C,
+            0xAA, 0x00, 0x01, 0x01, 0x00, 0x00, 0xFF, 0xFF,
+            0x7F,
+        ];
+        let metadata = EntityMetadata::new()
+            .with_byte(0, 0)
+            .with_short(1, 300)
+            .with_slot(
+                10,
+                ItemStack {
+                    id: 1,
+                    count: 1,
+                    damage: 0,
+                    nbt: None,
+                },
+            );
+        assert_eq!(round_trip(metadata), item);
+
+        let metadata = EntityMetadata::new()
+            .with_string(10, "Grumm")
+            .with_position(2, Position::new(1, 2, 3))
+            .with_byte(10, 1);
+        assert_eq!(
+            round_trip(metadata.clone()),
+            [0xC2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0x0A, 1, 0x7F]
+        );
+        assert_eq!(metadata.get(10), Some(&MetadataValue::Byte(1)));
+        assert_eq!(round_trip(EntityMetadata::new()), [0x7F]);
+    }
+
+    #[test]
+    fn test_entity_metadata_invalid() {
+        // Type 7 does not exist in 1.7.2.
+        assert!(matches!(
+            EntityMetadata::decode(&mut &[0xE0, 0, 0, 0, 0, 0x7F][..]),
+            Err(DecodeError::InvalidEnumVariant(7))
+        ));
+        // Missing terminator.
+        assert!(matches!(
+            EntityMetadata::decode(&mut &[0x00, 0x00][..]),
+            Err(DecodeError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,246 @@
+//! Conversions between 1.8.9 packets and [`neutral`] packets.
+
+use std::io::{self, Read, Write};
+
+use super::packets::{handshaking, login, play, status};
+use crate::{common::PackedPosition, neutral, PacketDecodeError, State};
+
+pub(crate) fn decode_serverbound(
+    state: State,
+    id: u32,
+    reader: &mut impl Read,
+) -> Result<neutral::Serverbound, PacketDecodeError> {
+    use neutral::Serverbound as N;
+
+    Ok(match state {
+        State::Handshaking => {
+            match handshaking::serverbound::HandshakingPackets::decode(id, reader)? {
+                handshaking::serverbound::HandshakingPackets::Handshake(packet) => N::Handshake {
+                    protocol_version: packet.protocol_version,
+                    server_address: packet.server_address,
+                    server_port: packet.server_port,
+                    next_state: packet.next_state,
+                },
+            }
+        }
+
+        State::Status => {
+            use status::serverbound::StatusPackets as P;
+
+            match P::decode(id, reader)? {
+                P::StatusRequest(_) => N::StatusRequest,
+                P::PingRequest(packet) => N::PingRequest {
+                    payload: packet.payload,
+                },
+            }
+        }
+
+        State::Login => {
+            use login::serverbound::LoginPackets as P;
+
+            match P::decode(id, reader)? {
+                P::LoginStart(packet) => N::LoginStart {
+                    username: packet.username,
+                },
+                P::EncryptionResponse(packet) => N::EncryptionResponse {
+                    shared_secret: packet.shared_secret,
+                    verify_token: packet.verify_token,
+                },
+            }
+        }
+
+        State::Play => {
+            use play::serverbound::PlayPackets as P;
+
+            match P::decode(id, reader)? {
+                P::KeepAlive(packet) => N::KeepAlive { id: packet.id },
+                P::ChatMessage(packet) => N::ChatMessage {
+                    message: packet.message,
+                },
+                P::Player(packet) => N::Player {
+                    on_ground: packet.on_ground,
+                },
+                P::PlayerPosition(packet) => N::PlayerPosition {
+                    x: packet.x,
+                    y: packet.feet_y,
+                    z: packet.z,
+                    on_ground: packet.on_ground,
+                },
+                P::PlayerLook(packet) => N::PlayerLook {
+                    yaw: packet.yaw,
+                    pitch: packet.pitch,
+                    on_ground: packet.on_ground,
+                },
+                P::PlayerPositionAndLook(packet) => N::PlayerPositionAndLook {
+                    x: packet.x,
+                    y: packet.feet_y,
+                    z: packet.z,
+                    yaw: packet.yaw,
+                    pitch: packet.pitch,
+                    on_ground: packet.on_ground,
+                },
+                P::HeldItemChange(packet) => N::HeldItemChange { slot: packet.slot },
+                P::ClientSettings(packet) => N::ClientSettings {
+                    locale: packet.locale,
+                    view_distance: packet.view_distance,
+                    chat_mode: packet.chat_mode,
+                    chat_colours: packet.chat_colours,
+                    show_cape: packet.displayed_skin_parts & 0x01 != 0,
+                },
+                P::ClientStatus(packet) => N::ClientStatus {
+                    action_id: packet.action_id,
+                },
+                P::PluginMessage(packet) => N::PluginMessage {
+                    channel: packet.channel,
+                    data: packet.data,
+                },
+                packet => N::Other(neutral::OtherServerbound::V1_8_9(Box::new(packet))),
+            }
+        }
+    })
+}
+
+pub(crate) fn encode_clientbound(
+    packet: &neutral::Clientbound,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    use neutral::Clientbound as N;
+
+    match packet {
+        N::StatusResponse { response } => {
+            status::clientbound::StatusPackets::from(status::clientbound::StatusResponse {
+                response: response.clone(),
+            })
+            .encode(writer)
+        }
+        N::PingResponse { payload } => {
+            status::clientbound::StatusPackets::from(status::clientbound::PingResponse {
+                payload: *payload,
+            })
+            .encode(writer)
+        }
+
+        N::LoginDisconnect { reason } => {
+            login::clientbound::LoginPackets::from(login::clientbound::Disconnect {
+                reason: reason.clone(),
+            })
+            .encode(writer)
+        }
+        N::EncryptionRequest {
+            server_id,
+            public_key,
+            verify_token,
+        } => login::clientbound::LoginPackets::from(login::clientbound::EncryptionRequest {
+            server_id: server_id.clone(),
+            public_key: public_key.clone(),
+            verify_token: verify_token.clone(),
+        })
+        .encode(writer),
+        N::LoginSuccess { uuid, username } => {
+            login::clientbound::LoginPackets::from(login::clientbound::LoginSuccess {
+                uuid: uuid.hyphenated().to_string(),
+                username: username.clone(),
+            })
+            .encode(writer)
+        }
+        N::SetCompression { threshold } => {
+            login::clientbound::LoginPackets::from(login::clientbound::SetCompression {
+                threshold: *threshold,
+            })
+            .encode(writer)
+        }
+
+        N::KeepAlive { id } => encode_play(play::clientbound::KeepAlive { id: *id }, writer),
+        N::JoinGame {
+            entity_id,
+            hardcore,
+            gamemode,
+            dimension,
+            difficulty,
+            max_players,
+            level_type,
+            reduced_debug_info,
+        } => encode_play(
+            play::clientbound::JoinGame {
+                entity_id: *entity_id,
+                hardcore: *hardcore,
+                gamemode: *gamemode,
+                dimension: *dimension,
+                difficulty: *difficulty,
+                max_players: *max_players,
+                level_type: level_type.clone(),
+                reduced_debug_info: *reduced_debug_info,
+            },
+            writer,
+        ),
+        N::ChatMessage { message, position } => encode_play(
+            play::clientbound::ChatMessage {
+                message: message.clone(),
+                position: *position,
+            },
+            writer,
+        ),
+        N::TimeUpdate {
+            world_age,
+            time_of_day,
+        } => encode_play(
+            play::clientbound::TimeUpdate {
+                world_age: *world_age,
+                time_of_day: *time_of_day,
+            },
+            writer,
+        ),
+        N::SpawnPosition { x, y, z } => encode_play(
+            play::clientbound::SpawnPosition {
+                location: PackedPosition::new(*x, *y, *z),
+            },
+            writer,
+        ),
+        N::PlayerPositionAndLook {
+            x,
+            y,
+            z,
+            yaw,
+            pitch,
+        } => encode_play(
+            play::clientbound::PlayerPositionAndLook {
+                x: *x,
+                y: *y,
+                z: *z,
+                yaw: *yaw,
+                pitch: *pitch,
+                flags: 0,
+            },
+            writer,
+        ),
+        N::HeldItemChange { slot } => {
+            encode_play(play::clientbound::HeldItemChange { slot: *slot }, writer)
+        }
+        N::PluginMessage { channel, data } => encode_play(
+            play::clientbound::PluginMessage {
+                channel: channel.clone(),
+                data: data.clone(),
+            },
+            writer,
+        ),
+        N::Disconnect { reason } => encode_play(
+            play::clientbound::Disconnect {
+                reason: reason.clone(),
+            },
+            writer,
+        ),
+        N::Other(neutral::OtherClientbound::V1_8_9(packet)) => packet.encode(writer),
+        #[cfg(feature = "v1_7_2")]
+        N::Other(neutral::OtherClientbound::V1_7_2(_)) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "a 1.7 packet can't be sent to a 1.8.9 connection",
+        )),
+    }
+}
+
+fn encode_play(
+    packet: impl Into<play::clientbound::PlayPackets>,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    packet.into().encode(writer)
+}
@@ -0,0 +1,5 @@
+pub(crate) mod convert;
+pub mod packets;
+pub mod types;
+
+pub use crate::enums;
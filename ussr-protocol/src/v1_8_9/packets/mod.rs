@@ -0,0 +1,487 @@
+//! Generated from `protocol/v1_8_9.json` by the build script.
+
+use std::io::{self, Read, Write};
+
+use ussr_buf::{decode_array, read_bytes, Decode, DecodeError, DecodeExt, Encode, EncodeExt};
+use ussr_protocol_macros::packets;
+
+use super::{
+    enums,
+    types::{MapIcon, MapUpdate, UpdateScoreAction},
+};
+use crate::{ServerStatus, TextComponent};
+
+include!(concat!(env!("OUT_DIR"), "/v1_8_9.rs"));
+
+impl_debug_packet!();
+
+impl Decode for play::clientbound::JoinGame {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let entity_id: i32 = reader.decode()?;
+        let gamemode: u8 = reader.decode()?;
+        let hardcore: bool = gamemode & 0x8 != 0;
+        let gamemode: enums::Gamemode = (gamemode & !0x8).try_into()?;
+
+        Ok(play::clientbound::JoinGame {
+            entity_id,
+            hardcore,
+            gamemode,
+            dimension: reader.decode()?,
+            difficulty: reader.decode()?,
+            max_players: reader.decode()?,
+            level_type: reader.decode()?,
+            reduced_debug_info: reader.decode()?,
+        })
+    }
+}
+
+impl Encode for play::clientbound::JoinGame {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.encode(self.entity_id)?;
+        writer.encode(self.gamemode as u8 | (self.hardcore as u8) << 3)?;
+        writer.encode(self.dimension)?;
+        writer.encode(self.difficulty)?;
+        writer.encode(self.max_players)?;
+        writer.encode(&self.level_type)?;
+        writer.encode(self.reduced_debug_info)
+    }
+}
+
+impl Decode for play::clientbound::SpawnObject {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let mut packet = play::clientbound::SpawnObject {
+            entity_id: reader.var_decode()?,
+            kind: reader.decode()?,
+            x: reader.decode()?,
+            y: reader.decode()?,
+            z: reader.decode()?,
+            pitch: reader.decode()?,
+            yaw: reader.decode()?,
+            data: reader.decode()?,
+            velocity_x: 0,
+            velocity_y: 0,
+            velocity_z: 0,
+        };
+
+        if packet.data > 0 {
+            packet.velocity_x = reader.decode()?;
+            packet.velocity_y = reader.decode()?;
+            packet.velocity_z = reader.decode()?;
+        }
+
+        Ok(packet)
+    }
+}
+
+impl Encode for play::clientbound::SpawnObject {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.var_encode(self.entity_id)?;
+        writer.encode(self.kind)?;
+        writer.encode(self.x)?;
+        writer.encode(self.y)?;
+        writer.encode(self.z)?;
+        writer.encode(self.pitch)?;
+        writer.encode(self.yaw)?;
+        writer.encode(self.data)?;
+
+        if self.data > 0 {
+            writer.encode(self.velocity_x)?;
+            writer.encode(self.velocity_y)?;
+            writer.encode(self.velocity_z)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl play::clientbound::Particle {
+    /// The number of arguments of a particle: 2 for `iconcrack`, 1 for `blockcrack` and `blockdust`, 0 for the rest.
+    pub const fn argument_count(particle_id: i32) -> usize {
+        match particle_id {
+            36 => 2,
+            37 | 38 => 1,
+            _ => 0,
+        }
+    }
+}
+
+impl Decode for play::clientbound::Particle {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let particle_id: i32 = reader.decode()?;
+
+        Ok(play::clientbound::Particle {
+            particle_id,
+            long_distance: reader.decode()?,
+            x: reader.decode()?,
+            y: reader.decode()?,
+            z: reader.decode()?,
+            offset_x: reader.decode()?,
+            offset_y: reader.decode()?,
+            offset_z: reader.decode()?,
+            speed: reader.decode()?,
+            count: reader.decode()?,
+            arguments: decode_array(
+                reader,
+                play::clientbound::Particle::argument_count(particle_id),
+                |reader| reader.var_decode(),
+            )?,
+        })
+    }
+}
+
+impl Encode for play::clientbound::Particle {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        let count: usize = play::clientbound::Particle::argument_count(self.particle_id);
+        if self.arguments.len() != count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "particle {} takes {count} arguments, not {}",
+                    self.particle_id,
+                    self.arguments.len()
+                ),
+            ));
+        }
+
+        writer.encode(self.particle_id)?;
+        writer.encode(self.long_distance)?;
+        writer.encode(self.x)?;
+        writer.encode(self.y)?;
+        writer.encode(self.z)?;
+        writer.encode(self.offset_x)?;
+        writer.encode(self.offset_y)?;
+        writer.encode(self.offset_z)?;
+        writer.encode(self.speed)?;
+        writer.encode(self.count)?;
+        self.arguments
+            .iter()
+            .try_for_each(|argument| writer.var_encode(argument))
+    }
+}
+
+impl play::clientbound::OpenWindow {
+    pub const HORSE_WINDOW_TYPE: &'static str = "EntityHorse";
+}
+
+impl Decode for play::clientbound::OpenWindow {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let window_id: u8 = reader.decode()?;
+        let window_type: String = reader.decode()?;
+        let window_title: TextComponent = reader.decode()?;
+        let slot_count: u8 = reader.decode()?;
+        let entity_id: i32 = if window_type == play::clientbound::OpenWindow::HORSE_WINDOW_TYPE {
+            reader.decode()?
+        } else {
+            0
+        };
+
+        Ok(play::clientbound::OpenWindow {
+            window_id,
+            window_type,
+            window_title,
+            slot_count,
+            entity_id,
+        })
+    }
+}
+
+impl Encode for play::clientbound::OpenWindow {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.encode(self.window_id)?;
+        writer.encode(&self.window_type)?;
+        writer.encode(&self.window_title)?;
+        writer.encode(self.slot_count)?;
+
+        if self.window_type == play::clientbound::OpenWindow::HORSE_WINDOW_TYPE {
+            writer.encode(self.entity_id)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Decode for play::clientbound::Maps {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let item_damage: i32 = reader.var_decode()?;
+        let scale: i8 = reader.decode()?;
+        let icons: Vec<MapIcon> = reader.decode()?;
+
+        let columns: u8 = reader.decode()?;
+        let update: Option<MapUpdate> = if columns > 0 {
+            let rows: u8 = reader.decode()?;
+            let x: u8 = reader.decode()?;
+            let z: u8 = reader.decode()?;
+            let length: usize = reader.var_decode()?;
+
+            Some(MapUpdate {
+                columns,
+                rows,
+                x,
+                z,
+                data: read_bytes(reader, length)?,
+            })
+        } else {
+            None
+        };
+
+        Ok(play::clientbound::Maps {
+            item_damage,
+            scale,
+            icons,
+            update,
+        })
+    }
+}
+
+impl Encode for play::clientbound::Maps {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.var_encode(self.item_damage)?;
+        writer.encode(self.scale)?;
+        self.icons.encode(writer)?;
+
+        let Some(update) = &self.update else {
+            return writer.encode(0u8);
+        };
+        if update.columns == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a map update must have at least one column",
+            ));
+        }
+
+        writer.encode(update.columns)?;
+        writer.encode(update.rows)?;
+        writer.encode(update.x)?;
+        writer.encode(update.z)?;
+        writer.var_encode(update.data.len())?;
+        writer.write_all(&update.data)
+    }
+}
+
+impl Decode for play::clientbound::UpdateScore {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let score_name: String = reader.decode()?;
+        let action: i8 = reader.decode()?;
+        let objective_name: String = reader.decode()?;
+        let action: UpdateScoreAction = match action {
+            0 => UpdateScoreAction::Update {
+                value: reader.var_decode()?,
+            },
+            1 => UpdateScoreAction::Remove,
+            action => return Err(DecodeError::InvalidEnumVariant(action.into())),
+        };
+
+        Ok(play::clientbound::UpdateScore {
+            score_name,
+            objective_name,
+            action,
+        })
+    }
+}
+
+impl Encode for play::clientbound::UpdateScore {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.encode(&self.score_name)?;
+        match self.action {
+            UpdateScoreAction::Update { value } => {
+                writer.encode(0i8)?;
+                writer.encode(&self.objective_name)?;
+                writer.var_encode(value)
+            }
+            UpdateScoreAction::Remove => {
+                writer.encode(1i8)?;
+                writer.encode(&self.objective_name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ussr_buf::VarDecode;
+
+    use super::*;
+    use crate::{test_utils::round_trip_packet, Packet};
+
+    #[test]
+    fn test_generated_ids() {
+        assert_eq!(handshaking::serverbound::Handshake::ID, 0x00);
+        assert_eq!(status::serverbound::PingRequest::ID, 0x01);
+        assert_eq!(login::clientbound::SetCompression::ID, 0x03);
+        assert_eq!(play::clientbound::SpawnPosition::ID, 0x05);
+        assert_eq!(play::clientbound::Disconnect::ID, 0x40);
+        assert_eq!(play::serverbound::ChatMessage::ID, 0x01);
+        assert_eq!(play::serverbound::PlayerPositionAndLook::ID, 0x06);
+        assert_eq!(play::serverbound::PluginMessage::ID, 0x17);
+
+        // The play state has no gaps, so the last IDs show that every packet is there
+        assert_eq!(play::clientbound::Particle::ID, 0x2A);
+        assert_eq!(play::clientbound::Teams::ID, 0x3E);
+        assert_eq!(play::clientbound::UpdateEntityNbt::ID, 0x49);
+        assert_eq!(play::serverbound::Animation::ID, 0x0A);
+        assert_eq!(play::serverbound::ResourcePackStatus::ID, 0x19);
+    }
+
+    #[test]
+    fn test_play_codecs() {
+        use play::{clientbound, serverbound};
+
+        use crate::v1_8_9::types::{Angle, FixedPoint, ItemStack, PackedPosition, Slot};
+
+        // The velocity is only sent for positive data
+        let object = clientbound::SpawnObject {
+            entity_id: 300,
+            kind: 2,
+            x: FixedPoint::from_f64(0.5),
+            y: FixedPoint::from_f64(64.0),
+            z: FixedPoint::from_f64(-0.5),
+            pitch: Angle(0),
+            yaw: Angle(64),
+            data: 0,
+            velocity_x: 0,
+            velocity_y: 0,
+            velocity_z: 0,
+        };
+        assert_eq!(round_trip_packet(object).len(), 1 + 2 + 1 + 12 + 2 + 4);
+        round_trip_packet(clientbound::SpawnObject {
+            data: 1,
+            velocity_x: -100,
+            velocity_y: 200,
+            velocity_z: 0,
+            ..object
+        });
+
+        round_trip_packet(clientbound::Particle {
+            particle_id: 36,
+            long_distance: false,
+            x: 1.0,
+            y: 2.0,
+            z: 3.0,
+            offset_x: 0.0,
+            offset_y: 0.5,
+            offset_z: 0.0,
+            speed: 0.1,
+            count: 8,
+            arguments: vec![276, 0],
+        });
+        let mut buf: Vec<u8> = Vec::new();
+        assert!(play::clientbound::PlayPackets::from(clientbound::Particle {
+            particle_id: 0,
+            long_distance: false,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            offset_z: 0.0,
+            speed: 0.0,
+            count: 1,
+            arguments: vec![1],
+        })
+        .encode(&mut buf)
+        .is_err());
+
+        let horse = clientbound::OpenWindow {
+            window_id: 1,
+            window_type: clientbound::OpenWindow::HORSE_WINDOW_TYPE.to_owned(),
+            window_title: "Horse".into(),
+            slot_count: 2,
+            entity_id: 42,
+        };
+        round_trip_packet(horse.clone());
+        round_trip_packet(clientbound::OpenWindow {
+            window_type: "minecraft:chest".to_owned(),
+            entity_id: 0,
+            ..horse
+        });
+
+        round_trip_packet(clientbound::Maps {
+            item_damage: 0,
+            scale: 2,
+            icons: vec![MapIcon {
+                direction: 8,
+                kind: 0,
+                x: 10,
+                z: -10,
+            }],
+            update: None,
+        });
+        round_trip_packet(clientbound::Maps {
+            item_damage: 3,
+            scale: 0,
+            icons: Vec::new(),
+            update: Some(MapUpdate {
+                columns: 2,
+                rows: 1,
+                x: 64,
+                z: 64,
+                data: vec![34, 35],
+            }),
+        });
+
+        assert_eq!(
+            round_trip_packet(clientbound::UpdateScore {
+                score_name: "Notch".to_owned(),
+                objective_name: "Kills".to_owned(),
+                action: UpdateScoreAction::Remove,
+            }),
+            [0x3C, 5, b'N', b'o', b't', b'c', b'h', 1, 5, b'K', b'i', b'l', b'l', b's']
+        );
+        round_trip_packet(clientbound::UpdateScore {
+            score_name: "Notch".to_owned(),
+            objective_name: "Kills".to_owned(),
+            action: UpdateScoreAction::Update { value: 300 },
+        });
+
+        round_trip_packet(clientbound::DestroyEntities {
+            entity_ids: vec![1, 300, -1],
+        });
+        round_trip_packet(clientbound::UpdateEntityNbt {
+            entity_id: 7,
+            nbt: None,
+        });
+
+        round_trip_packet(serverbound::Animation {});
+        round_trip_packet(serverbound::PlayerBlockPlacement {
+            location: PackedPosition::new(10, 63, -10),
+            face: 1,
+            held_item: Slot::from(ItemStack {
+                id: 1,
+                count: 64,
+                damage: 0,
+                nbt: None,
+            }),
+            cursor_x: 8,
+            cursor_y: 16,
+            cursor_z: 0,
+        });
+        assert_eq!(
+            round_trip_packet(serverbound::TabComplete {
+                text: "/tp ".to_owned(),
+                looked_at_block: None,
+            }),
+            [0x14, 4, b'/', b't', b'p', b' ', 0]
+        );
+    }
+
+    #[test]
+    fn test_generated_codecs() {
+        let packet: login::clientbound::LoginPackets = login::clientbound::EncryptionRequest {
+            server_id: String::new(),
+            public_key: vec![1, 2, 3],
+            verify_token: vec![4, 5, 6, 7],
+        }
+        .into();
+
+        let mut buf: Vec<u8> = Vec::new();
+        packet.encode(&mut buf).unwrap();
+        assert_eq!(buf, [0x01, 0x00, 0x03, 1, 2, 3, 0x04, 4, 5, 6, 7]);
+
+        let mut reader: &[u8] = &buf;
+        let id: u32 = u32::var_decode(&mut reader).unwrap();
+        assert_eq!(
+            login::clientbound::LoginPackets::decode(id, &mut reader).unwrap(),
+            packet
+        );
+    }
+}
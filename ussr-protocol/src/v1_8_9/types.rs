@@ -0,0 +1,957 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+use ussr_buf::{
+    decode_array, decode_string, Decode, DecodeError, DecodeExt, Encode, EncodeExt, VarDecode,
+    MAX_STRING_LENGTH,
+};
+use ussr_nbt::owned::Nbt;
+use uuid::Uuid;
+
+pub use crate::common::{
+    Angle, AttributeModifier, ByteFixedPoint, ExplosionRecord, FixedPoint, ItemStack,
+    PackedPosition, Position, Statistic,
+};
+use crate::{
+    common::{self, MetadataValueCodec},
+    TextComponent,
+};
+
+/// Reads the rest of the packet.
+/// 1.8 no longer prefixes plugin message data with its length.
+pub fn decode_remaining(reader: &mut impl Read) -> Result<Vec<u8>, DecodeError> {
+    let mut bytes: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub fn encode_remaining(bytes: &[u8], writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(bytes)
+}
+
+/// Decodes an uncompressed NBT compound, where a single `TAG_End` means there is no NBT.
+pub fn decode_optional_nbt(reader: &mut impl Read) -> Result<Option<Nbt>, DecodeError> {
+    let tag: u8 = reader.read_u8()?;
+    if tag == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(Nbt::read(&mut (&[tag][..]).chain(reader))?))
+}
+
+/// Encodes an uncompressed NBT compound, `None` is encoded as a single `TAG_End`.
+pub fn encode_optional_nbt(nbt: &Option<Nbt>, writer: &mut impl Write) -> io::Result<()> {
+    match nbt {
+        Some(nbt) => nbt.write(writer),
+        None => writer.write_u8(0),
+    }
+}
+
+/// An inventory slot, which might be empty.
+///
+/// An empty slot is encoded as an item id of `-1`.
+/// Otherwise, the id is followed by the count, the damage and the NBT, which is no longer gzipped since 1.8.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Slot(pub Option<ItemStack>);
+
+impl Slot {
+    pub const EMPTY: Slot = Slot(None);
+
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+impl From<ItemStack> for Slot {
+    fn from(item: ItemStack) -> Self {
+        Slot(Some(item))
+    }
+}
+
+impl Decode for Slot {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let id: i16 = reader.read_i16::<BE>()?;
+        if id < 0 {
+            return Ok(Slot::EMPTY);
+        }
+
+        Ok(Slot(Some(ItemStack {
+            id,
+            count: reader.read_u8()?,
+            damage: reader.read_i16::<BE>()?,
+            nbt: decode_optional_nbt(reader)?,
+        })))
+    }
+}
+
+impl Encode for Slot {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        let Some(item) = &self.0 else {
+            return writer.write_i16::<BE>(-1);
+        };
+
+        writer.write_i16::<BE>(item.id)?;
+        writer.write_u8(item.count)?;
+        writer.write_i16::<BE>(item.damage)?;
+        encode_optional_nbt(&item.nbt, writer)
+    }
+}
+
+/// A single value in [`EntityMetadata`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataValue {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Float(f32),
+    String(String),
+    Slot(Slot),
+    Position(Position),
+    /// The pose of an armor stand part in degrees.
+    Rotation {
+        x: f32,
+        y: f32,
+        z: f32,
+    },
+}
+
+impl MetadataValue {
+    /// The type id of the value, which is stored in the upper 3 bits of the entry header.
+    pub const fn type_id(&self) -> u8 {
+        match self {
+            MetadataValue::Byte(_) => 0,
+            MetadataValue::Short(_) => 1,
+            MetadataValue::Int(_) => 2,
+            MetadataValue::Float(_) => 3,
+            MetadataValue::String(_) => 4,
+            MetadataValue::Slot(_) => 5,
+            MetadataValue::Position(_) => 6,
+            MetadataValue::Rotation { .. } => 7,
+        }
+    }
+}
+
+impl MetadataValueCodec for MetadataValue {
+    fn type_id(&self) -> u8 {
+        MetadataValue::type_id(self)
+    }
+
+    fn decode_value(type_id: u8, reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(match type_id {
+            0 => MetadataValue::Byte(reader.read_i8()?),
+            1 => MetadataValue::Short(reader.read_i16::<BE>()?),
+            2 => MetadataValue::Int(reader.read_i32::<BE>()?),
+            3 => MetadataValue::Float(reader.read_f32::<BE>()?),
+            4 => MetadataValue::String(decode_string(reader, MAX_STRING_LENGTH)?),
+            5 => MetadataValue::Slot(Slot::decode(reader)?),
+            6 => MetadataValue::Position(Position::decode(reader)?),
+            7 => MetadataValue::Rotation {
+                x: reader.read_f32::<BE>()?,
+                y: reader.read_f32::<BE>()?,
+                z: reader.read_f32::<BE>()?,
+            },
+            type_id => return Err(DecodeError::InvalidEnumVariant(type_id.into())),
+        })
+    }
+
+    fn encode_value(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            MetadataValue::Byte(value) => writer.write_i8(*value),
+            MetadataValue::Short(value) => writer.write_i16::<BE>(*value),
+            MetadataValue::Int(value) => writer.write_i32::<BE>(*value),
+            MetadataValue::Float(value) => writer.write_f32::<BE>(*value),
+            MetadataValue::String(value) => value.encode(writer),
+            MetadataValue::Slot(value) => value.encode(writer),
+            MetadataValue::Position(value) => value.encode(writer),
+            MetadataValue::Rotation { x, y, z } => {
+                writer.write_f32::<BE>(*x)?;
+                writer.write_f32::<BE>(*y)?;
+                writer.write_f32::<BE>(*z)
+            }
+        }
+    }
+}
+
+/// Entity metadata with the value types of 1.8.
+pub type EntityMetadata = common::EntityMetadata<MetadataValue>;
+
+impl EntityMetadata {
+    pub fn with_byte(self, index: u8, value: i8) -> EntityMetadata {
+        self.with(index, MetadataValue::Byte(value))
+    }
+
+    pub fn with_short(self, index: u8, value: i16) -> EntityMetadata {
+        self.with(index, MetadataValue::Short(value))
+    }
+
+    pub fn with_int(self, index: u8, value: i32) -> EntityMetadata {
+        self.with(index, MetadataValue::Int(value))
+    }
+
+    pub fn with_float(self, index: u8, value: f32) -> EntityMetadata {
+        self.with(index, MetadataValue::Float(value))
+    }
+
+    pub fn with_string(self, index: u8, value: impl Into<String>) -> EntityMetadata {
+        self.with(index, MetadataValue::String(value.into()))
+    }
+
+    pub fn with_slot(self, index: u8, value: impl Into<Slot>) -> EntityMetadata {
+        self.with(index, MetadataValue::Slot(value.into()))
+    }
+
+    pub fn with_position(self, index: u8, value: Position) -> EntityMetadata {
+        self.with(index, MetadataValue::Position(value))
+    }
+
+    pub fn with_rotation(self, index: u8, x: f32, y: f32, z: f32) -> EntityMetadata {
+        self.with(index, MetadataValue::Rotation { x, y, z })
+    }
+}
+
+/// A single block change in a multi block change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BlockChangeRecord {
+    /// Relative to the chunk, 0-15.
+    pub x: u8,
+    /// Relative to the chunk, 0-15.
+    pub z: u8,
+    pub y: u8,
+    /// The block id shifted left by 4, or'd with the block metadata.
+    pub block_state: i32,
+}
+
+impl Decode for BlockChangeRecord {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let horizontal: u8 = reader.decode()?;
+
+        Ok(BlockChangeRecord {
+            x: horizontal >> 4,
+            z: horizontal & 0xF,
+            y: reader.decode()?,
+            block_state: reader.var_decode()?,
+        })
+    }
+}
+
+impl Encode for BlockChangeRecord {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.encode((self.x & 0xF) << 4 | self.z & 0xF)?;
+        writer.encode(self.y)?;
+        writer.var_encode(self.block_state)
+    }
+}
+
+/// The position and sections of a chunk column in a map chunk bulk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Decode, Encode)]
+pub struct ChunkMeta {
+    pub chunk_x: i32,
+    pub chunk_z: i32,
+    pub primary_bit_map: u16,
+}
+
+/// An attribute of an entity in the entity properties.
+#[derive(Debug, Clone, PartialEq, Decode, Encode)]
+pub struct EntityProperty {
+    pub key: String,
+    pub value: f64,
+
+    #[array(var, i32, AttributeModifier)]
+    pub modifiers: Vec<AttributeModifier>,
+}
+
+/// A marker on a map, such as a player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MapIcon {
+    /// 0-15, in steps of 22.5 degrees.
+    pub direction: u8,
+    /// 0-15.
+    pub kind: u8,
+    pub x: i8,
+    pub z: i8,
+}
+
+impl Decode for MapIcon {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        let direction_and_kind: u8 = reader.decode()?;
+
+        Ok(MapIcon {
+            direction: direction_and_kind >> 4,
+            kind: direction_and_kind & 0xF,
+            x: reader.decode()?,
+            z: reader.decode()?,
+        })
+    }
+}
+
+impl Encode for MapIcon {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.encode((self.direction & 0xF) << 4 | self.kind & 0xF)?;
+        writer.encode(self.x)?;
+        writer.encode(self.z)
+    }
+}
+
+/// The updated area of a map.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapUpdate {
+    /// Never 0, since that means there is no update.
+    pub columns: u8,
+    pub rows: u8,
+    pub x: u8,
+    pub z: u8,
+    pub data: Vec<u8>,
+}
+
+/// A property of a game profile, such as the skin.
+#[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    pub signature: Option<String>,
+}
+
+/// A player that is added to the player list.
+#[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+pub struct PlayerListEntry {
+    pub uuid: Uuid,
+    pub name: String,
+    #[array(var, i32, ProfileProperty)]
+    pub properties: Vec<ProfileProperty>,
+    #[var]
+    pub gamemode: i32,
+    #[var]
+    pub ping: i32,
+    pub display_name: Option<TextComponent>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerListAction {
+    AddPlayers(Vec<PlayerListEntry>),
+    UpdateGamemode(Vec<(Uuid, i32)>),
+    UpdateLatency(Vec<(Uuid, i32)>),
+    UpdateDisplayName(Vec<(Uuid, Option<TextComponent>)>),
+    RemovePlayers(Vec<Uuid>),
+}
+
+/// Decodes a VarInt prefixed array of players and their var int value.
+fn decode_var_entries(reader: &mut impl Read) -> Result<Vec<(Uuid, i32)>, DecodeError> {
+    let length: usize = reader.var_decode()?;
+    decode_array(reader, length, |reader| {
+        Ok((reader.decode()?, reader.var_decode()?))
+    })
+}
+
+fn encode_var_entries(entries: &[(Uuid, i32)], writer: &mut impl Write) -> io::Result<()> {
+    writer.var_encode(entries.len())?;
+    entries.iter().try_for_each(|(uuid, value)| {
+        writer.encode(uuid)?;
+        writer.var_encode(value)
+    })
+}
+
+impl Decode for PlayerListAction {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(match i32::var_decode(reader)? {
+            0 => PlayerListAction::AddPlayers(reader.decode()?),
+            1 => PlayerListAction::UpdateGamemode(decode_var_entries(reader)?),
+            2 => PlayerListAction::UpdateLatency(decode_var_entries(reader)?),
+            3 => {
+                let length: usize = reader.var_decode()?;
+                PlayerListAction::UpdateDisplayName(decode_array(reader, length, |reader| {
+                    Ok((reader.decode()?, reader.decode()?))
+                })?)
+            }
+            4 => PlayerListAction::RemovePlayers(reader.decode()?),
+            action => return Err(DecodeError::InvalidEnumVariant(action.into())),
+        })
+    }
+}
+
+impl Encode for PlayerListAction {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            PlayerListAction::AddPlayers(players) => {
+                writer.var_encode(0)?;
+                players.encode(writer)
+            }
+            PlayerListAction::UpdateGamemode(players) => {
+                writer.var_encode(1)?;
+                encode_var_entries(players, writer)
+            }
+            PlayerListAction::UpdateLatency(players) => {
+                writer.var_encode(2)?;
+                encode_var_entries(players, writer)
+            }
+            PlayerListAction::UpdateDisplayName(players) => {
+                writer.var_encode(3)?;
+                writer.var_encode(players.len())?;
+                players.iter().try_for_each(|(uuid, display_name)| {
+                    writer.encode(uuid)?;
+                    writer.encode(display_name)
+                })
+            }
+            PlayerListAction::RemovePlayers(players) => {
+                writer.var_encode(4)?;
+                players.encode(writer)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ObjectiveAction {
+    /// `kind` is either `integer` or `hearts`.
+    Create {
+        value: String,
+        kind: String,
+    },
+    Remove,
+    Update {
+        value: String,
+        kind: String,
+    },
+}
+
+impl Decode for ObjectiveAction {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(match i8::decode(reader)? {
+            0 => ObjectiveAction::Create {
+                value: reader.decode()?,
+                kind: reader.decode()?,
+            },
+            1 => ObjectiveAction::Remove,
+            2 => ObjectiveAction::Update {
+                value: reader.decode()?,
+                kind: reader.decode()?,
+            },
+            mode => return Err(DecodeError::InvalidEnumVariant(mode.into())),
+        })
+    }
+}
+
+impl Encode for ObjectiveAction {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            ObjectiveAction::Create { value, kind } => {
+                writer.encode(0i8)?;
+                writer.encode(value)?;
+                writer.encode(kind)
+            }
+            ObjectiveAction::Remove => writer.encode(1i8),
+            ObjectiveAction::Update { value, kind } => {
+                writer.encode(2i8)?;
+                writer.encode(value)?;
+                writer.encode(kind)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateScoreAction {
+    Update { value: i32 },
+    Remove,
+}
+
+/// The properties of a team that are sent when it is created or updated.
+#[derive(Debug, Clone, PartialEq, Eq, Decode, Encode)]
+pub struct TeamInfo {
+    pub display_name: String,
+    pub prefix: String,
+    pub suffix: String,
+    /// 0x1 friendly fire, 0x2 see invisible teammates.
+    pub friendly_fire: i8,
+    /// `always`, `hideForOtherTeams`, `hideForOwnTeam` or `never`.
+    pub name_tag_visibility: String,
+    /// The same as the chat formatting codes, -1 for none.
+    pub color: i8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TeamAction {
+    Create {
+        info: TeamInfo,
+        players: Vec<String>,
+    },
+    Remove,
+    Update(TeamInfo),
+    AddPlayers(Vec<String>),
+    RemovePlayers(Vec<String>),
+}
+
+impl Decode for TeamAction {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(match i8::decode(reader)? {
+            0 => TeamAction::Create {
+                info: reader.decode()?,
+                players: reader.decode()?,
+            },
+            1 => TeamAction::Remove,
+            2 => TeamAction::Update(reader.decode()?),
+            3 => TeamAction::AddPlayers(reader.decode()?),
+            4 => TeamAction::RemovePlayers(reader.decode()?),
+            mode => return Err(DecodeError::InvalidEnumVariant(mode.into())),
+        })
+    }
+}
+
+impl Encode for TeamAction {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            TeamAction::Create { info, players } => {
+                writer.encode(0i8)?;
+                writer.encode(info)?;
+                players.encode(writer)
+            }
+            TeamAction::Remove => writer.encode(1i8),
+            TeamAction::Update(info) => {
+                writer.encode(2i8)?;
+                writer.encode(info)
+            }
+            TeamAction::AddPlayers(players) => {
+                writer.encode(3i8)?;
+                players.encode(writer)
+            }
+            TeamAction::RemovePlayers(players) => {
+                writer.encode(4i8)?;
+                players.encode(writer)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CombatEventAction {
+    EnterCombat,
+    EndCombat {
+        duration: i32,
+        entity_id: i32,
+    },
+    EntityDead {
+        player_id: i32,
+        entity_id: i32,
+        message: String,
+    },
+}
+
+impl Decode for CombatEventAction {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(match i32::var_decode(reader)? {
+            0 => CombatEventAction::EnterCombat,
+            1 => CombatEventAction::EndCombat {
+                duration: reader.var_decode()?,
+                entity_id: reader.decode()?,
+            },
+            2 => CombatEventAction::EntityDead {
+                player_id: reader.var_decode()?,
+                entity_id: reader.decode()?,
+                message: reader.decode()?,
+            },
+            event => return Err(DecodeError::InvalidEnumVariant(event.into())),
+        })
+    }
+}
+
+impl Encode for CombatEventAction {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            CombatEventAction::EnterCombat => writer.var_encode(0),
+            CombatEventAction::EndCombat {
+                duration,
+                entity_id,
+            } => {
+                writer.var_encode(1)?;
+                writer.var_encode(duration)?;
+                writer.encode(entity_id)
+            }
+            CombatEventAction::EntityDead {
+                player_id,
+                entity_id,
+                message,
+            } => {
+                writer.var_encode(2)?;
+                writer.var_encode(player_id)?;
+                writer.encode(entity_id)?;
+                writer.encode(message)
+            }
+        }
+    }
+}
+
+/// `speed` is the number of milliseconds until the new diameter is reached.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldBorderAction {
+    SetSize {
+        diameter: f64,
+    },
+    LerpSize {
+        old_diameter: f64,
+        new_diameter: f64,
+        speed: i64,
+    },
+    SetCenter {
+        x: f64,
+        z: f64,
+    },
+    Initialize {
+        x: f64,
+        z: f64,
+        old_diameter: f64,
+        new_diameter: f64,
+        speed: i64,
+        portal_teleport_boundary: i32,
+        warning_time: i32,
+        warning_blocks: i32,
+    },
+    SetWarningTime {
+        warning_time: i32,
+    },
+    SetWarningBlocks {
+        warning_blocks: i32,
+    },
+}
+
+impl Decode for WorldBorderAction {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(match i32::var_decode(reader)? {
+            0 => WorldBorderAction::SetSize {
+                diameter: reader.decode()?,
+            },
+            1 => WorldBorderAction::LerpSize {
+                old_diameter: reader.decode()?,
+                new_diameter: reader.decode()?,
+                speed: reader.var_decode()?,
+            },
+            2 => WorldBorderAction::SetCenter {
+                x: reader.decode()?,
+                z: reader.decode()?,
+            },
+            3 => WorldBorderAction::Initialize {
+                x: reader.decode()?,
+                z: reader.decode()?,
+                old_diameter: reader.decode()?,
+                new_diameter: reader.decode()?,
+                speed: reader.var_decode()?,
+                portal_teleport_boundary: reader.var_decode()?,
+                warning_time: reader.var_decode()?,
+                warning_blocks: reader.var_decode()?,
+            },
+            4 => WorldBorderAction::SetWarningTime {
+                warning_time: reader.var_decode()?,
+            },
+            5 => WorldBorderAction::SetWarningBlocks {
+                warning_blocks: reader.var_decode()?,
+            },
+            action => return Err(DecodeError::InvalidEnumVariant(action.into())),
+        })
+    }
+}
+
+impl Encode for WorldBorderAction {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        match *self {
+            WorldBorderAction::SetSize { diameter } => {
+                writer.var_encode(0)?;
+                writer.encode(diameter)
+            }
+            WorldBorderAction::LerpSize {
+                old_diameter,
+                new_diameter,
+                speed,
+            } => {
+                writer.var_encode(1)?;
+                writer.encode(old_diameter)?;
+                writer.encode(new_diameter)?;
+                writer.var_encode(speed)
+            }
+            WorldBorderAction::SetCenter { x, z } => {
+                writer.var_encode(2)?;
+                writer.encode(x)?;
+                writer.encode(z)
+            }
+            WorldBorderAction::Initialize {
+                x,
+                z,
+                old_diameter,
+                new_diameter,
+                speed,
+                portal_teleport_boundary,
+                warning_time,
+                warning_blocks,
+            } => {
+                writer.var_encode(3)?;
+                writer.encode(x)?;
+                writer.encode(z)?;
+                writer.encode(old_diameter)?;
+                writer.encode(new_diameter)?;
+                writer.var_encode(speed)?;
+                writer.var_encode(portal_teleport_boundary)?;
+                writer.var_encode(warning_time)?;
+                writer.var_encode(warning_blocks)
+            }
+            WorldBorderAction::SetWarningTime { warning_time } => {
+                writer.var_encode(4)?;
+                writer.var_encode(warning_time)
+            }
+            WorldBorderAction::SetWarningBlocks { warning_blocks } => {
+                writer.var_encode(5)?;
+                writer.var_encode(warning_blocks)
+            }
+        }
+    }
+}
+
+/// The times are in ticks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TitleAction {
+    SetTitle(TextComponent),
+    SetSubtitle(TextComponent),
+    SetTimes {
+        fade_in: i32,
+        stay: i32,
+        fade_out: i32,
+    },
+    Hide,
+    Reset,
+}
+
+impl Decode for TitleAction {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(match i32::var_decode(reader)? {
+            0 => TitleAction::SetTitle(reader.decode()?),
+            1 => TitleAction::SetSubtitle(reader.decode()?),
+            2 => TitleAction::SetTimes {
+                fade_in: reader.decode()?,
+                stay: reader.decode()?,
+                fade_out: reader.decode()?,
+            },
+            3 => TitleAction::Hide,
+            4 => TitleAction::Reset,
+            action => return Err(DecodeError::InvalidEnumVariant(action.into())),
+        })
+    }
+}
+
+impl Encode for TitleAction {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        match self {
+            TitleAction::SetTitle(title) => {
+                writer.var_encode(0)?;
+                writer.encode(title)
+            }
+            TitleAction::SetSubtitle(subtitle) => {
+                writer.var_encode(1)?;
+                writer.encode(subtitle)
+            }
+            TitleAction::SetTimes {
+                fade_in,
+                stay,
+                fade_out,
+            } => {
+                writer.var_encode(2)?;
+                writer.encode(fade_in)?;
+                writer.encode(stay)?;
+                writer.encode(fade_out)
+            }
+            TitleAction::Hide => writer.var_encode(3),
+            TitleAction::Reset => writer.var_encode(4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UseEntityAction {
+    Interact,
+    Attack,
+    /// The position is relative to the entity.
+    InteractAt {
+        x: f32,
+        y: f32,
+        z: f32,
+    },
+}
+
+impl Decode for UseEntityAction {
+    fn decode(reader: &mut impl Read) -> Result<Self, DecodeError> {
+        Ok(match i32::var_decode(reader)? {
+            0 => UseEntityAction::Interact,
+            1 => UseEntityAction::Attack,
+            2 => UseEntityAction::InteractAt {
+                x: reader.decode()?,
+                y: reader.decode()?,
+                z: reader.decode()?,
+            },
+            action => return Err(DecodeError::InvalidEnumVariant(action.into())),
+        })
+    }
+}
+
+impl Encode for UseEntityAction {
+    fn encode(&self, writer: &mut impl Write) -> io::Result<()> {
+        match *self {
+            UseEntityAction::Interact => writer.var_encode(0),
+            UseEntityAction::Attack => writer.var_encode(1),
+            UseEntityAction::InteractAt { x, y, z } => {
+                writer.var_encode(2)?;
+                writer.encode(x)?;
+                writer.encode(y)?;
+                writer.encode(z)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ussr_nbt::owned::{Compound, Tag};
+
+    use super::*;
+    use crate::test_utils::round_trip;
+
+    #[test]
+    fn test_slot() {
+        assert_eq!(round_trip(Slot::EMPTY), [0xFF, 0xFF]);
+        assert_eq!(
+            round_trip(Slot::from(ItemStack {
+                id: 1,
+                count: 64,
+                damage: 0,
+                nbt: None,
+            })),
+            [0, 1, 64, 0, 0, 0]
+        );
+
+        let buf: Vec<u8> = round_trip(Slot::from(ItemStack {
+            id: 276,
+            count: 1,
+            damage: 3,
+            nbt: Some(Nbt {
+                name: String::new(),
+                compound: Compound {
+                    tags: vec![("RepairCost".to_owned(), Tag::Int(2))],
+                },
+            }),
+        }));
+        // The NBT is a named compound that is not gzipped
+        assert_eq!(buf[5..8], [0x0A, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_entity_metadata() {
+        let metadata = EntityMetadata::new()
+            .with_byte(0, 0)
+            .with_short(1, 300)
+            .with_rotation(11, 0.0, 45.0, 0.0);
+        #[rustfmt::skip]
+        assert_eq!(
+            round_trip(metadata),
+            [
+                0x00, 0x00,
+                0x21, 0x01, 0x2C,
+                0xEB, 0, 0, 0, 0, 0x42, 0x34, 0, 0, 0, 0, 0, 0,
+                0x7F,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_records() {
+        let record = BlockChangeRecord {
+            x: 15,
+            z: 1,
+            y: 64,
+            block_state: 1 << 4,
+        };
+        assert_eq!(round_trip(record), [0xF1, 64, 0x10]);
+
+        let icon = MapIcon {
+            direction: 8,
+            kind: 1,
+            x: -5,
+            z: 5,
+        };
+        assert_eq!(round_trip(icon), [0x81, 0xFB, 0x05]);
+    }
+
+    #[test]
+    fn test_actions() {
+        let uuid = Uuid::from_u128(0x069A79F4_44E9_4726_A5BE_FCA90E38AAF5);
+        round_trip(PlayerListAction::AddPlayers(vec![PlayerListEntry {
+            uuid,
+            name: "Notch".to_owned(),
+            properties: vec![ProfileProperty {
+                name: "textures".to_owned(),
+                value: "e30=".to_owned(),
+                signature: None,
+            }],
+            gamemode: 1,
+            ping: 35,
+            display_name: Some("Notch".into()),
+        }]));
+        round_trip(PlayerListAction::UpdateGamemode(vec![(uuid, 3)]));
+        round_trip(PlayerListAction::UpdateLatency(vec![(uuid, 120)]));
+        round_trip(PlayerListAction::UpdateDisplayName(vec![(uuid, None)]));
+        assert_eq!(
+            round_trip(PlayerListAction::RemovePlayers(vec![uuid]))[..2],
+            [4, 1]
+        );
+
+        round_trip(ObjectiveAction::Create {
+            value: "Kills".to_owned(),
+            kind: "integer".to_owned(),
+        });
+        assert_eq!(round_trip(ObjectiveAction::Remove), [1]);
+
+        let info = TeamInfo {
+            display_name: "Red".to_owned(),
+            prefix: "§c".to_owned(),
+            suffix: String::new(),
+            friendly_fire: 0x1,
+            name_tag_visibility: "always".to_owned(),
+            color: 12,
+        };
+        round_trip(TeamAction::Create {
+            info: info.clone(),
+            players: vec!["Notch".to_owned(), "jeb_".to_owned()],
+        });
+        round_trip(TeamAction::Update(info));
+        round_trip(TeamAction::RemovePlayers(vec!["jeb_".to_owned()]));
+
+        assert_eq!(round_trip(CombatEventAction::EnterCombat), [0]);
+        round_trip(CombatEventAction::EntityDead {
+            player_id: 1,
+            entity_id: 2,
+            message: "Notch was slain by Zombie".to_owned(),
+        });
+
+        round_trip(WorldBorderAction::Initialize {
+            x: 0.0,
+            z: 0.0,
+            old_diameter: 60_000_000.0,
+            new_diameter: 1000.0,
+            speed: 60_000,
+            portal_teleport_boundary: 29_999_984,
+            warning_time: 15,
+            warning_blocks: 5,
+        });
+        round_trip(TitleAction::SetTimes {
+            fade_in: 10,
+            stay: 70,
+            fade_out: 20,
+        });
+        assert_eq!(
+            round_trip(UseEntityAction::InteractAt {
+                x: 0.5,
+                y: 1.0,
+                z: 0.0
+            })[0],
+            2
+        );
+
+        assert!(matches!(
+            TitleAction::decode(&mut &[5][..]),
+            Err(DecodeError::InvalidEnumVariant(5))
+        ));
+    }
+}
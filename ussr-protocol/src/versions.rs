@@ -1,11 +1,481 @@
-//! Convenience module for exporting all protocol versions and re-exporting everything from the latest enabled version.
+//! The enabled protocol versions, each in its own module, and [`ProtocolVersion`] to choose between them at runtime.
+
+use std::io::{self, Read, Write};
+
+use ussr_buf::{DecodeError, VarDecode};
+
+use crate::{neutral, Direction, PacketDecodeError, State};
+
+/// Implements `debug_packet` in the `packets` module of a version, which decodes any packet and formats it with `{:#?}`.
+macro_rules! impl_debug_packet {
+    () => {
+        pub(crate) fn debug_packet(
+            direction: crate::Direction,
+            state: crate::State,
+            id: u32,
+            reader: &mut impl std::io::Read,
+        ) -> Result<String, crate::PacketDecodeError> {
+            use crate::{Direction, State};
+
+            Ok(match (direction, state) {
+                (Direction::Clientbound, State::Handshaking) => format!(
+                    "{:#?}",
+                    handshaking::clientbound::HandshakingPackets::decode(id, reader)?
+                ),
+                (Direction::Serverbound, State::Handshaking) => format!(
+                    "{:#?}",
+                    handshaking::serverbound::HandshakingPackets::decode(id, reader)?
+                ),
+                (Direction::Clientbound, State::Status) => format!(
+                    "{:#?}",
+                    status::clientbound::StatusPackets::decode(id, reader)?
+                ),
+                (Direction::Serverbound, State::Status) => format!(
+                    "{:#?}",
+                    status::serverbound::StatusPackets::decode(id, reader)?
+                ),
+                (Direction::Clientbound, State::Login) => format!(
+                    "{:#?}",
+                    login::clientbound::LoginPackets::decode(id, reader)?
+                ),
+                (Direction::Serverbound, State::Login) => format!(
+                    "{:#?}",
+                    login::serverbound::LoginPackets::decode(id, reader)?
+                ),
+                (Direction::Clientbound, State::Play) => {
+                    format!("{:#?}", play::clientbound::PlayPackets::decode(id, reader)?)
+                }
+                (Direction::Serverbound, State::Play) => {
+                    format!("{:#?}", play::serverbound::PlayPackets::decode(id, reader)?)
+                }
+            })
+        }
+    };
+}
 
 #[cfg(feature = "v1_7_2")]
 #[path = "v1_7_2/mod.rs"]
 pub mod v1_7_2;
 
-cfg_if::cfg_if! {
-    if #[cfg(feature = "v1_7_2")] {
-        pub use v1_7_2::*;
+#[cfg(feature = "v1_8_9")]
+#[path = "v1_8_9/mod.rs"]
+pub mod v1_8_9;
+
+#[cfg(not(any(feature = "v1_7_2", feature = "v1_8_9")))]
+compile_error!("at least one protocol version must be enabled");
+
+/// A protocol version supported by the server.
+///
+/// The version of a connection is chosen by [`Handshake::protocol_version`](neutral::Serverbound::Handshake),
+/// and converts between the packets of that version and [`neutral`] packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ProtocolVersion {
+    #[cfg(feature = "v1_7_2")]
+    V1_7_2,
+    /// 1.7.6 to 1.7.10, which share the packets of [`v1_7_2`],
+    /// but send UUIDs with dashes and the properties of the profile in [`SpawnPlayer`](v1_7_2::packets::play::clientbound::SpawnPlayer).
+    #[cfg(feature = "v1_7_2")]
+    V1_7_6,
+    #[cfg(feature = "v1_8_9")]
+    V1_8_9,
+}
+
+impl ProtocolVersion {
+    /// The latest enabled version.
+    pub const LATEST: ProtocolVersion = {
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "v1_8_9")] {
+                ProtocolVersion::V1_8_9
+            } else {
+                ProtocolVersion::V1_7_2
+            }
+        }
+    };
+
+    /// Returns the version with the given protocol number, if it is supported.
+    pub const fn from_protocol_version(protocol_version: i32) -> Option<ProtocolVersion> {
+        match protocol_version {
+            #[cfg(feature = "v1_7_2")]
+            4 => Some(ProtocolVersion::V1_7_2),
+            #[cfg(feature = "v1_7_2")]
+            5 => Some(ProtocolVersion::V1_7_6),
+            #[cfg(feature = "v1_8_9")]
+            47 => Some(ProtocolVersion::V1_8_9),
+            _ => None,
+        }
+    }
+
+    /// The protocol number sent in the handshake.
+    pub const fn protocol_version(self) -> i32 {
+        match self {
+            #[cfg(feature = "v1_7_2")]
+            ProtocolVersion::V1_7_2 => 4,
+            #[cfg(feature = "v1_7_2")]
+            ProtocolVersion::V1_7_6 => 5,
+            #[cfg(feature = "v1_8_9")]
+            ProtocolVersion::V1_8_9 => 47,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "v1_7_2")]
+            ProtocolVersion::V1_7_2 => "1.7.2",
+            #[cfg(feature = "v1_7_2")]
+            ProtocolVersion::V1_7_6 => "1.7.6",
+            #[cfg(feature = "v1_8_9")]
+            ProtocolVersion::V1_8_9 => "1.8.9",
+        }
+    }
+
+    /// Whether the version has compressed frames, enabled by [`neutral::Clientbound::SetCompression`].
+    pub const fn supports_compression(self) -> bool {
+        match self {
+            #[cfg(feature = "v1_7_2")]
+            ProtocolVersion::V1_7_2 | ProtocolVersion::V1_7_6 => false,
+            #[cfg(feature = "v1_8_9")]
+            ProtocolVersion::V1_8_9 => true,
+        }
+    }
+
+    /// Decodes the body of a packet with the given ID, sent by a client in `state`.
+    /// Play packets that aren't part of the [`neutral`] model are returned as [`neutral::Serverbound::Other`].
+    pub fn decode_serverbound(
+        self,
+        state: State,
+        id: u32,
+        reader: &mut impl Read,
+    ) -> Result<neutral::Serverbound, PacketDecodeError> {
+        match self {
+            #[cfg(feature = "v1_7_2")]
+            ProtocolVersion::V1_7_2 | ProtocolVersion::V1_7_6 => {
+                v1_7_2::convert::decode_serverbound(state, id, reader)
+            }
+            #[cfg(feature = "v1_8_9")]
+            ProtocolVersion::V1_8_9 => v1_8_9::convert::decode_serverbound(state, id, reader),
+        }
+    }
+
+    /// Decodes a frame (the packet ID followed by its body) of any packet, and formats it with `{:#?}`.
+    pub fn debug_packet(
+        self,
+        direction: Direction,
+        state: State,
+        frame: &[u8],
+    ) -> Result<String, PacketDecodeError> {
+        let mut reader: &[u8] = frame;
+        let id: u32 =
+            u32::var_decode(&mut reader).map_err(|e| PacketDecodeError::decode(e, 0, 0))?;
+        let body_offset: usize = frame.len() - reader.len();
+
+        let packet: String = match self {
+            #[cfg(feature = "v1_7_2")]
+            ProtocolVersion::V1_7_2 => {
+                v1_7_2::packets::debug_packet(direction, state, id, &mut reader)?
+            }
+            #[cfg(feature = "v1_7_2")]
+            ProtocolVersion::V1_7_6 => {
+                use crate::Packet;
+                use v1_7_2::packets::play::clientbound::{PlayPackets, SpawnPlayer};
+
+                if (direction, state, id) == (Direction::Clientbound, State::Play, SpawnPlayer::ID)
+                {
+                    let packet: SpawnPlayer =
+                        SpawnPlayer::decode_v1_7_6(&mut reader).map_err(|e| {
+                            let consumed: usize = frame.len() - body_offset - reader.len();
+                            PacketDecodeError::decode(e, body_offset, consumed)
+                        })?;
+                    format!("{:#?}", PlayPackets::from(packet))
+                } else {
+                    v1_7_2::packets::debug_packet(direction, state, id, &mut reader)?
+                }
+            }
+            #[cfg(feature = "v1_8_9")]
+            ProtocolVersion::V1_8_9 => {
+                v1_8_9::packets::debug_packet(direction, state, id, &mut reader)?
+            }
+        };
+
+        if !reader.is_empty() {
+            return Err(PacketDecodeError::decode(
+                DecodeError::TrailingBytes(reader.len()),
+                body_offset,
+                frame.len() - body_offset - reader.len(),
+            ));
+        }
+        Ok(packet)
+    }
+
+    /// Encodes the ID of a packet followed by its body.
+    pub fn encode_clientbound(
+        self,
+        packet: &neutral::Clientbound,
+        writer: &mut impl Write,
+    ) -> io::Result<()> {
+        match self {
+            #[cfg(feature = "v1_7_2")]
+            ProtocolVersion::V1_7_2 | ProtocolVersion::V1_7_6 => {
+                v1_7_2::convert::encode_clientbound(self, packet, writer)
+            }
+            #[cfg(feature = "v1_8_9")]
+            ProtocolVersion::V1_8_9 => v1_8_9::convert::encode_clientbound(packet, writer),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "v1_7_2", feature = "v1_8_9"))]
+mod tests {
+    use ussr_buf::{Encode, VarDecode};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::enums::{Difficulty, Dimension, Gamemode};
+
+    fn encode(version: ProtocolVersion, packet: &neutral::Clientbound) -> Vec<u8> {
+        let mut buf: Vec<u8> = Vec::new();
+        version.encode_clientbound(packet, &mut buf).unwrap();
+        buf
+    }
+
+    fn decode(
+        version: ProtocolVersion,
+        state: State,
+        frame: &[u8],
+    ) -> Result<neutral::Serverbound, PacketDecodeError> {
+        let mut reader: &[u8] = frame;
+        let id: u32 = u32::var_decode(&mut reader).unwrap();
+        let packet = version.decode_serverbound(state, id, &mut reader);
+        assert!(reader.is_empty());
+        packet
+    }
+
+    #[test]
+    fn test_protocol_version() {
+        assert_eq!(
+            ProtocolVersion::from_protocol_version(4),
+            Some(ProtocolVersion::V1_7_2)
+        );
+        assert_eq!(
+            ProtocolVersion::from_protocol_version(47),
+            Some(ProtocolVersion::V1_8_9)
+        );
+        assert_eq!(
+            ProtocolVersion::from_protocol_version(5),
+            Some(ProtocolVersion::V1_7_6)
+        );
+        assert_eq!(ProtocolVersion::from_protocol_version(6), None);
+        assert_eq!(ProtocolVersion::LATEST.protocol_version(), 47);
+        assert_eq!(ProtocolVersion::V1_7_2.name(), "1.7.2");
+        assert_eq!(ProtocolVersion::V1_7_6.protocol_version(), 5);
+    }
+
+    #[test]
+    fn test_v1_7_6() {
+        // 1.7.6 sends UUIDs with dashes
+        let login_success = neutral::Clientbound::LoginSuccess {
+            uuid: Uuid::parse_str("069a79f4-44e9-4726-a5be-fca90e38aaf5").unwrap(),
+            username: "Notch".to_owned(),
+        };
+        let v1_7_2: Vec<u8> = encode(ProtocolVersion::V1_7_2, &login_success);
+        assert_eq!(v1_7_2[1..34], *b"\x20069a79f444e94726a5befca90e38aaf5");
+        let v1_7_6: Vec<u8> = encode(ProtocolVersion::V1_7_6, &login_success);
+        assert_eq!(v1_7_6[1..38], *b"\x24069a79f4-44e9-4726-a5be-fca90e38aaf5");
+        assert_eq!(encode(ProtocolVersion::V1_8_9, &login_success), v1_7_6);
+
+        // and the properties of the profile when spawning a player
+        let packet = v1_7_2::packets::play::clientbound::SpawnPlayer {
+            entity_id: 1,
+            uuid: "069a79f4-44e9-4726-a5be-fca90e38aaf5".to_owned(),
+            name: "Notch".to_owned(),
+            properties: vec![v1_7_2::types::ProfileProperty {
+                name: "textures".to_owned(),
+                value: "e30=".to_owned(),
+                signature: String::new(),
+            }],
+            x: Default::default(),
+            y: Default::default(),
+            z: Default::default(),
+            yaw: Default::default(),
+            pitch: Default::default(),
+            current_item: 0,
+            metadata: Default::default(),
+        };
+        let spawn_player = neutral::Clientbound::Other(neutral::OtherClientbound::V1_7_2(
+            Box::new(packet.clone().into()),
+        ));
+        let v1_7_2: Vec<u8> = encode(ProtocolVersion::V1_7_2, &spawn_player);
+        let v1_7_6: Vec<u8> = encode(ProtocolVersion::V1_7_6, &spawn_player);
+        assert_eq!(v1_7_6.len(), v1_7_2.len() + 16);
+        assert_eq!(
+            v1_7_6[45..61],
+            [
+                0x01, 0x08, b't', b'e', b'x', b't', b'u', b'r', b'e', b's', 0x04, b'e', b'3', b'0',
+                b'=', 0x00
+            ]
+        );
+
+        for (version, frame) in [
+            (ProtocolVersion::V1_7_2, v1_7_2),
+            (ProtocolVersion::V1_7_6, v1_7_6),
+        ] {
+            let expected = v1_7_2::packets::play::clientbound::SpawnPlayer {
+                properties: match version {
+                    ProtocolVersion::V1_7_2 => Vec::new(),
+                    _ => packet.properties.clone(),
+                },
+                ..packet.clone()
+            };
+            assert_eq!(
+                version
+                    .debug_packet(Direction::Clientbound, State::Play, &frame)
+                    .unwrap(),
+                format!(
+                    "{:#?}",
+                    v1_7_2::packets::play::clientbound::PlayPackets::from(expected)
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_serverbound() {
+        let mut v1_7_2: Vec<u8> = vec![0x04];
+        for value in [1.5f64, 64.0, 65.62, -2.5] {
+            value.encode(&mut v1_7_2).unwrap();
+        }
+        true.encode(&mut v1_7_2).unwrap();
+
+        let mut v1_8_9: Vec<u8> = vec![0x04];
+        for value in [1.5f64, 64.0, -2.5] {
+            value.encode(&mut v1_8_9).unwrap();
+        }
+        true.encode(&mut v1_8_9).unwrap();
+
+        let position = neutral::Serverbound::PlayerPosition {
+            x: 1.5,
+            y: 64.0,
+            z: -2.5,
+            on_ground: true,
+        };
+        assert_eq!(
+            decode(ProtocolVersion::V1_7_2, State::Play, &v1_7_2).unwrap(),
+            position
+        );
+        assert_eq!(
+            decode(ProtocolVersion::V1_8_9, State::Play, &v1_8_9).unwrap(),
+            position
+        );
+
+        // 1.7 sends the chat mode and colours in separate fields, like 1.8
+        #[rustfmt::skip]
+        let client_settings: &[u8] = &[
+            0x15,
+            0x05, b'e', b'n', b'_', b'U', b'S',
+            0x00, // View distance
+            0x02, // Hidden chat
+            0x01, // Colours
+            0x02, // Normal difficulty
+            0x01, // Show cape
+        ];
+        assert_eq!(
+            decode(ProtocolVersion::V1_7_2, State::Play, client_settings).unwrap(),
+            neutral::Serverbound::ClientSettings {
+                locale: "en_US".to_owned(),
+                view_distance: 0,
+                chat_mode: 2,
+                chat_colours: true,
+                show_cape: true,
+            }
+        );
+
+        // Packets outside of the neutral model are passed on as the packet of their version, unknown packets are errors
+        assert_eq!(
+            decode(ProtocolVersion::V1_7_2, State::Play, &[0x0D, 0x00]).unwrap(),
+            neutral::Serverbound::Other(neutral::OtherServerbound::V1_7_2(Box::new(
+                v1_7_2::packets::play::serverbound::CloseWindow { window_id: 0 }.into()
+            )))
+        );
+        assert_eq!(
+            decode(ProtocolVersion::V1_8_9, State::Play, &[0x0A]).unwrap(),
+            neutral::Serverbound::Other(neutral::OtherServerbound::V1_8_9(Box::new(
+                v1_8_9::packets::play::serverbound::Animation {}.into()
+            )))
+        );
+        assert!(matches!(
+            decode(ProtocolVersion::V1_8_9, State::Play, &[0x1A]),
+            Err(PacketDecodeError::UnknownPacketId {
+                packet_id: 0x1A,
+                state: State::Play,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_encode_clientbound() {
+        let join_game = neutral::Clientbound::JoinGame {
+            entity_id: 1,
+            hardcore: false,
+            gamemode: Gamemode::Survival,
+            dimension: Dimension::Overworld,
+            difficulty: Difficulty::Normal,
+            max_players: 20,
+            level_type: "default".to_owned(),
+            reduced_debug_info: true,
+        };
+        let v1_7_2: Vec<u8> = encode(ProtocolVersion::V1_7_2, &join_game);
+        let v1_8_9: Vec<u8> = encode(ProtocolVersion::V1_8_9, &join_game);
+        assert_eq!(v1_8_9[..v1_7_2.len()], v1_7_2);
+        assert_eq!(v1_8_9[v1_7_2.len()..], [0x01]);
+
+        // 1.7 uses a different packet ID and a length prefix for plugin messages
+        let plugin_message = neutral::Clientbound::PluginMessage {
+            channel: "MC|Brand".to_owned(),
+            data: vec![0x04, b'u', b's', b's', b'r'],
+        };
+        assert_eq!(
+            encode(ProtocolVersion::V1_7_2, &plugin_message)[..12],
+            [0x3F, 0x08, b'M', b'C', b'|', b'B', b'r', b'a', b'n', b'd', 0x00, 0x05]
+        );
+        assert_eq!(
+            encode(ProtocolVersion::V1_8_9, &plugin_message)[10..],
+            [0x04, b'u', b's', b's', b'r']
+        );
+
+        // 1.7 sends the position of the eyes
+        let position = neutral::Clientbound::PlayerPositionAndLook {
+            x: 0.0,
+            y: 64.0,
+            z: 0.0,
+            yaw: 0.0,
+            pitch: 0.0,
+        };
+        let v1_7_2: Vec<u8> = encode(ProtocolVersion::V1_7_2, &position);
+        assert_eq!(v1_7_2[9..17], 65.62f64.to_be_bytes());
+        let v1_8_9: Vec<u8> = encode(ProtocolVersion::V1_8_9, &position);
+        assert_eq!(v1_8_9[9..17], 64.0f64.to_be_bytes());
+
+        // Compression was added in 1.8
+        let set_compression = neutral::Clientbound::SetCompression { threshold: 256 };
+        assert_eq!(
+            encode(ProtocolVersion::V1_8_9, &set_compression),
+            [0x03, 0x80, 0x02]
+        );
+        assert!(!ProtocolVersion::V1_7_2.supports_compression());
+        assert!(ProtocolVersion::V1_7_2
+            .encode_clientbound(&set_compression, &mut Vec::new())
+            .is_err());
+
+        // Packets of a version can only be sent to connections of that version
+        let camera = neutral::Clientbound::Other(neutral::OtherClientbound::V1_8_9(Box::new(
+            v1_8_9::packets::play::clientbound::Camera { camera_id: 1 }.into(),
+        )));
+        assert_eq!(encode(ProtocolVersion::V1_8_9, &camera), [0x43, 0x01]);
+        assert_eq!(
+            ProtocolVersion::V1_7_2
+                .encode_clientbound(&camera, &mut Vec::new())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::Unsupported
+        );
     }
 }
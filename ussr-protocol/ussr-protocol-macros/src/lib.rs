@@ -1,8 +1,15 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{parse, Error, Ident, Item, ItemMod, Result};
+use syn::{parse, Error, Expr, Ident, Item, ItemMod, LitInt, Result};
 
+/// Generates packet IDs and an enum of all packets in a mod.
+///
+/// Every struct in the mod is a packet. Its ID is either set with `#[id(0x00)]`,
+/// or is one more than the ID of the previous struct (starting at `0x00`).
+/// The direction of the packets is the name of the mod, either `clientbound` or `serverbound`.
+/// Each struct implements `Packet` and the mod gets a `{State}Packets` enum,
+/// which can decode a packet by its ID and encode a packet together with its ID.
 #[proc_macro_attribute]
 pub fn packets(attr: TokenStream, input: TokenStream) -> TokenStream {
     match try_expand(attr, input) {
@@ -24,30 +31,161 @@ fn try_expand(attr: TokenStream, input: TokenStream) -> Result<TokenStream2> {
         content,
         ..
     } = parse(input).map_err(|_| Error::new_spanned(&state, "#[packets] must be used on a mod"))?;
-    let Some((_, items)) = content else {
+    let Some((_, mut items)) = content else {
         return Err(Error::new_spanned(
             &state,
             "#[packets] must be used on a mod with items",
         ));
     };
 
+    let direction = match ident.to_string().as_str() {
+        "clientbound" => quote! { crate::Clientbound },
+        "serverbound" => quote! { crate::Serverbound },
+        _ => {
+            return Err(Error::new_spanned(
+                &ident,
+                "#[packets] must be used on a mod named `clientbound` or `serverbound`",
+            ))
+        }
+    };
+
+    let mut packet_names: Vec<Ident> = Vec::new();
+    let mut packet_ids: Vec<u32> = Vec::new();
+    let mut next_id: u32 = 0;
+    for item in &mut items {
+        let Item::Struct(s) = item else {
+            continue;
+        };
+
+        let mut id: u32 = next_id;
+        let mut explicit = false;
+        let mut result: Result<()> = Ok(());
+        s.attrs.retain(|attr| {
+            if !attr.path().is_ident("id") {
+                return true;
+            }
+
+            if explicit {
+                result = Err(Error::new_spanned(attr, "duplicate #[id] attribute"));
+            }
+            explicit = true;
+
+            match attr
+                .parse_args::<LitInt>()
+                .and_then(|lit| lit.base10_parse())
+            {
+                Ok(value) => id = value,
+                Err(err) => result = Err(err),
+            }
+            false
+        });
+        result?;
+
+        if let Some(index) = packet_ids.iter().position(|&other| other == id) {
+            return Err(Error::new_spanned(
+                &s.ident,
+                format!(
+                    "packet id {id:#04x} is already used by {}",
+                    packet_names[index]
+                ),
+            ));
+        }
+
+        packet_names.push(s.ident.clone());
+        packet_ids.push(id);
+        next_id = id + 1;
+    }
+
     let enum_name = Ident::new(&format!("{}Packets", state), state.span());
-    let packet_names = items
+    let ids = packet_ids
         .iter()
-        .filter_map(|item| match item {
-            Item::Struct(s) => Some(&s.ident),
-            _ => None,
-        })
+        .map(|id| syn::parse_str::<Expr>(&format!("{id:#04x}")).unwrap())
         .collect::<Vec<_>>();
 
-    let packets_enum = if packet_names.len() > 1 {
+    let unknown_id = quote! {
+        Err(crate::PacketDecodeError::UnknownPacketId {
+            packet_id: id,
+            state: crate::State::#state,
+        })
+    };
+    let decode_body = if packet_names.is_empty() {
         quote! {
-            pub enum #enum_name {
-                #( #packet_names(#packet_names) ),*
-            }
+            let _ = reader;
+            #unknown_id
         }
     } else {
-        quote! {}
+        quote! {
+            let mut reader = ussr_buf::decode::CountingReader::new(reader);
+            let result = match id {
+                #(
+                    #ids => <#packet_names as ussr_buf::Decode>::decode(&mut reader)
+                        .map(#enum_name::#packet_names),
+                )*
+                _ => return #unknown_id,
+            };
+
+            result.map_err(|e| {
+                crate::PacketDecodeError::decode(e, ussr_buf::var_int_len(id), reader.count())
+            })
+        }
+    };
+
+    let packets_enum = quote! {
+        #[derive(Debug, Clone, PartialEq)]
+        pub enum #enum_name {
+            #( #packet_names(#packet_names) ),*
+        }
+
+        impl #enum_name {
+            /// Decodes the body of a packet with the given ID.
+            /// The ID must already have been read from `reader`.
+            pub fn decode(
+                id: u32,
+                reader: &mut impl std::io::Read,
+            ) -> std::result::Result<Self, crate::PacketDecodeError> {
+                #decode_body
+            }
+
+            pub const STATE: crate::State = crate::State::#state;
+
+            /// Returns the ID of the packet.
+            pub fn id(&self) -> u32 {
+                match *self {
+                    #( #enum_name::#packet_names(_) => <#packet_names as crate::Packet>::ID, )*
+                }
+            }
+
+            /// Returns the name of the packet.
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    #( #enum_name::#packet_names(_) => <#packet_names as crate::Packet>::NAME, )*
+                }
+            }
+
+            /// Encodes the ID of the packet followed by its body.
+            pub fn encode(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+                ussr_buf::VarEncode::var_encode(&self.id(), writer)?;
+                match *self {
+                    #( #enum_name::#packet_names(ref packet) => ussr_buf::Encode::encode(packet, writer), )*
+                }
+            }
+        }
+
+        #(
+            impl crate::Packet for #packet_names {
+                const ID: u32 = #ids;
+                const STATE: crate::State = crate::State::#state;
+                const NAME: &'static str = stringify!(#packet_names);
+
+                type Direction = #direction;
+            }
+
+            impl From<#packet_names> for #enum_name {
+                fn from(packet: #packet_names) -> Self {
+                    #enum_name::#packet_names(packet)
+                }
+            }
+        )*
     };
 
     Ok(quote! {
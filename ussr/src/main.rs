@@ -11,18 +11,18 @@
 
 mod init_tracing;
 
-use bevy_app::{App, PostStartup, ScheduleRunnerPlugin};
+use bevy_app::{App, AppExit, PostStartup, ScheduleRunnerPlugin};
 use tracing::{info, instrument};
 use ussr_net::UssrNetPlugin;
 
-fn main() {
+fn main() -> AppExit {
     init_tracing::init();
 
     App::new()
         .add_plugins(ScheduleRunnerPlugin::default())
-        .add_plugins(UssrNetPlugin)
+        .add_plugins(UssrNetPlugin::default())
         .add_systems(PostStartup, post_startup)
-        .run();
+        .run()
 }
 
 #[instrument(skip_all, level = "trace")]